@@ -0,0 +1,191 @@
+use clvmr::{Allocator, NodePtr, SExp};
+
+/// Renders a program as Chialisp-style text, with operators in head position named by their mnemonic.
+pub fn disassemble(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, false, true, &mut output);
+    output
+}
+
+/// Renders a value, such as the output of a program, as Chialisp-style text. Unlike a program,
+/// its atoms are never named as operators.
+pub fn format_value(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, false, false, &mut output);
+    output
+}
+
+fn write_node(
+    allocator: &Allocator,
+    node: NodePtr,
+    head: bool,
+    mnemonics: bool,
+    output: &mut String,
+) {
+    match allocator.sexp(node) {
+        SExp::Atom => {
+            let atom = allocator.atom(node);
+            let bytes = atom.as_ref();
+
+            if head && mnemonics && bytes.len() == 1 {
+                if let Some(name) = opcode_name(bytes[0]) {
+                    output.push_str(name);
+                    return;
+                }
+            }
+
+            write_atom(allocator, node, bytes, output);
+        }
+        SExp::Pair(first, rest) => {
+            output.push('(');
+            write_node(allocator, first, true, mnemonics, output);
+
+            let mut rest = rest;
+
+            loop {
+                match allocator.sexp(rest) {
+                    SExp::Pair(first, next) => {
+                        output.push(' ');
+                        write_node(allocator, first, false, mnemonics, output);
+                        rest = next;
+                    }
+                    SExp::Atom => {
+                        if !allocator.atom(rest).as_ref().is_empty() {
+                            output.push_str(" . ");
+                            write_node(allocator, rest, false, mnemonics, output);
+                        }
+                        break;
+                    }
+                }
+            }
+
+            output.push(')');
+        }
+    }
+}
+
+fn write_atom(allocator: &Allocator, node: NodePtr, bytes: &[u8], output: &mut String) {
+    if bytes.is_empty() {
+        output.push_str("()");
+    } else if bytes.len() > 2
+        && bytes
+            .iter()
+            .all(|&byte| (0x20..0x7f).contains(&byte) && byte != b'"')
+    {
+        output.push('"');
+        output.push_str(std::str::from_utf8(bytes).unwrap());
+        output.push('"');
+    } else if bytes.len() <= 4 && is_canonical_int(bytes) {
+        output.push_str(&allocator.number(node).to_string());
+    } else {
+        output.push_str("0x");
+        output.push_str(&hex::encode(bytes));
+    }
+}
+
+/// Whether the atom is the minimal encoding of its integer value.
+fn is_canonical_int(bytes: &[u8]) -> bool {
+    match bytes {
+        [0x00, next, ..] => next & 0x80 != 0,
+        [0xff, next, ..] => next & 0x80 == 0,
+        _ => true,
+    }
+}
+
+fn opcode_name(opcode: u8) -> Option<&'static str> {
+    Some(match opcode {
+        1 => "q",
+        2 => "a",
+        3 => "i",
+        4 => "c",
+        5 => "f",
+        6 => "r",
+        7 => "l",
+        8 => "x",
+        9 => "=",
+        10 => ">s",
+        11 => "sha256",
+        12 => "substr",
+        13 => "strlen",
+        14 => "concat",
+        16 => "+",
+        17 => "-",
+        18 => "*",
+        19 => "/",
+        20 => "divmod",
+        21 => ">",
+        22 => "ash",
+        23 => "lsh",
+        24 => "logand",
+        25 => "logior",
+        26 => "logxor",
+        27 => "lognot",
+        29 => "point_add",
+        30 => "pubkey_for_exp",
+        32 => "not",
+        33 => "any",
+        34 => "all",
+        36 => "softfork",
+        62 => "keccak256",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rue_compiler::{compile, CompilerOptions, Target};
+    use rue_parser::parse;
+
+    use super::*;
+
+    fn check(source: &str, expected: &str) {
+        check_with(source, &CompilerOptions::default(), expected);
+    }
+
+    fn check_with(source: &str, options: &CompilerOptions, expected: &str) {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, options);
+        assert!(output.diagnostics().is_empty());
+
+        assert_eq!(disassemble(&allocator, output.node_ptr()), expected);
+    }
+
+    #[test]
+    fn test_operator_names() {
+        check(
+            "fun main(a: Int, b: Int) -> Int { if a > 3 { a * 2 - b } else { a + b / 3 } }",
+            "(a (q 2 (i (> 2 (q . 3)) (q 17 (* 2 (q . 2)) 5) (q 16 2 (/ 5 (q . 3)))) 1) 1)",
+        );
+    }
+
+    #[test]
+    fn test_atoms() {
+        check(
+            "fun main() -> Bytes { sha256(\"hello\") }",
+            "(a (q 2 2 (c (q . \"hello\") ())) (c (q 11 2) 1))",
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let mut options = CompilerOptions::default();
+        options.set_target(Target::Keccak);
+
+        check_with(
+            "fun main() -> Bytes { keccak256(\"abc\") }",
+            &options,
+            "(a (q 2 2 (c (q . \"abc\") ())) (c (q 62 2) 1))",
+        );
+    }
+
+    #[test]
+    fn test_const_digest() {
+        check(
+            "const HASH: Bytes32 = sha256(\"abc\"); fun main() -> Bytes32 { HASH }",
+            "(a (q 1 . 0xba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad) 1)",
+        );
+    }
+}
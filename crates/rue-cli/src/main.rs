@@ -1,58 +1,1457 @@
-use std::fs;
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+};
 
-use clap::Parser;
-use clvmr::{run_program, serde::node_to_bytes, Allocator, ChiaDialect, NodePtr};
-use rue_compiler::compile;
-use rue_parser::{line_col, parse, LineCol};
+use clap::{CommandFactory, Parser, Subcommand};
+use clvm_utils::tree_hash;
+use clvmr::{
+    reduction::EvalErr,
+    run_program,
+    serde::{node_from_bytes, node_to_bytes},
+    Allocator, ChiaDialect, NodePtr, SExp,
+};
+use rue_compiler::{
+    compile, compile_tests, explain, CompilerOptions, CurriedValue, Diagnostic, DiagnosticKind,
+    Lowering, Target, BUILTINS, DEFAULT_MAX_DEPTH,
+};
+use rue_parser::{line_col, parse, parse_bytes, AstNode, LineCol};
+
+use crate::{
+    disassemble::{disassemble, format_value},
+    project::Project,
+    snippet::snippet,
+};
+
+mod disassemble;
+mod project;
+mod snippet;
 
 /// The Rue language compiler and toolchain.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The source file to compile.
-    file: String,
+    file: Option<String>,
+
+    /// Enables items marked with `@cfg(NAME)`.
+    #[arg(short = 'D', long = "define", value_name = "NAME", global = true)]
+    defines: Vec<String>,
+
+    /// Checks each function's `where` clause at runtime, for calls with non-constant arguments.
+    #[arg(long, global = true)]
+    runtime_guards: bool,
+
+    /// Lowers `if` chains which compare a value against integers to a jump table.
+    #[arg(long, global = true)]
+    jump_tables: bool,
+
+    /// Limits how deeply nested a value which is evaluated at compile time can be, such as a chain
+    /// of constants which each refer to the last.
+    #[arg(long, value_name = "DEPTH", default_value_t = DEFAULT_MAX_DEPTH, global = true)]
+    max_depth: usize,
+
+    /// Curries constants into the leading parameters of `main`, given as hex such as `0xcafe`
+    /// for bytes or as decimal integers.
+    #[arg(long, value_name = "VALUE", num_args = 1.., allow_hyphen_values = true, global = true)]
+    curry: Vec<String>,
+
+    /// Prints an intermediate representation instead of the compiled program, where `ir-json` is
+    /// the optimized LIR as JSON.
+    #[arg(long, value_name = "KIND", value_parser = ["ir-json"])]
+    emit: Option<String>,
+
+    /// Prints the compiled program as Chialisp-style text instead of hex.
+    #[arg(long)]
+    pretty: bool,
+
+    /// Prints a rough estimate of the program's cost, based on the operators it contains.
+    #[arg(long)]
+    estimate_cost: bool,
+
+    /// Prints every resolved symbol with its kind, type, and captures, nested by scope.
+    #[arg(long)]
+    dump_symbols: bool,
+
+    /// Prints the tree hash of the compiled program, which identifies it on-chain.
+    #[arg(long)]
+    puzzle_hash: bool,
+
+    /// Fails unless the tree hash of the compiled program is this one, as hex, which guards
+    /// against accidentally changing a puzzle that's already on-chain.
+    #[arg(long, value_name = "EXPECTED")]
+    check_puzzle_hash: Option<String>,
+
+    /// Prints the parameters of `main` as the argument list of a Chialisp `mod`, which is the
+    /// order that curried and passed in values are read in.
+    #[arg(long)]
+    mod_args: bool,
+
+    /// Runs the program with this solution, as serialized CLVM in hex, instead of nil.
+    #[arg(long, value_name = "HEX")]
+    run_args: Option<String>,
+
+    /// Reports warnings as errors, so that any warning fails the build.
+    #[arg(long, global = true)]
+    warnings_as_errors: bool,
+
+    /// Shows the line of source that each diagnostic refers to, with its span underlined.
+    #[arg(long, global = true)]
+    snippets: bool,
+
+    /// Sets the warning level, where `-W error` is the same as `--warnings-as-errors`.
+    #[arg(short = 'W', value_name = "LEVEL", value_parser = ["error"], global = true)]
+    warning_level: Option<String>,
+
+    /// Selects the CLVM operators which the program may use, and the dialect it's run with.
+    #[arg(long, default_value = "base", value_parser = ["base", "keccak"], global = true)]
+    target: String,
+
+    /// Prints a longer explanation of a diagnostic code, such as `E0001`, instead of compiling.
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compiles every file listed in a project manifest, and writes the program to its output directory.
+    Build {
+        /// The project manifest.
+        #[arg(default_value = "rue.toml")]
+        manifest: PathBuf,
+    },
+
+    /// Compiles and runs a single expression, and prints its value.
+    Eval {
+        /// The expression, which is used as the body of `main`.
+        expr: String,
+    },
+
+    /// Compiles and runs each test block in a source file, and reports which of them passed.
+    Test {
+        /// The source file containing the tests.
+        file: String,
+    },
+
+    /// Lists every builtin with its signature and what it's compiled to.
+    Builtins,
+}
+
+/// The start of the source that an evaluated expression is wrapped in.
+const EVAL_PREFIX: &str = "fun main() -> Any {\n";
+
 fn main() {
     let args = Args::parse();
 
-    let source = fs::read_to_string(args.file).expect("could not read source file");
-    let (ast, errors) = parse(&source);
+    if let Some(code) = &args.explain {
+        match explanation(code) {
+            Ok(text) => println!("{text}"),
+            Err(error) => {
+                eprintln!("error: {error}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut options = CompilerOptions::default();
+    for name in &args.defines {
+        options.define(name.clone());
+    }
+
+    let target = Target::from_name(&args.target).expect("unknown target");
+    options.set_target(target);
+    options.set_dump_symbols(args.dump_symbols);
+    options.set_dump_ir(args.emit.as_deref() == Some("ir-json"));
+    options.set_runtime_guards(args.runtime_guards);
+    options.set_jump_tables(args.jump_tables);
+    options.set_max_depth(args.max_depth);
+
+    for text in &args.curry {
+        let Some(value) = CurriedValue::parse(text) else {
+            eprintln!("error: invalid curried value `{text}`");
+            process::exit(1);
+        };
+        options.curry(value);
+    }
+
+    let warnings_as_errors = args.warnings_as_errors || args.warning_level.is_some();
+
+    match &args.command {
+        Some(Command::Build { manifest }) => {
+            build(manifest, &options, warnings_as_errors, args.snippets);
+            return;
+        }
+        Some(Command::Eval { expr }) => {
+            match evaluate(expr, &options, warnings_as_errors, args.snippets) {
+                Some(value) => println!("{value}"),
+                None => process::exit(1),
+            }
+            return;
+        }
+        Some(Command::Test { file }) => {
+            test(file, &options, warnings_as_errors, args.snippets);
+            return;
+        }
+        Some(Command::Builtins) => {
+            print!("{}", builtin_list());
+            return;
+        }
+        None => {}
+    }
+
+    let Some(file) = &args.file else {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "a source file or a subcommand is required",
+            )
+            .exit();
+    };
+
+    // The file is read as bytes, so that invalid UTF-8 is reported like any other parser error.
+    let bytes = fs::read(file).expect("could not read source file");
+    let (ast, errors) = parse_bytes(&bytes);
+    let source = ast.syntax().text().to_string();
+
+    let locate = |index| location(&source, index);
+    let render = |span: &Range<usize>| snippet(&source, span);
+    let render: Option<RenderSnippet> = args.snippets.then_some(&render);
 
     for error in &errors {
-        let LineCol { line, col } = line_col(&source, error.span().start);
-        let line = line + 1;
-        let col = col + 1;
+        report(
+            &format!("error: {}", error.kind()),
+            error.span(),
+            &locate,
+            render,
+        );
+    }
 
-        eprintln!("{} at {line}:{col}", error.kind());
+    let mut allocator = Allocator::new();
+    let output = compile(&mut allocator, ast, errors.is_empty(), &options);
+
+    // The symbols are printed even if compilation fails, since that's when they're most useful.
+    if let Some(symbol_dump) = output.symbol_dump() {
+        print!("{symbol_dump}");
     }
 
+    let failed = report_diagnostics(&locate, render, output.diagnostics(), warnings_as_errors);
+
+    if failed || !errors.is_empty() {
+        process::exit(1);
+    }
+
+    if let Some(ir_json) = output.ir_json() {
+        print!("{ir_json}");
+        return;
+    }
+
+    if args.pretty {
+        println!("{}", disassemble(&allocator, output.node_ptr()));
+    } else {
+        let bytes = node_to_bytes(&allocator, output.node_ptr()).unwrap();
+        println!("{}", hex::encode(bytes));
+    }
+
+    if args.estimate_cost {
+        println!("Estimated cost: {}", output.estimated_cost());
+    }
+
+    if args.puzzle_hash {
+        println!(
+            "Puzzle hash: {}",
+            puzzle_hash(&allocator, output.node_ptr())
+        );
+    }
+
+    if let Some(expected) = &args.check_puzzle_hash {
+        let actual = puzzle_hash(&allocator, output.node_ptr());
+
+        if let Err(error) = check_puzzle_hash(&actual, expected) {
+            eprintln!("error: {error}");
+            process::exit(1);
+        }
+    }
+
+    if args.mod_args {
+        if let Some(mod_arguments) = output.mod_arguments() {
+            println!("Mod arguments: {mod_arguments}");
+        }
+    }
+
+    let solution = match &args.run_args {
+        Some(run_args) => parse_solution(&mut allocator, run_args).unwrap_or_else(|error| {
+            eprintln!("error: {error}");
+            process::exit(1);
+        }),
+        None => NodePtr::NIL,
+    };
+
+    // The program has compiled successfully by now, so a target which can't be run only skips
+    // running it.
+    let dialect = match dialect(target) {
+        Ok(dialect) => dialect,
+        Err(error) => {
+            eprintln!("note: {error}");
+            return;
+        }
+    };
+
+    match run_program(&mut allocator, &dialect, output.node_ptr(), solution, 0) {
+        Ok(output) => println!(
+            "Serialized output: {}",
+            hex::encode(node_to_bytes(&allocator, output.1).unwrap())
+        ),
+        Err(error) => eprintln!("error: {}", run_error(&allocator, &error)),
+    }
+}
+
+/// Compiles a project from its manifest and writes the artifact, exiting if it fails.
+fn build(manifest: &Path, options: &CompilerOptions, warnings_as_errors: bool, snippets: bool) {
+    let project = Project::load(manifest).unwrap_or_else(|error| {
+        eprintln!("error: {error}");
+        process::exit(1);
+    });
+
     let mut allocator = Allocator::new();
-    let output = compile(&mut allocator, ast, errors.is_empty());
+    let Some(hex) = compile_project(
+        &mut allocator,
+        &project,
+        options,
+        warnings_as_errors,
+        snippets,
+    ) else {
+        process::exit(1);
+    };
 
-    if !output.diagnostics().is_empty() {
-        for error in output.diagnostics() {
-            let LineCol { line, col } = line_col(&source, error.span().start);
-            let line = line + 1;
-            let col = col + 1;
-            eprintln!("{} at {line}:{col}", error.info());
+    match project.write_artifact(&hex) {
+        Ok(path) => println!("Wrote {}", path.display()),
+        Err(error) => {
+            eprintln!("error: {error}");
+            process::exit(1);
         }
-        return;
     }
+}
+
+/// Compiles the combined source of a project, returning the program as hex unless there are errors.
+fn compile_project(
+    allocator: &mut Allocator,
+    project: &Project,
+    options: &CompilerOptions,
+    warnings_as_errors: bool,
+    snippets: bool,
+) -> Option<String> {
+    let (ast, errors) = parse(project.source());
+
+    let locate = |index| project.location(index);
+    let render = |span: &Range<usize>| project.snippet(span);
+    let render: Option<RenderSnippet> = snippets.then_some(&render);
+
+    for error in &errors {
+        report(
+            &format!("error: {}", error.kind()),
+            error.span(),
+            &locate,
+            render,
+        );
+    }
+
+    // Each file only sees the items which the others export.
+    let mut options = options.clone();
+    options.set_file_starts(project.file_starts());
+
+    let output = compile(allocator, ast, errors.is_empty(), &options);
+    let failed = report_diagnostics(&locate, render, output.diagnostics(), warnings_as_errors);
+
+    if failed || !errors.is_empty() {
+        return None;
+    }
+
+    let bytes = node_to_bytes(allocator, output.node_ptr()).unwrap();
+    Some(hex::encode(bytes))
+}
+
+/// Compiles an expression as the body of `main` and runs it, returning its value unless
+/// compiling or running it fails. Diagnostics are located relative to the expression itself.
+fn evaluate(
+    expr: &str,
+    options: &CompilerOptions,
+    warnings_as_errors: bool,
+    snippets: bool,
+) -> Option<String> {
+    let source = format!("{EVAL_PREFIX}{expr}\n}}\n");
+    let (ast, errors) = parse(&source);
+
+    let offset = |index: usize| index.saturating_sub(EVAL_PREFIX.len()).min(expr.len());
+    let locate = |index| location(expr, offset(index));
+    let render = |span: &Range<usize>| snippet(expr, &(offset(span.start)..offset(span.end)));
+    let render: Option<RenderSnippet> = snippets.then_some(&render);
+
+    for error in &errors {
+        report(
+            &format!("error: {}", error.kind()),
+            error.span(),
+            &locate,
+            render,
+        );
+    }
+
+    let mut allocator = Allocator::new();
+    let output = compile(&mut allocator, ast, errors.is_empty(), options);
+    let failed = report_diagnostics(&locate, render, output.diagnostics(), warnings_as_errors);
+
+    if failed || !errors.is_empty() {
+        return None;
+    }
+
+    let dialect = dialect(options.target())
+        .inspect_err(|error| eprintln!("error: {error}"))
+        .ok()?;
 
-    let bytes = node_to_bytes(&allocator, output.node_ptr()).unwrap();
-    println!("{}", hex::encode(bytes));
     match run_program(
         &mut allocator,
-        &ChiaDialect::new(0),
+        &dialect,
         output.node_ptr(),
         NodePtr::NIL,
-        0,
+        u64::MAX,
     ) {
-        Ok(output) => println!(
-            "Serialized output: {}",
-            hex::encode(node_to_bytes(&allocator, output.1).unwrap())
-        ),
-        Err(error) => eprintln!("Error: {:?}", error),
+        Ok(reduction) => Some(format_value(&allocator, reduction.1)),
+        Err(error) => {
+            eprintln!("error: {}", run_error(&allocator, &error));
+            None
+        }
+    }
+}
+
+/// Runs the tests in a source file and prints the result of each, exiting if any of them fail.
+fn test(file: &str, options: &CompilerOptions, warnings_as_errors: bool, snippets: bool) {
+    let source = fs::read_to_string(file).expect("could not read source file");
+
+    let Some(results) = run_tests(&source, options, warnings_as_errors, snippets) else {
+        process::exit(1);
+    };
+
+    let mut failed = 0;
+
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("test {name} ... ok"),
+            Err(error) => {
+                println!("test {name} ... FAILED: {error}");
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} passed; {failed} failed", results.len() - failed);
+
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Compiles the test blocks in the source and runs each of them, returning whether each one ran
+/// without raising, unless the source fails to compile.
+fn run_tests(
+    source: &str,
+    options: &CompilerOptions,
+    warnings_as_errors: bool,
+    snippets: bool,
+) -> Option<Vec<(String, Result<(), String>)>> {
+    let (ast, errors) = parse(source);
+
+    let locate = |index| location(source, index);
+    let render = |span: &Range<usize>| snippet(source, span);
+    let render: Option<RenderSnippet> = snippets.then_some(&render);
+
+    for error in &errors {
+        report(
+            &format!("error: {}", error.kind()),
+            error.span(),
+            &locate,
+            render,
+        );
+    }
+
+    let mut allocator = Allocator::new();
+    let output = compile_tests(&mut allocator, ast, errors.is_empty(), options);
+    let failed = report_diagnostics(&locate, render, output.diagnostics(), warnings_as_errors);
+
+    if failed || !errors.is_empty() {
+        return None;
+    }
+
+    let dialect = dialect(options.target())
+        .inspect_err(|error| eprintln!("error: {error}"))
+        .ok()?;
+
+    let results = output
+        .tests()
+        .iter()
+        .map(|test| {
+            let result = run_program(
+                &mut allocator,
+                &dialect,
+                test.node_ptr(),
+                NodePtr::NIL,
+                u64::MAX,
+            )
+            .map(|_| ())
+            // The raised value is included, so that it's clear which assertion failed.
+            .map_err(|error| run_error(&allocator, &error));
+
+            (test.name().to_string(), result)
+        })
+        .collect();
+
+    Some(results)
+}
+
+/// Describes why running a program failed. A raised value is decoded like the output of a
+/// program, and other errors include the value they're about, unless it's nil.
+fn run_error(allocator: &Allocator, error: &EvalErr) -> String {
+    let EvalErr(node, message) = error;
+    let value = format_value(allocator, *node);
+
+    if message == "clvm raise" {
+        return format!("the program raised {value}");
+    }
+
+    match allocator.sexp(*node) {
+        SExp::Atom if allocator.atom_len(*node) == 0 => message.clone(),
+        _ => format!("{message}: {value}"),
+    }
+}
+
+/// The tree hash of a program as hex, which is the same as `sha256tree` computes in Chialisp.
+fn puzzle_hash(allocator: &Allocator, program: NodePtr) -> String {
+    hex::encode(tree_hash(allocator, program))
+}
+
+/// Compares a puzzle hash against the one it's expected to be, which is given as hex with or
+/// without a `0x` prefix, in any case.
+fn check_puzzle_hash(actual: &str, expected: &str) -> Result<(), String> {
+    let expected = expected.to_lowercase();
+    let normalized = expected.strip_prefix("0x").unwrap_or(&expected);
+
+    if normalized == actual {
+        return Ok(());
+    }
+
+    Err(format!(
+        "puzzle hash mismatch\n- expected: {normalized}\n+   actual: {actual}"
+    ))
+}
+
+/// Deserializes a solution which is given as hex, with or without a `0x` prefix.
+fn parse_solution(allocator: &mut Allocator, hex: &str) -> Result<NodePtr, String> {
+    let hex = hex.to_lowercase();
+    let bytes = hex::decode(hex.strip_prefix("0x").unwrap_or(&hex))
+        .map_err(|error| format!("invalid solution hex: {error}"))?;
+    node_from_bytes(allocator, &bytes).map_err(|error| format!("invalid solution: {error}"))
+}
+
+/// The explanation of a diagnostic code, which is looked up case insensitively.
+fn explanation(code: &str) -> Result<&'static str, String> {
+    explain(&code.to_uppercase()).ok_or_else(|| format!("unknown diagnostic code `{code}`"))
+}
+
+/// A line for each builtin, with its signature followed by the CLVM opcode it's compiled to, or
+/// how it's compiled if it isn't a single operator.
+fn builtin_list() -> String {
+    let signatures: Vec<String> = BUILTINS
+        .iter()
+        .map(|builtin| format!("{}{}", builtin.name(), builtin.signature()))
+        .collect();
+
+    let width = signatures.iter().map(String::len).max().unwrap_or(0);
+
+    BUILTINS
+        .iter()
+        .zip(signatures)
+        .map(|(builtin, signature)| {
+            let lowering = match builtin.lowering() {
+                Lowering::Operator(op) => format!("opcode {}", op.operator().opcode()),
+                Lowering::Condition(opcode) => format!("condition {opcode}"),
+                Lowering::Constant => "compile time".to_string(),
+                Lowering::Expanded => "expanded".to_string(),
+            };
+            format!("{signature:width$}  {lowering}\n")
+        })
+        .collect()
+}
+
+/// The one-based line and column of an index into a source file.
+fn location(source: &str, index: usize) -> String {
+    let LineCol { line, col } = line_col(source, index);
+    format!("{}:{}", line + 1, col + 1)
+}
+
+/// The dialect for running programs compiled for the target.
+fn dialect(target: Target) -> Result<ChiaDialect, String> {
+    match target {
+        Target::Base => Ok(ChiaDialect::new(0)),
+        // This version of clvmr doesn't implement `keccak256`, so it would be run like any unknown
+        // operator, and return nil rather than the digest.
+        Target::Keccak => Err(format!(
+            "programs for target `{}` can't be run, since `keccak256` isn't supported",
+            target.name()
+        )),
+    }
+}
+
+/// Renders the source that a span refers to.
+type RenderSnippet<'a> = &'a dyn Fn(&Range<usize>) -> String;
+
+/// Prints a message with the location of its span, followed by the source it refers to if
+/// snippets are enabled.
+fn report(
+    message: &str,
+    span: &Range<usize>,
+    locate: &dyn Fn(usize) -> String,
+    render: Option<RenderSnippet>,
+) {
+    eprintln!("{message} at {}", locate(span.start));
+
+    if let Some(render) = render {
+        eprintln!("{}", render(span));
+    }
+}
+
+/// Prints each diagnostic with its severity, and returns whether any of them is an error.
+fn report_diagnostics(
+    locate: &dyn Fn(usize) -> String,
+    render: Option<RenderSnippet>,
+    diagnostics: &[Diagnostic],
+    warnings_as_errors: bool,
+) -> bool {
+    let severity = |diagnostic: &Diagnostic| match diagnostic.kind() {
+        DiagnosticKind::Warning if warnings_as_errors => DiagnosticKind::Error,
+        kind => kind,
+    };
+
+    for diagnostic in diagnostics {
+        let severity = match severity(diagnostic) {
+            DiagnosticKind::Warning => "warning",
+            DiagnosticKind::Error => "error",
+        };
+
+        report(
+            &format!("{severity}[{}]: {}", diagnostic.code(), diagnostic.info()),
+            diagnostic.span(),
+            locate,
+            render,
+        );
+    }
+
+    diagnostics
+        .iter()
+        .any(|diagnostic| severity(diagnostic) == DiagnosticKind::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use clvmr::SExp;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    fn check(source: &str, warnings_as_errors: bool, expected_failure: bool) {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(!output.diagnostics().is_empty());
+
+        assert_eq!(
+            report_diagnostics(
+                &|index| location(source, index),
+                None,
+                output.diagnostics(),
+                warnings_as_errors
+            ),
+            expected_failure
+        );
+    }
+
+    #[test]
+    fn test_warning_succeeds() {
+        check(
+            "fun main(value: Any) -> Bool { value is Any }",
+            false,
+            false,
+        );
+    }
+
+    #[test]
+    fn test_warnings_as_errors() {
+        check("fun main(value: Any) -> Bool { value is Any }", true, true);
+    }
+
+    #[test]
+    fn test_infinite_recursion_warns() {
+        let source = "fun main(x: Int) -> Int { f(x) } fun f(x: Int) -> Int { f(x) }";
+        check(source, false, false);
+        check(source, true, true);
+    }
+
+    #[test]
+    fn test_error_fails() {
+        check("fun main() -> Int { missing }", false, true);
+    }
+
+    fn compile_for(source: &str, target: Target) -> Vec<String> {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut options = CompilerOptions::default();
+        options.set_target(target);
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &options);
+
+        output
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.info().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_keccak256_target() {
+        let source = "fun main() -> Bytes32 { keccak256(\"abc\") }";
+
+        assert_eq!(
+            compile_for(source, Target::Base),
+            ["`keccak256` is not available for target `base`"]
+        );
+
+        assert!(compile_for(source, Target::Keccak).is_empty());
+
+        // The program compiles, but this version of clvmr can't run it correctly.
+        let mut options = CompilerOptions::default();
+        options.set_target(Target::Keccak);
+        assert_eq!(evaluate("keccak256(\"abc\")", &options, false, false), None);
+        assert!(dialect(Target::Keccak).is_err());
+    }
+
+    #[test]
+    fn test_build_project() {
+        let root = std::env::temp_dir().join(format!("rue-project-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        fs::write(
+            root.join("rue.toml"),
+            "name = \"example\"\nentrypoint = \"main.rue\"\nsources = [\"math.rue\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("main.rue"),
+            "fun main() -> Int {\n    square(LIMIT)\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("math.rue"),
+            "pub const LIMIT: Int = 12;\n\npub fun square(value: Int) -> Int {\n    value * value\n}\n",
+        )
+        .unwrap();
+
+        let project = Project::load(&root.join("rue.toml")).unwrap();
+        assert_eq!(
+            project.location(project.source().find("pub const").unwrap()),
+            "math.rue:1:1"
+        );
+
+        let mut allocator = Allocator::new();
+        let hex = compile_project(
+            &mut allocator,
+            &project,
+            &CompilerOptions::default(),
+            false,
+            false,
+        )
+        .expect("the project compiles");
+
+        let path = project.write_artifact(&hex).unwrap();
+        assert_eq!(path, root.join("build").join("example.hex"));
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), hex);
+
+        let program =
+            clvmr::serde::node_from_bytes(&mut allocator, &hex::decode(&hex).unwrap()).unwrap();
+        let output = run_program(
+            &mut allocator,
+            &ChiaDialect::new(0),
+            program,
+            NodePtr::NIL,
+            u64::MAX,
+        )
+        .unwrap()
+        .1;
+        assert_eq!(
+            hex::encode(node_to_bytes(&allocator, output).unwrap()),
+            "820090"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_private_items() {
+        let root = std::env::temp_dir().join(format!("rue-private-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        fs::write(
+            root.join("rue.toml"),
+            "name = \"example\"\nentrypoint = \"main.rue\"\nsources = [\"math.rue\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("math.rue"),
+            "pub fun square(value: Int) -> Int {\n    double(value) * value / 2\n}\n\n\
+             fun double(value: Int) -> Int {\n    value * 2\n}\n",
+        )
+        .unwrap();
+
+        // Private items can be used from their own file, and exported ones from anywhere.
+        fs::write(
+            root.join("main.rue"),
+            "fun main() -> Int {\n    square(12)\n}\n",
+        )
+        .unwrap();
+        let project = Project::load(&root.join("rue.toml")).unwrap();
+        let options = CompilerOptions::default();
+        let mut allocator = Allocator::new();
+        assert!(compile_project(&mut allocator, &project, &options, false, false).is_some());
+
+        fs::write(
+            root.join("main.rue"),
+            "fun main() -> Int {\n    double(12)\n}\n",
+        )
+        .unwrap();
+        let project = Project::load(&root.join("rue.toml")).unwrap();
+        let mut options = options.clone();
+        options.set_file_starts(project.file_starts());
+
+        let (ast, errors) = parse(project.source());
+        assert!(errors.is_empty());
+        let output = compile(&mut allocator, ast, true, &options);
+        let messages: Vec<String> = output
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                format!(
+                    "{} at {}",
+                    diagnostic.info(),
+                    project.location(diagnostic.span().start)
+                )
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            ["`double` is private to the file it's declared in at main.rue:2:5"]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let options = CompilerOptions::default();
+
+        assert_eq!(
+            evaluate("1 + 2 * 3", &options, false, false),
+            Some("7".to_string())
+        );
+        assert_eq!(
+            evaluate("[1, 2, 3]", &options, false, false),
+            Some("(1 2 3)".to_string())
+        );
+        assert_eq!(evaluate("1 + missing", &options, false, false), None);
+    }
+
+    #[test]
+    fn test_run_tests() {
+        let source = r#"
+            fun add(a: Int, b: Int) -> Int {
+                a + b
+            }
+
+            test "adds" {
+                assert add(1, 2) == 3;
+            }
+
+            test "adds wrong" {
+                let sum = add(2, 2);
+                assert sum == 5, "wrong sum";
+            }
+        "#;
+
+        let results = run_tests(source, &CompilerOptions::default(), false, false).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], ("adds".to_string(), Ok(())));
+        assert_eq!(results[1].0, "adds wrong");
+        assert!(results[1].1.as_ref().unwrap_err().contains("wrong sum"));
+
+        assert_eq!(
+            run_tests(
+                "test \"broken\" { assert missing; }",
+                &CompilerOptions::default(),
+                false,
+                false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_error() {
+        let run = |source: &str| {
+            let (root, errors) = parse(source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            let error = run_program(
+                &mut allocator,
+                &ChiaDialect::new(0),
+                output.node_ptr(),
+                NodePtr::NIL,
+                u64::MAX,
+            )
+            .unwrap_err();
+            run_error(&allocator, &error)
+        };
+
+        assert_eq!(
+            run("fun main() -> Int { raise \"amount must be positive\"; }"),
+            "the program raised \"amount must be positive\""
+        );
+        assert_eq!(
+            run("fun main() -> Int { raise 42; }"),
+            "the program raised 42"
+        );
+    }
+
+    /// Compiles and runs a program with the serialized environment, returning the serialized
+    /// output, or `None` if the program raised.
+    fn run_with(source: &str, options: &CompilerOptions, env: &str) -> Option<String> {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, options);
+        assert!(output.diagnostics().is_empty());
+
+        let env =
+            clvmr::serde::node_from_bytes(&mut allocator, &hex::decode(env).unwrap()).unwrap();
+        let output = run_program(
+            &mut allocator,
+            &ChiaDialect::new(0),
+            output.node_ptr(),
+            env,
+            u64::MAX,
+        )
+        .ok()?
+        .1;
+        Some(hex::encode(node_to_bytes(&allocator, output).unwrap()))
+    }
+
+    #[test]
+    fn test_dbg() {
+        let source = "fun main(value: Int) -> Int { dbg(value * 2) + 1 }";
+        let without_dbg = "fun main(value: Int) -> Int { value * 2 + 1 }";
+
+        let compiled = |source: &str, options: &CompilerOptions| {
+            let (root, errors) = parse(source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, options);
+            assert!(output.diagnostics().is_empty());
+            disassemble(&allocator, output.node_ptr())
+        };
+
+        // Without `debug` defined, the value is passed through as if `dbg` weren't there.
+        let production = CompilerOptions::default();
+        assert_eq!(
+            compiled(source, &production),
+            compiled(without_dbg, &production)
+        );
+        assert_eq!(run_with(source, &production, "ff1580").unwrap(), "2b");
+
+        let mut debug = CompilerOptions::default();
+        debug.define("debug");
+        assert_eq!(run_with(source, &debug, "ff1580"), None);
+    }
+
+    #[test]
+    fn test_deprecated() {
+        let source = r#"
+            @deprecated("use double instead")
+            fun twice(value: Int) -> Int { value * 2 }
+
+            fun main() -> Int { twice(21) }
+        "#;
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+
+        let [diagnostic] = output.diagnostics() else {
+            panic!("expected one diagnostic");
+        };
+        assert_eq!(diagnostic.kind(), DiagnosticKind::Warning);
+        assert_eq!(
+            diagnostic.info().to_string(),
+            "`twice` is deprecated: use double instead"
+        );
+        assert_eq!(location(source, diagnostic.span().start), "5:33");
+
+        // The warning doesn't fail the build unless warnings are errors, and the call still runs.
+        let locate = |index| location(source, index);
+        assert!(!report_diagnostics(
+            &locate,
+            None,
+            output.diagnostics(),
+            false
+        ));
+        assert!(report_diagnostics(
+            &locate,
+            None,
+            output.diagnostics(),
+            true
+        ));
+
+        let result = run_program(
+            &mut allocator,
+            &ChiaDialect::new(0),
+            output.node_ptr(),
+            NodePtr::NIL,
+            u64::MAX,
+        )
+        .unwrap()
+        .1;
+        assert_eq!(format_value(&allocator, result), "42");
+    }
+
+    #[test]
+    fn test_implicit_conversion() {
+        let source = r#"fun main() -> Bool { "a" == 97 }"#;
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+
+        let [diagnostic] = output.diagnostics() else {
+            panic!("expected one diagnostic");
+        };
+        assert_eq!(diagnostic.kind(), DiagnosticKind::Warning);
+        assert_eq!(
+            diagnostic.info().to_string(),
+            "implicit conversion from `Int` to `Bytes`, use `as` to make it explicit"
+        );
+
+        // The comparison still runs, and compares the bytes of the integer.
+        let result = run_program(
+            &mut allocator,
+            &ChiaDialect::new(0),
+            output.node_ptr(),
+            NodePtr::NIL,
+            u64::MAX,
+        )
+        .unwrap()
+        .1;
+        assert_eq!(format_value(&allocator, result), "1");
+
+        // Casting makes the conversion explicit, so there's nothing to warn about.
+        let (root, _) = parse(r#"fun main() -> Bool { "a" == 97 as Bytes }"#);
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(output.diagnostics().is_empty());
+    }
+
+    fn run_serialized(source: &str) -> String {
+        run_with(source, &CompilerOptions::default(), "80").unwrap()
+    }
+
+    #[test]
+    fn test_leading_zero_bytes() {
+        assert_eq!(run_serialized("fun main() -> Bytes { 0x0041 }"), "820041");
+        assert_eq!(run_serialized("fun main() -> Bytes { 0x00 }"), "00");
+        assert_eq!(
+            run_serialized("const PREFIX: Bytes = 0x00; fun main() -> Bytes { PREFIX + 0x41 }"),
+            "820041"
+        );
+        assert_eq!(
+            run_serialized("fun main() -> Bytes { substr(0x000041, 0, 2) }"),
+            "820000"
+        );
+
+        // Integers are minimally encoded, so the same value doesn't keep a leading zero.
+        assert_eq!(
+            run_serialized("fun main() -> Int { 0x0041 as Int + 0 }"),
+            "41"
+        );
+    }
+
+    #[test]
+    fn test_runtime_guards() {
+        let source = "fun main(width: Int) -> Int { pad(width) }
+            fun pad(width: Int) -> Int where width > 0 { width }";
+
+        // Without runtime guards, only calls with constant arguments are checked.
+        let options = CompilerOptions::default();
+        assert_eq!(run_with(source, &options, "ff8080"), Some("80".to_string()));
+
+        let mut options = CompilerOptions::default();
+        options.set_runtime_guards(true);
+        assert_eq!(run_with(source, &options, "ff0480"), Some("04".to_string()));
+        assert_eq!(run_with(source, &options, "ff8080"), None);
+    }
+
+    #[test]
+    fn test_mod_arguments() {
+        fn mod_arguments(source: &str) -> String {
+            let (root, errors) = parse(source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            output.mod_arguments().unwrap().to_string()
+        }
+
+        // The helper function is curried in front, so the solution is read from after it.
+        let source = "fun main(amount: Int, fee: Int) -> Int { sub(amount, fee) }
+            fun sub(a: Int, b: Int) -> Int { a - b }";
+        assert_eq!(mod_arguments(source), "(amount fee)");
+
+        let mut allocator = Allocator::new();
+        let solution = parse_solution(&mut allocator, "0xff0aff0380").unwrap();
+        let solution = hex::encode(node_to_bytes(&allocator, solution).unwrap());
+        assert_eq!(
+            run_with(source, &CompilerOptions::default(), &solution),
+            Some("07".to_string())
+        );
+
+        // A spread parameter is the rest of the solution, even when read from a nested scope.
+        let source = "fun main(a: Int, b: Int, ...rest: Int[]) -> Int[] {
+            let sum = a + b;
+            [sum, ...rest]
+        }";
+        assert_eq!(mod_arguments(source), "(a b . rest)");
+        assert_eq!(
+            run_with(source, &CompilerOptions::default(), "ff01ff02ff03ff0480"),
+            Some("ff03ff03ff0480".to_string())
+        );
+
+        assert_eq!(
+            mod_arguments("fun main(...args: Int[]) -> Int[] { args }"),
+            "args"
+        );
+        assert_eq!(mod_arguments("fun main() -> Int { 1 }"), "()");
+    }
+
+    #[test]
+    fn test_curry() {
+        let source = "fun main(base: Int, offset: Int) -> Int { base + offset }";
+
+        let mut options = CompilerOptions::default();
+        options.curry(CurriedValue::parse("40").unwrap());
+        assert_eq!(run_with(source, &options, "ff0280"), Some("2a".to_string()));
+
+        // The value is baked into the program, so it's the same as passing it in the solution.
+        assert_eq!(
+            run_with(source, &CompilerOptions::default(), "ff28ff0280"),
+            Some("2a".to_string())
+        );
+
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut options = CompilerOptions::default();
+        options.curry(CurriedValue::parse("0xcafe").unwrap());
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &options);
+        let codes: Vec<&str> = output
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.info().code())
+            .collect();
+        assert_eq!(codes, ["E0060"]);
+
+        assert_eq!(CurriedValue::parse("zz"), None);
+        assert_eq!(CurriedValue::parse("0xabc"), None);
+    }
+
+    #[test]
+    fn test_concat_folding() {
+        let disassembled = |source: &str| {
+            let (root, errors) = parse(source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            assert!(output.diagnostics().is_empty());
+            disassemble(&allocator, output.node_ptr())
+        };
+
+        // The disassembler shows small atoms as integers, so `0x0102` is written as `258`.
+        assert_eq!(
+            disassembled("fun main() -> Bytes { concat(0x01, 0x02) }"),
+            "(a (q 1 . 258) 1)"
+        );
+
+        // Adjacent constants are merged even when the other operands aren't constant.
+        assert_eq!(
+            disassembled("fun main(x: Bytes) -> Bytes { concat(0x01, x, 0x02, 0x03) }"),
+            "(a (q 14 (q . 1) 2 (q . 515)) 1)"
+        );
+        assert_eq!(
+            run_serialized("fun main() -> Bytes { concat(0x01, 0x02) }"),
+            "820102"
+        );
+    }
+
+    #[test]
+    fn test_jump_tables() {
+        let source = "fun main(x: Int) -> Int {
+            if x == 1 { 10 } else if x == 2 { 20 } else if x == 4 { 40 }
+            else if x == 3 { 30 } else if x == 5 { 50 } else { 0 }
+        }";
+
+        let mut options = CompilerOptions::default();
+        options.set_jump_tables(true);
+
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &options);
+        assert!(output.diagnostics().is_empty());
+
+        assert_eq!(
+            disassemble(&allocator, output.node_ptr()),
+            "(a (q 2 (i (any (not (= 2 (+ 2))) (> (q . 1) 2) (> 2 (q . 5))) () (q 2 (a (- (ash (q . 3) (- 2 (q . 1))) (q . 1)) (q (q . 10) (q . 20) (q . 30) (q . 40) (q . 50))) 1)) 1) 1)"
+        );
+
+        // Every value gives the same result as the chain of comparisons, including those outside.
+        for x in 0..=6 {
+            let env = match x {
+                0 => "ff8080".to_string(),
+                x => format!("ff{x:02x}80"),
+            };
+            assert_eq!(
+                run_with(source, &options, &env),
+                run_with(source, &CompilerOptions::default(), &env)
+            );
+        }
+
+        // A value which isn't minimally encoded matches none of the comparisons, so it falls
+        // through to the `else` branch rather than being looked up like the integer it encodes.
+        assert_eq!(
+            run_with(source, &options, "ff82000180"),
+            run_with(source, &CompilerOptions::default(), "ff82000180")
+        );
+    }
+
+    /// A `match` over an enum whose discriminants have no gaps looks up its arm by the tag, while
+    /// one with gaps keeps comparing the tag against each discriminant.
+    #[test]
+    fn test_match_jump_tables() {
+        let source = |discriminants: [u8; 5]| {
+            let variants: Vec<String> = discriminants
+                .iter()
+                .enumerate()
+                .map(|(i, discriminant)| format!("V{i} = {discriminant}"))
+                .collect();
+            format!(
+                "enum Tag {{ {} }}
+                fun main(tag: Int) -> Int {{
+                    match tag as Tag {{ V0 => 10, V1 => 20, V2 => 30, V3 => 40, V4 => 50 }}
+                }}",
+                variants.join(", ")
+            )
+        };
+
+        let mut options = CompilerOptions::default();
+        options.set_jump_tables(true);
+
+        for (discriminants, is_dense) in [([1, 2, 3, 4, 5], true), ([1, 2, 4, 8, 16], false)] {
+            let source = source(discriminants);
+
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &options);
+            assert!(output.diagnostics().is_empty());
+
+            let program = disassemble(&allocator, output.node_ptr());
+            assert_eq!(program.contains("(= (f 2)"), !is_dense, "{program}");
+
+            // Every tag gives the same result as the chain of comparisons.
+            for tag in discriminants {
+                let env = format!("ff{tag:02x}80");
+                assert_eq!(
+                    run_with(&source, &options, &env),
+                    run_with(&source, &CompilerOptions::default(), &env)
+                );
+            }
+        }
+    }
+
+    fn sha256tree(allocator: &Allocator, node: NodePtr) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        match allocator.sexp(node) {
+            SExp::Pair(first, rest) => {
+                hasher.update([2]);
+                hasher.update(sha256tree(allocator, first));
+                hasher.update(sha256tree(allocator, rest));
+            }
+            SExp::Atom => {
+                hasher.update([1]);
+                hasher.update(allocator.atom(node));
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    fn ir_json(source: &str) -> String {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut options = CompilerOptions::default();
+        options.set_dump_ir(true);
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &options);
+        assert!(output.diagnostics().is_empty());
+
+        output.ir_json().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_ir_json() {
+        let source = "
+            fun double(value: Int) -> Int { value * 2 }
+            fun main(value: Int) -> Int { double(value) + double(value + 1) }
+        ";
+
+        let json = ir_json(source);
+        assert_eq!(json, ir_json(source));
+
+        let ir: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(ir["root"], 0);
+
+        let nodes = ir["nodes"].as_array().unwrap();
+        let mut counts = std::collections::BTreeMap::new();
+
+        for (index, node) in nodes.iter().enumerate() {
+            assert_eq!(node["id"], index);
+
+            for operand in node["operands"].as_array().unwrap() {
+                assert!(operand.as_u64().unwrap() < nodes.len() as u64);
+            }
+
+            *counts.entry(node["kind"].as_str().unwrap()).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts["FunctionBody"], 1);
+        assert_eq!(counts["Mul"], 1);
+        assert_eq!(counts["Run"], 2);
+        assert_eq!(counts["Add"], 2);
+        assert_eq!(counts.values().sum::<usize>(), nodes.len());
+    }
+
+    #[test]
+    fn test_puzzle_hash() {
+        let (root, errors) = parse("fun main(value: Int) -> Int { value * 2 }");
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(output.diagnostics().is_empty());
+
+        assert_eq!(
+            puzzle_hash(&allocator, output.node_ptr()),
+            hex::encode(sha256tree(&allocator, output.node_ptr()))
+        );
+
+        // The hash of `(q . 1)`, which doesn't depend on the compiler's output.
+        let one = allocator.new_small_number(1).unwrap();
+        let quote = allocator.new_pair(one, one).unwrap();
+        assert_eq!(
+            puzzle_hash(&allocator, quote),
+            "69ae360134b1fae04326e5546f25dc794a19192a1f22a44a46d038e7f0d1ecbb"
+        );
+    }
+
+    #[test]
+    fn test_check_puzzle_hash() {
+        let (root, errors) = parse("fun main(value: Int) -> Int { value * 2 }");
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(output.diagnostics().is_empty());
+
+        let actual = puzzle_hash(&allocator, output.node_ptr());
+        assert_eq!(check_puzzle_hash(&actual, &actual), Ok(()));
+        assert_eq!(
+            check_puzzle_hash(&actual, &format!("0x{}", actual.to_uppercase())),
+            Ok(())
+        );
+        assert_eq!(check_puzzle_hash(&actual, &format!("0X{actual}")), Ok(()));
+        assert_eq!(
+            check_puzzle_hash(&actual, &format!("0x0x{actual}")),
+            Err(format!(
+                "puzzle hash mismatch\n- expected: 0x{actual}\n+   actual: {actual}"
+            ))
+        );
+
+        let expected = "69ae360134b1fae04326e5546f25dc794a19192a1f22a44a46d038e7f0d1ecbb";
+        assert_eq!(
+            check_puzzle_hash(&actual, expected),
+            Err(format!(
+                "puzzle hash mismatch\n- expected: {expected}\n+   actual: {actual}"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_builtin_list() {
+        let list = builtin_list();
+
+        let sha256 = list
+            .lines()
+            .find(|line| line.starts_with("sha256("))
+            .unwrap();
+        assert!(sha256.starts_with("sha256(bytes: Bytes) -> Bytes32 "));
+        assert!(sha256.ends_with("  opcode 11"));
+
+        assert!(list
+            .lines()
+            .any(|line| line.starts_with("create_coin(") && line.ends_with("  condition 51")));
+        assert_eq!(list.lines().count(), BUILTINS.len());
+    }
+
+    #[test]
+    fn test_explain() {
+        let text = explanation("E0002").unwrap();
+        assert!(text.starts_with("A name was used which isn't defined"));
+        assert_eq!(explanation("e0002"), Ok(text));
+
+        assert_eq!(
+            explanation("E9999"),
+            Err("unknown diagnostic code `E9999`".to_string())
+        );
     }
 }
@@ -0,0 +1,166 @@
+use std::{
+    fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use rue_parser::{line_col, LineCol};
+use serde::Deserialize;
+
+use crate::snippet::snippet;
+
+/// The contents of a `rue.toml` manifest, which describes a project made of several source files.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The name of the project, which is also used for the name of its artifact.
+    pub name: String,
+
+    /// The file containing the `main` function, relative to the manifest.
+    pub entrypoint: String,
+
+    /// The other files which are compiled along with the entrypoint, relative to the manifest.
+    #[serde(default)]
+    pub sources: Vec<String>,
+
+    /// The directory that artifacts are written to, relative to the manifest.
+    #[serde(default = "default_output")]
+    pub output: String,
+}
+
+fn default_output() -> String {
+    "build".to_string()
+}
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(PathBuf, io::Error),
+    Manifest(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            Self::Manifest(path, error) => write!(f, "{}: {error}", path.display()),
+        }
+    }
+}
+
+struct SourceFile {
+    path: String,
+    start: usize,
+}
+
+/// A project loaded from its manifest. Its files share a single namespace, so they are compiled
+/// together as one source, with each file starting on a new line.
+pub struct Project {
+    root: PathBuf,
+    manifest: Manifest,
+    files: Vec<SourceFile>,
+    source: String,
+}
+
+impl Project {
+    pub fn load(manifest_path: &Path) -> Result<Self, ProjectError> {
+        let text = read(manifest_path)?;
+        let manifest: Manifest = toml::from_str(&text)
+            .map_err(|error| ProjectError::Manifest(manifest_path.to_path_buf(), error))?;
+
+        let root = manifest_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut files = Vec::new();
+        let mut source = String::new();
+
+        for path in [&manifest.entrypoint].into_iter().chain(&manifest.sources) {
+            if !source.is_empty() {
+                source.push('\n');
+            }
+
+            files.push(SourceFile {
+                path: path.clone(),
+                start: source.len(),
+            });
+            source.push_str(&read(&root.join(path))?);
+        }
+
+        Ok(Self {
+            root,
+            manifest,
+            files,
+            source,
+        })
+    }
+
+    /// The combined source of every file in the project.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Where each file starts in the combined source, in order.
+    pub fn file_starts(&self) -> Vec<usize> {
+        self.files.iter().map(|file| file.start).collect()
+    }
+
+    /// Describes where an index into the combined source is, as a file and a line and column.
+    pub fn location(&self, index: usize) -> String {
+        let file = self.file(index);
+
+        let LineCol { line, col } = line_col(&self.source, index);
+        let LineCol {
+            line: start_line, ..
+        } = line_col(&self.source, file.start);
+
+        format!("{}:{}:{}", file.path, line - start_line + 1, col + 1)
+    }
+
+    /// Renders the line of the file that a span into the combined source starts on.
+    pub fn snippet(&self, span: &Range<usize>) -> String {
+        let file = self.file(span.start);
+        let end = self
+            .files
+            .iter()
+            .find(|other| other.start > file.start)
+            .map_or(self.source.len(), |next| next.start);
+
+        snippet(
+            &self.source[file.start..end],
+            &(span.start - file.start..span.end.min(end) - file.start),
+        )
+    }
+
+    fn file(&self, index: usize) -> &SourceFile {
+        self.files
+            .iter()
+            .rev()
+            .find(|file| file.start <= index)
+            .expect("the entrypoint starts at the beginning")
+    }
+
+    /// The path of the compiled program's artifact, which contains it as hex.
+    pub fn artifact_path(&self) -> PathBuf {
+        self.root
+            .join(&self.manifest.output)
+            .join(format!("{}.hex", self.manifest.name))
+    }
+
+    pub fn write_artifact(&self, hex: &str) -> Result<PathBuf, ProjectError> {
+        let path = self.artifact_path();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| ProjectError::Io(parent.into(), error))?;
+        }
+
+        fs::write(&path, format!("{hex}\n"))
+            .map_err(|error| ProjectError::Io(path.clone(), error))?;
+
+        Ok(path)
+    }
+}
+
+fn read(path: &Path) -> Result<String, ProjectError> {
+    fs::read_to_string(path).map_err(|error| ProjectError::Io(path.to_path_buf(), error))
+}
@@ -0,0 +1,62 @@
+use std::ops::Range;
+
+/// Renders the line of source which a span starts on, with the span underlined by carets.
+/// Spans which continue onto later lines are only underlined up to the end of the first one.
+pub fn snippet(source: &str, span: &Range<usize>) -> String {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |index| start + index);
+
+    let line = source[line_start..line_end].trim_end_matches('\r');
+    let line_number = (source[..line_start].matches('\n').count() + 1).to_string();
+
+    let column = source[line_start..start].chars().count();
+    let end = span.end.clamp(start, line_start + line.len());
+    let width = source[start..end].chars().count().max(1);
+
+    let gutter = " ".repeat(line_number.len());
+
+    format!(
+        "{gutter} |\n{line_number} | {line}\n{gutter} | {}{}",
+        " ".repeat(column),
+        "^".repeat(width)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mid_line() {
+        let source = "fun main() -> Int {\n    let value = missing + 1;\n    value\n}\n";
+        let start = source.find("missing").unwrap();
+
+        assert_eq!(
+            snippet(source, &(start..start + "missing".len())),
+            "  |\n2 |     let value = missing + 1;\n  |                 ^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn test_empty_span() {
+        let source = "fun main() -> Int {\n    42";
+
+        assert_eq!(
+            snippet(source, &(source.len()..source.len())),
+            "  |\n2 |     42\n  |       ^"
+        );
+    }
+
+    #[test]
+    fn test_multiline_span() {
+        let source = "fun main() -> Int {\n    42\n}";
+
+        assert_eq!(
+            snippet(source, &(18..source.len())),
+            "  |\n1 | fun main() -> Int {\n  |                   ^"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use clvmr::Allocator;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rue_compiler::{compile_with, CompilerOptions, Database};
+use rue_parser::parse;
+
+/// A program with many functions, where the one at `edited` differs from the original.
+fn source(edited: Option<usize>) -> String {
+    let calls: Vec<String> = (0..16).map(|i| format!("f{i}(value)")).collect();
+    let mut source = format!("fun main(value: Int) -> Int {{ {} }}\n", calls.join(" + "));
+
+    for i in 0..16 {
+        let terms: Vec<String> = (0..64)
+            .map(|j| {
+                format!(
+                    "(if value > {j} {{ value * {} }} else {{ value - {j} }})",
+                    i + usize::from(edited == Some(i))
+                )
+            })
+            .collect();
+        source.push_str(&format!(
+            "fun f{i}(value: Int) -> Int {{ {} }}\n",
+            terms.join(" + ")
+        ));
+    }
+
+    source
+}
+
+fn compile(db: &mut Database, source: &str) {
+    let (root, errors) = parse(source);
+    assert!(errors.is_empty());
+    let mut allocator = Allocator::new();
+    let output = compile_with(
+        db,
+        &mut allocator,
+        root,
+        errors.is_empty(),
+        &CompilerOptions::default(),
+    );
+    assert!(output.diagnostics().is_empty());
+}
+
+/// Compiles a program after editing one of its functions, either with a new database or with
+/// the database from compiling it before the edit, which reuses the functions that didn't change.
+/// Both drop what the compilation before the edit allocated, which a reused database does when
+/// it's cleared.
+fn recompile(c: &mut Criterion) {
+    let original = source(None);
+    let edited = source(Some(7));
+
+    // The original is compiled twice, since a long-running process will have compacted what it
+    // keeps between compilations by then.
+    let setup = || {
+        let mut db = Database::default();
+        compile(&mut db, &original);
+        compile(&mut db, &original);
+        db
+    };
+
+    let mut group = c.benchmark_group("recompile after editing one function");
+
+    group.bench_function("new database", |b| {
+        b.iter_batched(
+            setup,
+            |db| {
+                drop(db);
+                let mut db = Database::default();
+                compile(&mut db, &edited);
+                db
+            },
+            BatchSize::SmallInput,
+        );
+    });
+
+    group.bench_function("reused database", |b| {
+        b.iter_batched(
+            setup,
+            |mut db| {
+                compile(&mut db, &edited);
+                db
+            },
+            BatchSize::SmallInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, recompile);
+criterion_main!(benches);
@@ -0,0 +1,544 @@
+use std::fmt;
+
+/// How a call to a builtin is compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lowering {
+    /// A single CLVM operator, whose argument and result types are the ones in the table.
+    Operator(Op),
+    /// A condition, which is a list starting with this condition opcode.
+    Condition(u8),
+    /// A value which is computed at compile time.
+    Constant,
+    /// Several operators, or a call to a function which is included in programs that use it.
+    Expanded,
+}
+
+/// A function which is available without being declared, either because it's defined in the
+/// root scope or because calls to it are compiled directly.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    name: &'static str,
+    signature: &'static str,
+    lowering: Lowering,
+}
+
+impl Builtin {
+    const fn new(name: &'static str, signature: &'static str, lowering: Lowering) -> Self {
+        Self {
+            name,
+            signature,
+            lowering,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The parameters and return type, as they'd be written after the name of a function.
+    /// Generic item types are written as `T`, since they depend on the arguments.
+    pub fn signature(&self) -> &'static str {
+        self.signature
+    }
+
+    pub fn lowering(&self) -> Lowering {
+        self.lowering
+    }
+}
+
+/// Every builtin, sorted by name. A builtin which isn't listed here can't be called.
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("abs", "(value: Int) -> Int", Lowering::Expanded),
+    Builtin::new(
+        "agg_sig_me",
+        "(public_key: PublicKey, message: Bytes) -> Any[]",
+        Lowering::Condition(50),
+    ),
+    Builtin::new(
+        "agg_sig_unsafe",
+        "(public_key: PublicKey, message: Bytes) -> Any[]",
+        Lowering::Condition(49),
+    ),
+    Builtin::new(
+        "apply",
+        "(program: Any, env: Any) -> Any",
+        Lowering::Operator(Op::Apply),
+    ),
+    Builtin::new(
+        "assert_coin_announcement",
+        "(announcement_id: Bytes32) -> Any[]",
+        Lowering::Condition(61),
+    ),
+    Builtin::new(
+        "assert_height_absolute",
+        "(height: Int) -> Any[]",
+        Lowering::Condition(83),
+    ),
+    Builtin::new(
+        "assert_height_relative",
+        "(height: Int) -> Any[]",
+        Lowering::Condition(82),
+    ),
+    Builtin::new(
+        "assert_my_amount",
+        "(amount: Int) -> Any[]",
+        Lowering::Condition(73),
+    ),
+    Builtin::new(
+        "assert_my_coin_id",
+        "(coin_id: Bytes32) -> Any[]",
+        Lowering::Condition(70),
+    ),
+    Builtin::new(
+        "assert_my_parent_id",
+        "(parent_id: Bytes32) -> Any[]",
+        Lowering::Condition(71),
+    ),
+    Builtin::new(
+        "assert_my_puzzle_hash",
+        "(puzzle_hash: Bytes32) -> Any[]",
+        Lowering::Condition(72),
+    ),
+    Builtin::new(
+        "assert_puzzle_announcement",
+        "(announcement_id: Bytes32) -> Any[]",
+        Lowering::Condition(63),
+    ),
+    Builtin::new(
+        "assert_seconds_absolute",
+        "(seconds: Int) -> Any[]",
+        Lowering::Condition(81),
+    ),
+    Builtin::new(
+        "assert_seconds_relative",
+        "(seconds: Int) -> Any[]",
+        Lowering::Condition(80),
+    ),
+    Builtin::new(
+        "bit",
+        "(value: Int, index: Int) -> Bool",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "checked_div",
+        "(lhs: Int, rhs: Int) -> Int[]",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "clamp",
+        "(value: Int, low: Int, high: Int) -> Int",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "concat",
+        "(...values: Bytes) -> Bytes",
+        Lowering::Operator(Op::Concat),
+    ),
+    Builtin::new(
+        "contains",
+        "(list: T[], value: T) -> Bool",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "create_coin",
+        "(puzzle_hash: Bytes32, amount: Int) -> Any[]",
+        Lowering::Condition(51),
+    ),
+    Builtin::new(
+        "create_coin_announcement",
+        "(message: Bytes) -> Any[]",
+        Lowering::Condition(60),
+    ),
+    Builtin::new(
+        "create_puzzle_announcement",
+        "(message: Bytes) -> Any[]",
+        Lowering::Condition(62),
+    ),
+    Builtin::new("dbg", "(value: T) -> T", Lowering::Expanded),
+    Builtin::new(
+        "divmod",
+        "(lhs: Int, rhs: Int) -> (Int, Int)",
+        Lowering::Operator(Op::Divmod),
+    ),
+    Builtin::new("enumerate", "(list: T[]) -> (Int, T)[]", Lowering::Expanded),
+    Builtin::new("env", "() -> Any", Lowering::Expanded),
+    Builtin::new(
+        "guard",
+        "(condition: Bool, value: T, fallback: T) -> T",
+        Lowering::Expanded,
+    ),
+    Builtin::new("head", "(list: T[]) -> T[]", Lowering::Expanded),
+    Builtin::new(
+        "index_of",
+        "(list: T[], value: T) -> Int[]",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "keccak256",
+        "(bytes: Bytes) -> Bytes32",
+        Lowering::Operator(Op::Keccak256),
+    ),
+    Builtin::new("last", "(list: T[]) -> T[]", Lowering::Expanded),
+    Builtin::new(
+        "le_bytes",
+        "(value: Int, width: Int) -> Bytes",
+        Lowering::Expanded,
+    ),
+    Builtin::new("max", "(...values: Int) -> Int", Lowering::Expanded),
+    Builtin::new("min", "(...values: Int) -> Int", Lowering::Expanded),
+    Builtin::new("nth", "(list: T[], index: Int) -> T", Lowering::Expanded),
+    Builtin::new(
+        "nth_opt",
+        "(list: T[], index: Int) -> T[]",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "op",
+        "(opcode: Int, ...args: Any) -> Any",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "pad",
+        "(value: Bytes, width: Int) -> Bytes",
+        Lowering::Expanded,
+    ),
+    Builtin::new("pubkey", "(hex: Bytes) -> PublicKey", Lowering::Constant),
+    Builtin::new("require", "(condition: Bool) -> Nil", Lowering::Expanded),
+    Builtin::new(
+        "require_eq",
+        "(lhs: Int, rhs: Int) -> Nil",
+        Lowering::Expanded,
+    ),
+    Builtin::new(
+        "reserve_fee",
+        "(amount: Int) -> Any[]",
+        Lowering::Condition(52),
+    ),
+    Builtin::new("reverse", "(list: T[]) -> T[]", Lowering::Expanded),
+    Builtin::new(
+        "serialized_length",
+        "(value: Any) -> Int",
+        Lowering::Constant,
+    ),
+    Builtin::new(
+        "sha256",
+        "(bytes: Bytes) -> Bytes32",
+        Lowering::Operator(Op::Sha256),
+    ),
+    Builtin::new("sign", "(value: Int) -> Int", Lowering::Expanded),
+    Builtin::new("signature", "(hex: Bytes) -> Signature", Lowering::Constant),
+    Builtin::new(
+        "softfork",
+        "(cost: Int, value: Any) -> Nil",
+        Lowering::Operator(Op::Softfork),
+    ),
+    Builtin::new(
+        "substr",
+        "(value: Bytes, start: Int, end: Int) -> Bytes",
+        Lowering::Operator(Op::Substr),
+    ),
+    Builtin::new(
+        "substr_clamped",
+        "(value: Bytes, start: Int, end: Int) -> Bytes",
+        Lowering::Expanded,
+    ),
+    Builtin::new("tail", "(list: T[]) -> T[]", Lowering::Expanded),
+    Builtin::new(
+        "truncate",
+        "(value: Bytes, width: Int) -> Bytes",
+        Lowering::Expanded,
+    ),
+    Builtin::new("try", "(value: T, fallback: T) -> T", Lowering::Expanded),
+    Builtin::new("zip", "(a: A[], b: B[]) -> (A, B)[]", Lowering::Expanded),
+];
+
+/// Looks up a builtin by its name.
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS
+        .binary_search_by(|builtin| builtin.name.cmp(name))
+        .ok()
+        .map(|index| &BUILTINS[index])
+}
+
+/// The type of an operator's argument or result, which the lowerer resolves to the builtin type
+/// of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandType {
+    Any,
+    Bool,
+    Int,
+    Bytes,
+    Bytes32,
+    Nil,
+    Pair(&'static OperandType, &'static OperandType),
+}
+
+impl fmt::Display for OperandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "Any"),
+            Self::Bool => write!(f, "Bool"),
+            Self::Int => write!(f, "Int"),
+            Self::Bytes => write!(f, "Bytes"),
+            Self::Bytes32 => write!(f, "Bytes32"),
+            Self::Nil => write!(f, "Nil"),
+            Self::Pair(first, rest) => write!(f, "({first}, {rest})"),
+        }
+    }
+}
+
+/// Every operator which the compiler emits, in the same order as the table, so that codegen can
+/// only refer to an operator which exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Quote,
+    Apply,
+    If,
+    Cons,
+    First,
+    Rest,
+    Listp,
+    Raise,
+    Eq,
+    Sha256,
+    Substr,
+    Strlen,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Divmod,
+    Gt,
+    Ash,
+    Logand,
+    Not,
+    Any,
+    Softfork,
+    Keccak256,
+}
+
+impl Op {
+    /// The entry for this operator in the table.
+    pub fn operator(self) -> &'static Operator {
+        &OPERATORS[self as usize]
+    }
+}
+
+/// A CLVM operator which the compiler emits, with the types of the arguments it's called with.
+#[derive(Debug, Clone, Copy)]
+pub struct Operator {
+    op: Op,
+    name: &'static str,
+    opcode: u8,
+    arity: usize,
+    arg_types: &'static [OperandType],
+    result_type: OperandType,
+    variadic: bool,
+}
+
+impl Operator {
+    const fn new(
+        op: Op,
+        name: &'static str,
+        opcode: u8,
+        arg_types: &'static [OperandType],
+        result_type: OperandType,
+    ) -> Self {
+        Self {
+            op,
+            name,
+            opcode,
+            arity: arg_types.len(),
+            arg_types,
+            result_type,
+            variadic: false,
+        }
+    }
+
+    /// The last argument type can be repeated any number of times, including none.
+    const fn variadic(mut self) -> Self {
+        self.arity -= 1;
+        self.variadic = true;
+        self
+    }
+
+    pub fn op(&self) -> Op {
+        self.op
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    /// The number of arguments, or the least number of arguments if it's variadic.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn arg_types(&self) -> &'static [OperandType] {
+        self.arg_types
+    }
+
+    /// The type of the argument at this index, repeating the last type if it's variadic.
+    pub fn arg_type(&self, index: usize) -> Option<OperandType> {
+        match self.arg_types.get(index) {
+            Some(&arg_type) => Some(arg_type),
+            None if self.variadic => self.arg_types.last().copied(),
+            None => None,
+        }
+    }
+
+    pub fn result_type(&self) -> OperandType {
+        self.result_type
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.variadic
+    }
+
+    /// Whether the operator can be called with this many arguments.
+    pub fn accepts(&self, count: usize) -> bool {
+        if self.variadic {
+            count >= self.arity
+        } else {
+            count == self.arity
+        }
+    }
+}
+
+/// Every operator which the compiler emits, sorted by opcode.
+pub const OPERATORS: &[Operator] = {
+    use OperandType::{Any, Bool, Bytes, Bytes32, Int, Nil};
+
+    &[
+        Operator::new(Op::Quote, "q", 1, &[Any], Any),
+        Operator::new(Op::Apply, "a", 2, &[Any, Any], Any),
+        Operator::new(Op::If, "i", 3, &[Bool, Any, Any], Any),
+        Operator::new(Op::Cons, "c", 4, &[Any, Any], Any),
+        Operator::new(Op::First, "f", 5, &[Any], Any),
+        Operator::new(Op::Rest, "r", 6, &[Any], Any),
+        Operator::new(Op::Listp, "l", 7, &[Any], Bool),
+        Operator::new(Op::Raise, "x", 8, &[Any], Any).variadic(),
+        Operator::new(Op::Eq, "=", 9, &[Any, Any], Bool),
+        Operator::new(Op::Sha256, "sha256", 11, &[Bytes], Bytes32),
+        Operator::new(Op::Substr, "substr", 12, &[Bytes, Int, Int], Bytes),
+        Operator::new(Op::Strlen, "strlen", 13, &[Bytes], Int),
+        Operator::new(Op::Concat, "concat", 14, &[Bytes], Bytes).variadic(),
+        Operator::new(Op::Add, "+", 16, &[Int], Int).variadic(),
+        Operator::new(Op::Sub, "-", 17, &[Int], Int).variadic(),
+        Operator::new(Op::Mul, "*", 18, &[Int], Int).variadic(),
+        Operator::new(Op::Div, "/", 19, &[Int, Int], Int),
+        Operator::new(
+            Op::Divmod,
+            "divmod",
+            20,
+            &[Int, Int],
+            OperandType::Pair(&Int, &Int),
+        ),
+        Operator::new(Op::Gt, ">", 21, &[Int, Int], Bool),
+        Operator::new(Op::Ash, "ash", 22, &[Int, Int], Int),
+        Operator::new(Op::Logand, "logand", 24, &[Int], Int).variadic(),
+        Operator::new(Op::Not, "not", 32, &[Bool], Bool),
+        Operator::new(Op::Any, "any", 33, &[Bool], Bool).variadic(),
+        Operator::new(Op::Softfork, "softfork", 36, &[Int, Any, Any, Any], Nil),
+        Operator::new(Op::Keccak256, "keccak256", 62, &[Bytes], Bytes32),
+    ]
+};
+
+/// Looks up an operator by its name.
+pub fn operator(name: &str) -> Option<&'static Operator> {
+    OPERATORS.iter().find(|operator| operator.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operators_sorted() {
+        for pair in OPERATORS.windows(2) {
+            assert!(pair[0].opcode < pair[1].opcode, "{}", pair[1].name);
+        }
+    }
+
+    #[test]
+    fn test_operator_arity() {
+        let cons = operator("c").unwrap();
+        assert!(cons.accepts(2));
+        assert!(!cons.accepts(1));
+        assert!(!cons.accepts(3));
+
+        let add = operator("+").unwrap();
+        assert!(add.accepts(0));
+        assert!(add.accepts(5));
+
+        let substr = operator("substr").unwrap();
+        assert_eq!(substr.arity(), 3);
+        assert!(!substr.is_variadic());
+    }
+
+    /// Codegen finds an operator by its position in the table, so it must match the order of `Op`.
+    #[test]
+    fn test_operator_order() {
+        for (index, operator) in OPERATORS.iter().enumerate() {
+            assert_eq!(operator.op as usize, index, "{}", operator.name);
+            assert_eq!(operator.op.operator().opcode, operator.opcode);
+        }
+    }
+
+    #[test]
+    fn test_operator_types() {
+        let substr = Op::Substr.operator();
+        assert_eq!(
+            substr.arg_types(),
+            [OperandType::Bytes, OperandType::Int, OperandType::Int]
+        );
+        assert_eq!(substr.result_type(), OperandType::Bytes);
+        assert_eq!(substr.arg_type(3), None);
+
+        let concat = Op::Concat.operator();
+        assert_eq!(concat.arg_type(0), Some(OperandType::Bytes));
+        assert_eq!(concat.arg_type(5), Some(OperandType::Bytes));
+
+        assert_eq!(
+            Op::Divmod.operator().result_type().to_string(),
+            "(Int, Int)"
+        );
+    }
+
+    /// A builtin which is compiled to a single operator is type checked with the operator's
+    /// types, so its signature needs to agree with them.
+    #[test]
+    fn test_builtin_operator_types() {
+        for builtin in BUILTINS {
+            let Lowering::Operator(op) = builtin.lowering else {
+                continue;
+            };
+            let operator = op.operator();
+
+            let (params, result) = builtin.signature[1..].split_once(") -> ").unwrap();
+            assert_eq!(result, operator.result_type.to_string(), "{}", builtin.name);
+
+            for (index, param) in params.split(", ").enumerate() {
+                let (name, ty) = param.split_once(": ").unwrap();
+                assert_eq!(
+                    name.starts_with("..."),
+                    operator.variadic,
+                    "{}",
+                    builtin.name
+                );
+                assert_eq!(
+                    operator.arg_type(index).map(|ty| ty.to_string()).as_deref(),
+                    Some(ty),
+                    "{}",
+                    builtin.name
+                );
+            }
+        }
+    }
+}
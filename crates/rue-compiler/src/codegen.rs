@@ -1,119 +1,100 @@
+use std::collections::HashMap;
+
 use clvmr::{Allocator, NodePtr};
 
 use crate::{
+    builtins::{Op, OPERATORS},
     database::{Database, LirId},
     lir::Lir,
 };
 
+/// Generates CLVM from the LIR that the optimizer produces, which is its only input.
 pub struct Codegen<'a> {
     db: &'a mut Database,
     allocator: &'a mut Allocator,
-    ops: Ops,
-}
-
-struct Ops {
-    q: NodePtr,
-    a: NodePtr,
-    i: NodePtr,
-    c: NodePtr,
-    f: NodePtr,
-    r: NodePtr,
-    l: NodePtr,
-    x: NodePtr,
-    eq: NodePtr,
-    sha256: NodePtr,
-    strlen: NodePtr,
-    concat: NodePtr,
-    add: NodePtr,
-    sub: NodePtr,
-    mul: NodePtr,
-    div: NodePtr,
-    divmod: NodePtr,
-    gt: NodePtr,
-    not: NodePtr,
-    any: NodePtr,
+    /// The opcode of each operator, in the same order as the table.
+    ops: Vec<NodePtr>,
+    nodes: HashMap<LirId, NodePtr>,
 }
 
 impl<'a> Codegen<'a> {
     pub fn new(db: &'a mut Database, allocator: &'a mut Allocator) -> Self {
-        let ops = Ops {
-            q: allocator.one(),
-            a: allocator.new_small_number(2).unwrap(),
-            i: allocator.new_small_number(3).unwrap(),
-            c: allocator.new_small_number(4).unwrap(),
-            f: allocator.new_small_number(5).unwrap(),
-            r: allocator.new_small_number(6).unwrap(),
-            l: allocator.new_small_number(7).unwrap(),
-            x: allocator.new_small_number(8).unwrap(),
-            eq: allocator.new_small_number(9).unwrap(),
-            sha256: allocator.new_small_number(11).unwrap(),
-            strlen: allocator.new_small_number(13).unwrap(),
-            concat: allocator.new_small_number(14).unwrap(),
-            add: allocator.new_small_number(16).unwrap(),
-            sub: allocator.new_small_number(17).unwrap(),
-            mul: allocator.new_small_number(18).unwrap(),
-            div: allocator.new_small_number(19).unwrap(),
-            divmod: allocator.new_small_number(20).unwrap(),
-            gt: allocator.new_small_number(21).unwrap(),
-            not: allocator.new_small_number(32).unwrap(),
-            any: allocator.new_small_number(33).unwrap(),
-        };
-        Self { db, allocator, ops }
+        let ops = OPERATORS
+            .iter()
+            .map(|operator| {
+                allocator
+                    .new_small_number(operator.opcode().into())
+                    .unwrap()
+            })
+            .collect();
+        Self {
+            db,
+            allocator,
+            ops,
+            nodes: HashMap::new(),
+        }
     }
 
     pub fn gen_lir(&mut self, lir_id: LirId) -> NodePtr {
+        // Shared LIR nodes, such as function definitions, are generated once and reused.
+        if let Some(&node_ptr) = self.nodes.get(&lir_id) {
+            return node_ptr;
+        }
+
+        let node_ptr = self.gen_lir_uncached(lir_id);
+        self.nodes.insert(lir_id, node_ptr);
+        node_ptr
+    }
+
+    fn gen_lir_uncached(&mut self, lir_id: LirId) -> NodePtr {
         match self.db.lir(lir_id).clone() {
             Lir::Atom(atom) => self.gen_atom(atom.clone()),
-            Lir::Pair(first, rest) => self.gen_pair(first, rest),
+            Lir::Pair(first, rest) => self.gen_operator(Op::Cons, &[first, rest]),
             Lir::Path(path) => self.allocator.new_small_number(path).unwrap(),
-            Lir::Run(program, args) => self.gen_run(program, args),
+            Lir::Run(program, args) => self.gen_operator(Op::Apply, &[program, args]),
             Lir::Curry(body, args) => self.gen_apply(body, args),
             Lir::Closure(body, args) => self.gen_closure(body, args),
             Lir::FunctionBody(body) => self.gen_quote(body),
-            Lir::First(value) => self.gen_first(value),
-            Lir::Rest(value) => self.gen_rest(value),
-            Lir::Raise(value) => self.gen_raise(value),
-            Lir::Sha256(value) => self.gen_sha256(value),
-            Lir::IsCons(value) => self.gen_is_cons(value),
-            Lir::Strlen(value) => self.gen_strlen(value),
-            Lir::Concat(values) => self.gen_concat(values),
+            Lir::First(value) => self.gen_operator(Op::First, &[value]),
+            Lir::Rest(value) => self.gen_operator(Op::Rest, &[value]),
+            Lir::Raise(value) => self.gen_operator(Op::Raise, value.as_slice()),
+            Lir::Sha256(value) => self.gen_operator(Op::Sha256, &[value]),
+            Lir::Keccak256(value) => self.gen_operator(Op::Keccak256, &[value]),
+            Lir::IsCons(value) => self.gen_operator(Op::Listp, &[value]),
+            Lir::Strlen(value) => self.gen_operator(Op::Strlen, &[value]),
+            Lir::Substr(value, start, end) => self.gen_operator(Op::Substr, &[value, start, end]),
+            Lir::Softfork(cost, program) => self.gen_softfork(cost, program),
+            Lir::Op(opcode, args) => self.gen_op(opcode, args),
+            Lir::Concat(values) => self.gen_operator(Op::Concat, &values),
             Lir::If(condition, then_branch, else_branch) => {
                 self.gen_if(condition, then_branch, else_branch)
             }
-            Lir::Not(value) => self.gen_not(value),
-            Lir::Any(values) => self.gen_any(values),
-            Lir::Add(values) => self.gen_add(values),
-            Lir::Sub(values) => self.gen_sub(values),
-            Lir::Mul(values) => self.gen_mul(values),
-            Lir::Div(lhs, rhs) => self.gen_div(lhs, rhs),
-            Lir::Divmod(lhs, rhs) => self.gen_divmod(lhs, rhs),
-            Lir::Eq(lhs, rhs) => self.gen_eq(lhs, rhs),
-            Lir::Gt(lhs, rhs) => self.gen_gt(lhs, rhs),
+            Lir::Dispatch(path, branches) => self.gen_dispatch(path, branches),
+            Lir::Not(value) => self.gen_operator(Op::Not, &[value]),
+            Lir::Any(values) => self.gen_operator(Op::Any, &values),
+            Lir::Add(values) => self.gen_operator(Op::Add, &values),
+            Lir::Sub(values) => self.gen_operator(Op::Sub, &values),
+            Lir::Mul(values) => self.gen_operator(Op::Mul, &values),
+            Lir::Div(lhs, rhs) => self.gen_operator(Op::Div, &[lhs, rhs]),
+            Lir::Divmod(lhs, rhs) => self.gen_operator(Op::Divmod, &[lhs, rhs]),
+            Lir::Eq(lhs, rhs) => self.gen_operator(Op::Eq, &[lhs, rhs]),
+            Lir::Gt(lhs, rhs) => self.gen_operator(Op::Gt, &[lhs, rhs]),
+            Lir::Ash(value, shift) => self.gen_operator(Op::Ash, &[value, shift]),
+            Lir::Logand(lhs, rhs) => self.gen_operator(Op::Logand, &[lhs, rhs]),
         }
     }
 
+    /// The atom is allocated with its exact bytes, so leading zeros in byte literals are kept.
     fn gen_atom(&mut self, value: Vec<u8>) -> NodePtr {
         let int_ptr = self.allocator.new_atom(&value).unwrap();
         self.quote(int_ptr)
     }
 
-    fn gen_pair(&mut self, first: LirId, rest: LirId) -> NodePtr {
-        let first = self.gen_lir(first);
-        let rest = self.gen_lir(rest);
-        self.list(&[self.ops.c, first, rest])
-    }
-
-    fn gen_run(&mut self, program: LirId, args: LirId) -> NodePtr {
-        let program = self.gen_lir(program);
-        let args = self.gen_lir(args);
-        self.list(&[self.ops.a, program, args])
-    }
-
     fn gen_apply(&mut self, body: LirId, args: Vec<LirId>) -> NodePtr {
         let body = self.gen_quote(body);
         let args: Vec<NodePtr> = args.into_iter().map(|arg| self.gen_lir(arg)).collect();
-        let args = self.runtime_list(&args, self.ops.q);
-        self.list(&[self.ops.a, body, args])
+        let args = self.runtime_list(&args, self.op(Op::Quote));
+        self.list(&[self.op(Op::Apply), body, args])
     }
 
     fn gen_closure(&mut self, body: LirId, args: Vec<LirId>) -> NodePtr {
@@ -127,42 +108,38 @@ impl<'a> Codegen<'a> {
         self.quote(body)
     }
 
-    fn gen_first(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.f, value])
+    fn gen_softfork(&mut self, cost: LirId, program: LirId) -> NodePtr {
+        let cost = self.gen_lir(cost);
+        let extension = self.quote(NodePtr::NIL);
+        let program = self.gen_quote(program);
+
+        // The program is run with the current environment, so it can refer to anything in scope.
+        self.list(&[
+            self.op(Op::Softfork),
+            cost,
+            extension,
+            program,
+            self.op(Op::Quote),
+        ])
     }
 
-    fn gen_rest(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.r, value])
-    }
+    /// Generates a call to an operator from the table, with each of its arguments in order.
+    fn gen_operator(&mut self, op: Op, values: &[LirId]) -> NodePtr {
+        debug_assert!(
+            op.operator().accepts(values.len()),
+            "wrong number of arguments for {}",
+            op.operator().name()
+        );
 
-    fn gen_raise(&mut self, value: Option<LirId>) -> NodePtr {
-        if let Some(value) = value {
-            let value = self.gen_lir(value);
-            self.list(&[self.ops.x, value])
-        } else {
-            self.list(&[self.ops.x])
+        let mut args = vec![self.op(op)];
+        for &value in values {
+            args.push(self.gen_lir(value));
         }
+        self.list(&args)
     }
 
-    fn gen_sha256(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.sha256, value])
-    }
-
-    fn gen_is_cons(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.l, value])
-    }
-
-    fn gen_strlen(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.strlen, value])
-    }
-
-    fn gen_concat(&mut self, values: Vec<LirId>) -> NodePtr {
-        let mut args = vec![self.ops.concat];
+    fn gen_op(&mut self, opcode: Vec<u8>, values: Vec<LirId>) -> NodePtr {
+        let mut args = vec![self.allocator.new_atom(&opcode).unwrap()];
         for value in values {
             args.push(self.gen_lir(value));
         }
@@ -177,73 +154,27 @@ impl<'a> Codegen<'a> {
         let then_branch = self.quote(then_branch);
         let else_branch = self.quote(else_branch);
 
-        let conditional = self.list(&[self.ops.i, condition, then_branch, else_branch]);
-        self.list(&[self.ops.a, conditional, self.ops.q])
-    }
-
-    fn gen_not(&mut self, value: LirId) -> NodePtr {
-        let value = self.gen_lir(value);
-        self.list(&[self.ops.not, value])
-    }
-
-    fn gen_any(&mut self, values: Vec<LirId>) -> NodePtr {
-        let mut args = vec![self.ops.any];
-        for value in values {
-            args.push(self.gen_lir(value));
-        }
-        self.list(&args)
-    }
-
-    fn gen_add(&mut self, values: Vec<LirId>) -> NodePtr {
-        let mut args = vec![self.ops.add];
-        for value in values {
-            args.push(self.gen_lir(value));
-        }
-        self.list(&args)
+        let conditional = self.list(&[self.op(Op::If), condition, then_branch, else_branch]);
+        self.list(&[self.op(Op::Apply), conditional, self.op(Op::Quote)])
     }
 
-    fn gen_sub(&mut self, values: Vec<LirId>) -> NodePtr {
-        let mut args = vec![self.ops.sub];
-        for value in values {
-            args.push(self.gen_lir(value));
-        }
-        self.list(&args)
-    }
+    fn gen_dispatch(&mut self, path: LirId, branches: Vec<LirId>) -> NodePtr {
+        let path = self.gen_lir(path);
 
-    fn gen_mul(&mut self, values: Vec<LirId>) -> NodePtr {
-        let mut args = vec![self.ops.mul];
-        for value in values {
-            args.push(self.gen_lir(value));
+        let mut table = NodePtr::NIL;
+        for branch in branches.into_iter().rev() {
+            let branch = self.gen_lir(branch);
+            table = self.allocator.new_pair(branch, table).unwrap();
         }
-        self.list(&args)
-    }
-
-    fn gen_div(&mut self, lhs: LirId, rhs: LirId) -> NodePtr {
-        let lhs = self.gen_lir(lhs);
-        let rhs = self.gen_lir(rhs);
-        self.list(&[self.ops.div, lhs, rhs])
-    }
-
-    fn gen_divmod(&mut self, lhs: LirId, rhs: LirId) -> NodePtr {
-        let lhs = self.gen_lir(lhs);
-        let rhs = self.gen_lir(rhs);
-        self.list(&[self.ops.divmod, lhs, rhs])
-    }
-
-    fn gen_gt(&mut self, lhs: LirId, rhs: LirId) -> NodePtr {
-        let lhs = self.gen_lir(lhs);
-        let rhs = self.gen_lir(rhs);
-        self.list(&[self.ops.gt, lhs, rhs])
-    }
+        let table = self.quote(table);
 
-    fn gen_eq(&mut self, lhs: LirId, rhs: LirId) -> NodePtr {
-        let lhs = self.gen_lir(lhs);
-        let rhs = self.gen_lir(rhs);
-        self.list(&[self.ops.eq, lhs, rhs])
+        // Running the path as a program with the table as its environment selects the branch.
+        let branch = self.list(&[self.op(Op::Apply), path, table]);
+        self.list(&[self.op(Op::Apply), branch, self.op(Op::Quote)])
     }
 
     fn gen_closure_wrapper(&mut self, body: NodePtr, captures: &[NodePtr]) -> NodePtr {
-        let runtime_a = self.quote(self.ops.a);
+        let runtime_a = self.quote(self.op(Op::Apply));
         let runtime_quoted_body = self.runtime_quote(body);
 
         let mut args = Vec::new();
@@ -253,7 +184,7 @@ impl<'a> Codegen<'a> {
             args.push(runtime_quoted_arg);
         }
 
-        let quoted_one = self.quote(self.ops.q);
+        let quoted_one = self.quote(self.op(Op::Quote));
         let runtime_args = self.runtime_runtime_list(&args, quoted_one);
 
         self.runtime_list(
@@ -262,11 +193,15 @@ impl<'a> Codegen<'a> {
         )
     }
 
+    fn op(&self, op: Op) -> NodePtr {
+        self.ops[op as usize]
+    }
+
     fn quote(&mut self, ptr: NodePtr) -> NodePtr {
         if ptr.is_atom() && self.allocator.atom(ptr).as_ref().is_empty() {
             return ptr;
         }
-        self.allocator.new_pair(self.ops.q, ptr).unwrap()
+        self.allocator.new_pair(self.op(Op::Quote), ptr).unwrap()
     }
 
     fn list(&mut self, items: &[NodePtr]) -> NodePtr {
@@ -281,7 +216,7 @@ impl<'a> Codegen<'a> {
         let mut ptr = end;
 
         for &item in items.iter().rev() {
-            ptr = self.list(&[self.ops.c, item, ptr]);
+            ptr = self.list(&[self.op(Op::Cons), item, ptr]);
         }
         ptr
     }
@@ -289,7 +224,7 @@ impl<'a> Codegen<'a> {
     fn runtime_runtime_list(&mut self, items: &[NodePtr], end: NodePtr) -> NodePtr {
         let mut ptr = end;
 
-        let quoted_c = self.quote(self.ops.c);
+        let quoted_c = self.quote(self.op(Op::Cons));
         for &item in items.iter().rev() {
             ptr = self.runtime_list(&[quoted_c, item, ptr], NodePtr::NIL);
         }
@@ -297,7 +232,80 @@ impl<'a> Codegen<'a> {
     }
 
     fn runtime_quote(&mut self, ptr: NodePtr) -> NodePtr {
-        let quoted_q = self.quote(self.ops.q);
-        self.list(&[self.ops.c, quoted_q, ptr])
+        let quoted_q = self.quote(self.op(Op::Quote));
+        self.list(&[self.op(Op::Cons), quoted_q, ptr])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use clvmr::SExp;
+
+    use crate::lir::Lir;
+
+    use super::*;
+
+    #[test]
+    fn test_operator_opcodes() {
+        for operator in OPERATORS {
+            let mut db = Database::default();
+            let mut allocator = Allocator::new();
+
+            let count = operator.arity() + usize::from(operator.is_variadic());
+            let args: Vec<LirId> = (0..count).map(|_| db.alloc_lir(Lir::Path(2))).collect();
+
+            let mut codegen = Codegen::new(&mut db, &mut allocator);
+            let ptr = codegen.gen_operator(operator.op(), &args);
+
+            let SExp::Pair(opcode, mut rest) = allocator.sexp(ptr) else {
+                panic!("{} isn't a call", operator.name());
+            };
+            assert_eq!(
+                allocator.atom(opcode).as_ref(),
+                [operator.opcode()],
+                "{}",
+                operator.name()
+            );
+
+            let mut len = 0;
+            while let SExp::Pair(_, next) = allocator.sexp(rest) {
+                len += 1;
+                rest = next;
+            }
+            assert_eq!(len, count, "{}", operator.name());
+        }
+    }
+
+    #[test]
+    fn test_shared_nodes() {
+        let mut db = Database::default();
+        let mut allocator = Allocator::new();
+
+        // Each level refers to the one below twice, so there would be 2^64 leaves to generate if
+        // shared nodes weren't reused.
+        let mut lir_id = db.alloc_lir(Lir::Path(2));
+        for _ in 0..64 {
+            lir_id = db.alloc_lir(Lir::Pair(lir_id, lir_id));
+        }
+
+        let mut codegen = Codegen::new(&mut db, &mut allocator);
+        let first = codegen.gen_lir(lir_id);
+        let second = codegen.gen_lir(lir_id);
+        assert_eq!(first, second);
+
+        // Every distinct node is only reached once, however many parents it has. Each level is a
+        // call to `c`, which is a list of three pairs, and the only atoms are `c`, nil and the path.
+        let mut nodes = HashSet::new();
+        let mut stack = vec![first];
+        while let Some(node_ptr) = stack.pop() {
+            if nodes.insert(node_ptr) {
+                if let SExp::Pair(first, rest) = allocator.sexp(node_ptr) {
+                    stack.extend([first, rest]);
+                }
+            }
+        }
+        assert_eq!(nodes.len(), 64 * 3 + 3);
     }
 }
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+
+use rowan::{NodeOrToken, TextRange, TextSize};
+use rue_parser::{
+    parse, AstNode, Block, FunctionItem, Item, LambdaExpr, MatchArm, Root, Stmt, SyntaxKind,
+    SyntaxNode, SyntaxToken, Type,
+};
+
+/// What kind of name a completion is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionKind {
+    Function,
+    Parameter,
+    Let,
+    Const,
+    Type,
+    Keyword,
+}
+
+/// A name which can be written at a position in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Completion {
+    name: String,
+    kind: CompletionKind,
+    ty: Option<String>,
+}
+
+impl Completion {
+    pub fn new(name: String, kind: CompletionKind, ty: Option<String>) -> Self {
+        Self { name, kind, ty }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> CompletionKind {
+        self.kind
+    }
+
+    /// The type of the symbol as it's written in the source, if it has one.
+    pub fn ty(&self) -> Option<&str> {
+        self.ty.as_deref()
+    }
+}
+
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "let", "return", "raise", "if", "assert", "break", "match", "fun", "nil", "true", "false",
+];
+
+const ITEM_KEYWORDS: &[&str] = &[
+    "pub",
+    "fun",
+    "type",
+    "struct",
+    "enum",
+    "const",
+    "static_assert",
+];
+
+/// Lists the symbols which are in scope at a byte offset into the source, from the innermost
+/// scope outwards, followed by the keywords which can be written there. Only the syntax is used,
+/// so the source doesn't need to compile, and types are given as they're written.
+pub fn completions_at(source: &str, offset: usize) -> Vec<Completion> {
+    let (root, _errors) = parse(source);
+
+    let offset = TextSize::try_from(offset.min(source.len())).unwrap();
+    let node = match root.syntax().covering_element(TextRange::empty(offset)) {
+        NodeOrToken::Node(node) => node,
+        NodeOrToken::Token(token) => token.parent().unwrap(),
+    };
+
+    let mut completions = Completions::default();
+    let mut in_block = false;
+
+    for node in node.ancestors() {
+        // Trailing whitespace and comments are part of a node, but not inside of its scope.
+        if node.kind() != SyntaxKind::Root {
+            let range = significant_range(&node);
+            if offset <= range.start() || offset >= range.end() {
+                continue;
+            }
+        }
+
+        if let Some(block) = Block::cast(node.clone()) {
+            in_block = true;
+            completions.block(block, offset);
+        } else if let Some(function) = FunctionItem::cast(node.clone()) {
+            for param in function.params() {
+                completions.add(param.name(), CompletionKind::Parameter, param.ty());
+            }
+        } else if let Some(lambda) = LambdaExpr::cast(node.clone()) {
+            for param in lambda.params() {
+                completions.add(param.name(), CompletionKind::Parameter, param.ty());
+            }
+        } else if let Some(arm) = MatchArm::cast(node.clone()) {
+            let bindings = arm.pattern().and_then(|pattern| pattern.bindings());
+            for binding in bindings.unwrap_or_default() {
+                completions.add(Some(binding), CompletionKind::Let, None);
+            }
+        } else if let Some(root) = Root::cast(node) {
+            completions.items(root.items());
+        }
+    }
+
+    let keywords = if in_block {
+        STATEMENT_KEYWORDS
+    } else {
+        ITEM_KEYWORDS
+    };
+
+    for keyword in keywords {
+        completions.list.push(Completion::new(
+            keyword.to_string(),
+            CompletionKind::Keyword,
+            None,
+        ));
+    }
+
+    completions.list
+}
+
+#[derive(Default)]
+struct Completions {
+    list: Vec<Completion>,
+    /// The names which have already been added, so that outer scopes don't repeat shadowed names.
+    /// Types are kept separately from values, since they don't shadow each other.
+    seen: HashSet<(String, bool)>,
+}
+
+impl Completions {
+    fn add(&mut self, name: Option<SyntaxToken>, kind: CompletionKind, ty: Option<Type>) {
+        let ty = ty.map(|ty| type_text(ty.syntax()));
+        self.add_text(name, kind, ty);
+    }
+
+    fn add_text(&mut self, name: Option<SyntaxToken>, kind: CompletionKind, ty: Option<String>) {
+        let Some(name) = name else {
+            return;
+        };
+
+        let key = (name.text().to_string(), kind == CompletionKind::Type);
+
+        if self.seen.insert(key) {
+            self.list
+                .push(Completion::new(name.text().to_string(), kind, ty));
+        }
+    }
+
+    fn block(&mut self, block: Block, offset: TextSize) {
+        // Later bindings shadow earlier ones, and only those before the offset are in scope.
+        for stmt in block.stmts().into_iter().rev() {
+            let Stmt::LetStmt(let_stmt) = stmt else {
+                continue;
+            };
+
+            if significant_range(let_stmt.syntax()).end() > offset {
+                continue;
+            }
+
+            if let Some(tuple_pattern) = let_stmt.tuple_pattern() {
+                for name in tuple_pattern.names().into_iter().rev() {
+                    self.add(Some(name), CompletionKind::Let, None);
+                }
+            } else if let Some(struct_pattern) = let_stmt.struct_pattern() {
+                for name in struct_pattern.fields().into_iter().rev() {
+                    self.add(Some(name), CompletionKind::Let, None);
+                }
+            } else {
+                self.add(let_stmt.name(), CompletionKind::Let, let_stmt.ty());
+            }
+        }
+
+        self.items(block.items());
+    }
+
+    fn items(&mut self, items: Vec<Item>) {
+        for item in items {
+            match item {
+                Item::FunctionItem(function) => {
+                    let ty = function_type_text(&function);
+                    self.add_text(function.name(), CompletionKind::Function, ty);
+                }
+                Item::ConstItem(const_item) => {
+                    self.add(const_item.name(), CompletionKind::Const, const_item.ty());
+                }
+                Item::TypeAliasItem(alias) => {
+                    self.add(alias.name(), CompletionKind::Type, alias.ty());
+                }
+                Item::StructItem(struct_item) => {
+                    self.add(struct_item.name(), CompletionKind::Type, None);
+                }
+                Item::EnumItem(enum_item) => {
+                    self.add(enum_item.name(), CompletionKind::Type, None);
+                }
+                Item::StaticAssertItem(..) | Item::ImplItem(..) | Item::TestItem(..) => {}
+            }
+        }
+    }
+}
+
+/// Writes the signature of a function as a function type, such as `fun(Int) -> Bool`.
+fn function_type_text(function: &FunctionItem) -> Option<String> {
+    let mut params = Vec::new();
+
+    for param in function.params() {
+        let ty = type_text(param.ty()?.syntax());
+        if param.spread().is_some() {
+            params.push(format!("...{ty}"));
+        } else {
+            params.push(ty);
+        }
+    }
+
+    let return_type = type_text(function.return_type()?.syntax());
+    Some(format!("fun({}) -> {return_type}", params.join(", ")))
+}
+
+/// The range of a node, from the start of its first token to the end of its last, ignoring trivia.
+fn significant_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .filter(|token| {
+            !matches!(
+                token.kind(),
+                SyntaxKind::Whitespace | SyntaxKind::LineComment | SyntaxKind::BlockComment
+            )
+        });
+
+    let Some(first) = tokens.next() else {
+        return TextRange::empty(node.text_range().start());
+    };
+    let last = tokens.last().unwrap_or_else(|| first.clone());
+    TextRange::new(first.text_range().start(), last.text_range().end())
+}
+
+fn type_text(node: &SyntaxNode) -> String {
+    node.text().to_string().trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(completions: &[Completion], kind: CompletionKind) -> Vec<&str> {
+        completions
+            .iter()
+            .filter(|completion| completion.kind() == kind)
+            .map(Completion::name)
+            .collect()
+    }
+
+    #[test]
+    fn test_function_body() {
+        let source = "fun main(value: Int, flag: Bool) -> Int {\n    let doubled = value * 2;\n    \n    let later = 1;\n    doubled\n}\n\nconst LIMIT: Int = 10;\n\nfun helper(...items: Int[]) -> Int { 0 }\n";
+        let offset = source.find("    \n").unwrap() + 4;
+        let completions = completions_at(source, offset);
+
+        assert_eq!(
+            names(&completions, CompletionKind::Parameter),
+            ["value", "flag"]
+        );
+        assert_eq!(names(&completions, CompletionKind::Let), ["doubled"]);
+        assert_eq!(names(&completions, CompletionKind::Const), ["LIMIT"]);
+        assert_eq!(
+            names(&completions, CompletionKind::Function),
+            ["main", "helper"]
+        );
+
+        let value = completions
+            .iter()
+            .find(|completion| completion.name() == "value")
+            .unwrap();
+        assert_eq!(value.ty(), Some("Int"));
+
+        let helper = completions
+            .iter()
+            .find(|completion| completion.name() == "helper")
+            .unwrap();
+        assert_eq!(helper.ty(), Some("fun(...Int[]) -> Int"));
+
+        assert!(names(&completions, CompletionKind::Keyword).contains(&"let"));
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let source = "fun main(value: Int) -> Int {\n    let value: Bool = value > 0;\n    0\n}\n";
+        let offset = source.find("0\n}").unwrap();
+        let completions = completions_at(source, offset);
+
+        let value: Vec<&Completion> = completions
+            .iter()
+            .filter(|completion| completion.name() == "value")
+            .collect();
+        assert_eq!(
+            value,
+            [&Completion::new(
+                "value".to_string(),
+                CompletionKind::Let,
+                Some("Bool".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_top_level() {
+        let source = "fun main(value: Int) -> Int { value }\n\n";
+        let completions = completions_at(source, source.len());
+
+        assert!(names(&completions, CompletionKind::Parameter).is_empty());
+        assert_eq!(names(&completions, CompletionKind::Function), ["main"]);
+        assert!(names(&completions, CompletionKind::Keyword).contains(&"struct"));
+    }
+}
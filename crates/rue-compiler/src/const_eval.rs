@@ -0,0 +1,625 @@
+use std::{cell::Cell, collections::HashMap};
+
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+use crate::{
+    database::{Database, HirId, LirId, ScopeId, SymbolId},
+    eval_order::may_fail,
+    hir::{Hir, HirBinaryOp},
+    lir::Lir,
+    lowerer::bigint_to_bytes,
+    symbol::Symbol,
+};
+
+/// Evaluation was stopped because the value is nested more deeply than the limit, such as a
+/// long chain of constants which each refer to the last, rather than overflowing the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TooDeep;
+
+/// Evaluates a HIR expression to an atom at compile time, following CLVM semantics.
+/// Returns `None` if the value depends on anything that isn't known until runtime.
+/// Calls are only evaluated if they are to one of the builtins.
+pub(crate) fn eval_hir(
+    db: &Database,
+    builtins: ScopeId,
+    max_depth: usize,
+    hir_id: HirId,
+) -> Result<Option<Vec<u8>>, TooDeep> {
+    let evaluator = Evaluator::new(db, builtins, max_depth, None);
+    let value = evaluator.eval(hir_id);
+    evaluator.finish(value)
+}
+
+/// Evaluates an expression which refers to the parameters of a function, such as its `where`
+/// clause, given the values of the parameters which are known.
+pub(crate) fn eval_hir_with_params(
+    db: &Database,
+    builtins: ScopeId,
+    max_depth: usize,
+    hir_id: HirId,
+    params: HashMap<SymbolId, Vec<u8>>,
+) -> Result<Option<Vec<u8>>, TooDeep> {
+    let evaluator = Evaluator::new(db, builtins, max_depth, Some(params));
+    let value = evaluator.eval(hir_id);
+    evaluator.finish(value)
+}
+
+/// The length of a constant value once it's serialized, which may be a tree of pairs rather than
+/// only an atom. Returns `None` if any part of the value isn't known until runtime.
+pub(crate) fn eval_serialized_length(
+    db: &Database,
+    builtins: ScopeId,
+    max_depth: usize,
+    hir_id: HirId,
+) -> Result<Option<usize>, TooDeep> {
+    let evaluator = Evaluator::new(db, builtins, max_depth, None);
+    let length = evaluator.serialized_length(hir_id);
+    evaluator.finish(length)
+}
+
+struct Evaluator<'a> {
+    db: &'a Database,
+    builtins: ScopeId,
+    /// The argument values while evaluating the body of a called function.
+    params: Option<HashMap<SymbolId, Vec<u8>>>,
+    max_depth: usize,
+    depth: Cell<usize>,
+    too_deep: Cell<bool>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(
+        db: &'a Database,
+        builtins: ScopeId,
+        max_depth: usize,
+        params: Option<HashMap<SymbolId, Vec<u8>>>,
+    ) -> Self {
+        Self {
+            db,
+            builtins,
+            params,
+            max_depth,
+            depth: Cell::new(0),
+            too_deep: Cell::new(false),
+        }
+    }
+
+    fn finish<T>(&self, value: Option<T>) -> Result<Option<T>, TooDeep> {
+        if self.too_deep.get() {
+            Err(TooDeep)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Runs a step of the evaluation one level deeper, unless that's past the limit.
+    fn nested<T>(&self, f: impl FnOnce() -> Option<T>) -> Option<T> {
+        if self.depth.get() >= self.max_depth {
+            self.too_deep.set(true);
+            return None;
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        let value = f();
+        self.depth.set(self.depth.get() - 1);
+        value
+    }
+
+    fn eval(&self, hir_id: HirId) -> Option<Vec<u8>> {
+        self.nested(|| self.eval_nested(hir_id))
+    }
+
+    fn eval_nested(&self, hir_id: HirId) -> Option<Vec<u8>> {
+        let db = self.db;
+
+        match db.hir(self.resolve(hir_id)).clone() {
+            Hir::Atom(atom) => Some(atom),
+            Hir::Reference(symbol_id) => self.params.as_ref()?.get(&symbol_id).cloned(),
+            Hir::FunctionCall { callee, args } => self.eval_call(callee, args),
+            Hir::BinaryOp { op, lhs, rhs } => self.eval_binary_op(op, lhs, rhs),
+            // The other half of the pair is evaluated at runtime too, so it mustn't raise.
+            Hir::First(value) => match db.hir(self.resolve(value)) {
+                Hir::Pair(first, rest) if !may_fail(db, *rest) => self.eval(*first),
+                _ => None,
+            },
+            Hir::Rest(value) => match db.hir(self.resolve(value)) {
+                Hir::Pair(first, rest) if !may_fail(db, *first) => self.eval(*rest),
+                _ => None,
+            },
+            Hir::Not(value) => Some(bool_atom(self.eval(value)?.is_empty())),
+            Hir::Sha256(value) => Some(Sha256::digest(self.eval(value)?).to_vec()),
+            Hir::Keccak256(value) => Some(Keccak256::digest(self.eval(value)?).to_vec()),
+            Hir::IsCons(value) => match db.hir(self.resolve(value)) {
+                Hir::Pair(first, rest) if !may_fail(db, *first) && !may_fail(db, *rest) => {
+                    Some(bool_atom(true))
+                }
+                Hir::Pair(..) => None,
+                _ => self.eval(value).map(|_| bool_atom(false)),
+            },
+            Hir::Strlen(value) => Some(bigint_to_bytes(BigInt::from(self.eval(value)?.len()))),
+            Hir::Substr { value, start, end } => {
+                let value = self.eval(value)?;
+                let start =
+                    usize::try_from(BigInt::from_signed_bytes_be(&self.eval(start)?)).ok()?;
+                let end = usize::try_from(BigInt::from_signed_bytes_be(&self.eval(end)?)).ok()?;
+
+                // Out of range indices raise at runtime, so they aren't folded.
+                if start > end || end > value.len() {
+                    return None;
+                }
+
+                Some(value[start..end].to_vec())
+            }
+            Hir::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                if self.eval(condition)?.is_empty() {
+                    self.eval(else_block)
+                } else {
+                    self.eval(then_block)
+                }
+            }
+            Hir::Dispatch { index, branches } => {
+                let index =
+                    usize::try_from(BigInt::from_signed_bytes_be(&self.eval(index)?)).ok()?;
+                self.eval(*branches.get(index)?)
+            }
+            Hir::Unknown
+            | Hir::Pair(..)
+            | Hir::Scope { .. }
+            | Hir::Raise(..)
+            | Hir::Softfork { .. }
+            | Hir::Op { .. }
+            | Hir::Env => None,
+        }
+    }
+
+    /// Evaluates a call to a builtin with constant arguments, such as `sha256(0x01)`.
+    fn eval_call(&self, callee: HirId, mut args: HirId) -> Option<Vec<u8>> {
+        let Hir::Reference(symbol_id) = self.db.hir(callee) else {
+            return None;
+        };
+
+        if !self.db.scope(self.builtins).is_local(*symbol_id) {
+            return None;
+        }
+
+        let Symbol::Function {
+            scope_id,
+            hir_id,
+            ty,
+        } = self.db.symbol(*symbol_id)
+        else {
+            return None;
+        };
+
+        if ty.varargs() {
+            return None;
+        }
+
+        let mut values = Vec::new();
+
+        while let Hir::Pair(first, rest) = self.db.hir(args) {
+            values.push(self.eval(*first)?);
+            args = *rest;
+        }
+
+        let params: Vec<SymbolId> = self
+            .db
+            .scope(*scope_id)
+            .local_symbols()
+            .into_iter()
+            .filter(|&symbol_id| matches!(self.db.symbol(symbol_id), Symbol::Parameter { .. }))
+            .collect();
+
+        if params.len() != values.len() {
+            return None;
+        }
+
+        // The body is evaluated with its own parameters, but counts towards the same limit.
+        let evaluator = Evaluator::new(
+            self.db,
+            self.builtins,
+            self.max_depth,
+            Some(params.into_iter().zip(values).collect()),
+        );
+        evaluator.depth.set(self.depth.get());
+
+        let value = evaluator.eval(*hir_id);
+        if evaluator.too_deep.get() {
+            self.too_deep.set(true);
+        }
+        value
+    }
+
+    fn serialized_length(&self, hir_id: HirId) -> Option<usize> {
+        self.nested(|| self.serialized_length_nested(hir_id))
+    }
+
+    fn serialized_length_nested(&self, hir_id: HirId) -> Option<usize> {
+        if let Hir::Pair(first, rest) = self.db.hir(self.resolve(hir_id)).clone() {
+            return Some(1 + self.serialized_length(first)? + self.serialized_length(rest)?);
+        }
+
+        let atom = self.eval(hir_id)?;
+
+        // A single byte below 0x80 is written as is, and any other atom is prefixed by a length
+        // which takes more bytes the longer the atom is.
+        let prefix = match atom.len() {
+            1 if atom[0] <= 0x7f => 0,
+            0..=0x3f => 1,
+            0x40..=0x1fff => 2,
+            0x2000..=0xf_ffff => 3,
+            0x10_0000..=0x7ff_ffff => 4,
+            _ => 5,
+        };
+
+        Some(prefix + atom.len())
+    }
+
+    /// Follows references to constants until a value is found.
+    fn resolve(&self, mut hir_id: HirId) -> HirId {
+        while let Hir::Reference(symbol_id) = self.db.hir(hir_id) {
+            let Symbol::ConstBinding { hir_id: value, .. } = self.db.symbol(*symbol_id) else {
+                break;
+            };
+            hir_id = *value;
+        }
+        hir_id
+    }
+
+    fn eval_binary_op(&self, op: HirBinaryOp, lhs: HirId, rhs: HirId) -> Option<Vec<u8>> {
+        let lhs = self.eval(lhs)?;
+        let rhs = self.eval(rhs)?;
+
+        let int = |bytes: &[u8]| BigInt::from_signed_bytes_be(bytes);
+
+        Some(match op {
+            HirBinaryOp::Add => bigint_to_bytes(int(&lhs) + int(&rhs)),
+            HirBinaryOp::Subtract => bigint_to_bytes(int(&lhs) - int(&rhs)),
+            HirBinaryOp::Multiply => bigint_to_bytes(int(&lhs) * int(&rhs)),
+            HirBinaryOp::Divide => bigint_to_bytes(floor_divmod(int(&lhs), int(&rhs))?.0),
+            HirBinaryOp::Remainder => bigint_to_bytes(floor_divmod(int(&lhs), int(&rhs))?.1),
+            HirBinaryOp::LessThan => bool_atom(int(&lhs) < int(&rhs)),
+            HirBinaryOp::GreaterThan => bool_atom(int(&lhs) > int(&rhs)),
+            HirBinaryOp::LessThanEquals => bool_atom(int(&lhs) <= int(&rhs)),
+            HirBinaryOp::GreaterThanEquals => bool_atom(int(&lhs) >= int(&rhs)),
+            HirBinaryOp::Equals => bool_atom(lhs == rhs),
+            HirBinaryOp::NotEquals => bool_atom(lhs != rhs),
+            HirBinaryOp::Concat => [lhs, rhs].concat(),
+            HirBinaryOp::Divmod => return None,
+            HirBinaryOp::ShiftRight => bigint_to_bytes(ash(int(&lhs), -int(&rhs))?),
+            HirBinaryOp::BitwiseAnd => bigint_to_bytes(int(&lhs) & int(&rhs)),
+        })
+    }
+}
+
+/// An arithmetic shift, which is to the left for positive amounts, like CLVM's `ash`.
+/// Returns `None` if the amount is out of the range that CLVM allows, since it would raise.
+fn ash(value: BigInt, shift: BigInt) -> Option<BigInt> {
+    let shift = i32::try_from(shift).ok()?;
+
+    if shift.abs() > 65535 {
+        return None;
+    }
+
+    Some(if shift >= 0 {
+        value << shift
+    } else {
+        value >> -shift
+    })
+}
+
+/// Division which rounds towards negative infinity, like CLVM's `/` and `divmod`.
+fn floor_divmod(lhs: BigInt, rhs: BigInt) -> Option<(BigInt, BigInt)> {
+    if rhs == BigInt::ZERO {
+        return None;
+    }
+
+    let mut quotient = &lhs / &rhs;
+    let mut remainder = &lhs % &rhs;
+
+    if remainder != BigInt::ZERO && ((remainder < BigInt::ZERO) != (rhs < BigInt::ZERO)) {
+        quotient -= 1;
+        remainder += &rhs;
+    }
+
+    Some((quotient, remainder))
+}
+
+fn bool_atom(value: bool) -> Vec<u8> {
+    if value {
+        vec![1]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Evaluates a fully constant LIR subtree to an atom, following CLVM semantics exactly.
+/// Returns `None` if the value isn't an atom, depends on the environment, or would raise.
+pub(crate) fn eval_const(db: &Database, lir_id: LirId) -> Option<Vec<u8>> {
+    let int = |lir_id: LirId| Some(BigInt::from_signed_bytes_be(&eval_const(db, lir_id)?));
+
+    match db.lir(lir_id).clone() {
+        Lir::Atom(atom) => Some(atom),
+        // Both halves of a pair are evaluated, so the other half is only dropped if it can't raise.
+        Lir::First(value) => match db.lir(value) {
+            Lir::Pair(first, rest) if cannot_fail(db, *rest) => eval_const(db, *first),
+            _ => None,
+        },
+        Lir::Rest(value) => match db.lir(value) {
+            Lir::Pair(first, rest) if cannot_fail(db, *first) => eval_const(db, *rest),
+            _ => None,
+        },
+        Lir::Sha256(value) => Some(Sha256::digest(eval_const(db, value)?).to_vec()),
+        Lir::Keccak256(value) => Some(Keccak256::digest(eval_const(db, value)?).to_vec()),
+        Lir::IsCons(value) => match db.lir(value) {
+            Lir::Pair(first, rest) if cannot_fail(db, *first) && cannot_fail(db, *rest) => {
+                Some(bool_atom(true))
+            }
+            Lir::Pair(..) => None,
+            _ => eval_const(db, value).map(|_| bool_atom(false)),
+        },
+        Lir::Strlen(value) => Some(bigint_to_bytes(BigInt::from(eval_const(db, value)?.len()))),
+        Lir::Substr(value, start, end) => {
+            let value = eval_const(db, value)?;
+            let start = usize::try_from(int(start)?).ok()?;
+            let end = usize::try_from(int(end)?).ok()?;
+
+            if start > end || end > value.len() {
+                return None;
+            }
+
+            Some(value[start..end].to_vec())
+        }
+        Lir::If(condition, then_branch, else_branch) => {
+            if eval_const(db, condition)?.is_empty() {
+                eval_const(db, else_branch)
+            } else {
+                eval_const(db, then_branch)
+            }
+        }
+        Lir::Not(value) => Some(bool_atom(eval_const(db, value)?.is_empty())),
+        Lir::Any(values) => {
+            let mut result = false;
+            for value in values {
+                result |= !eval_const(db, value)?.is_empty();
+            }
+            Some(bool_atom(result))
+        }
+        Lir::Concat(values) => {
+            let mut result = Vec::new();
+            for value in values {
+                result.extend(eval_const(db, value)?);
+            }
+            Some(result)
+        }
+        Lir::Add(values) => {
+            let mut result = BigInt::ZERO;
+            for value in values {
+                result += int(value)?;
+            }
+            Some(bigint_to_bytes(result))
+        }
+        Lir::Sub(values) => {
+            let mut values = values.into_iter();
+            let mut result = match values.next() {
+                Some(value) => int(value)?,
+                None => BigInt::ZERO,
+            };
+            for value in values {
+                result -= int(value)?;
+            }
+            Some(bigint_to_bytes(result))
+        }
+        Lir::Mul(values) => {
+            let mut result = BigInt::from(1);
+            for value in values {
+                result *= int(value)?;
+            }
+            Some(bigint_to_bytes(result))
+        }
+        Lir::Div(lhs, rhs) => Some(bigint_to_bytes(floor_divmod(int(lhs)?, int(rhs)?)?.0)),
+        Lir::Eq(lhs, rhs) => Some(bool_atom(eval_const(db, lhs)? == eval_const(db, rhs)?)),
+        Lir::Gt(lhs, rhs) => Some(bool_atom(int(lhs)? > int(rhs)?)),
+        Lir::Ash(value, shift) => Some(bigint_to_bytes(ash(int(value)?, int(shift)?)?)),
+        Lir::Logand(lhs, rhs) => Some(bigint_to_bytes(int(lhs)? & int(rhs)?)),
+        Lir::Pair(..)
+        | Lir::Path(..)
+        | Lir::Run(..)
+        | Lir::Curry(..)
+        | Lir::Closure(..)
+        | Lir::FunctionBody(..)
+        | Lir::Raise(..)
+        | Lir::Divmod(..)
+        | Lir::Softfork(..)
+        | Lir::Op(..)
+        | Lir::Dispatch(..) => None,
+    }
+}
+
+/// Whether the LIR is known not to raise, which is the case for constants and environment paths.
+fn cannot_fail(db: &Database, lir_id: LirId) -> bool {
+    match db.lir(lir_id) {
+        Lir::Atom(..) | Lir::Path(..) => true,
+        Lir::Pair(first, rest) => cannot_fail(db, *first) && cannot_fail(db, *rest),
+        _ => eval_const(db, lir_id).is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(db: &mut Database, value: i64) -> LirId {
+        db.alloc_lir(Lir::Atom(bigint_to_bytes(BigInt::from(value))))
+    }
+
+    fn bytes(db: &mut Database, value: &[u8]) -> LirId {
+        db.alloc_lir(Lir::Atom(value.to_vec()))
+    }
+
+    fn check(db: &Database, lir_id: LirId, expected: i64) {
+        assert_eq!(
+            eval_const(db, lir_id),
+            Some(bigint_to_bytes(BigInt::from(expected)))
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut db = Database::default();
+        let seven = atom(&mut db, 7);
+        let two = atom(&mut db, 2);
+        let negative = atom(&mut db, -7);
+
+        let add = db.alloc_lir(Lir::Add(vec![seven, two, two]));
+        check(&db, add, 11);
+
+        let sub = db.alloc_lir(Lir::Sub(vec![seven, two, two]));
+        check(&db, sub, 3);
+
+        let mul = db.alloc_lir(Lir::Mul(vec![seven, two]));
+        check(&db, mul, 14);
+
+        let div = db.alloc_lir(Lir::Div(negative, two));
+        check(&db, div, -4);
+
+        let zero = atom(&mut db, 0);
+        let div_by_zero = db.alloc_lir(Lir::Div(seven, zero));
+        assert_eq!(eval_const(&db, div_by_zero), None);
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let mut db = Database::default();
+        let value = atom(&mut db, -45);
+        let two = atom(&mut db, 2);
+        let minus_two = atom(&mut db, -2);
+
+        let left = db.alloc_lir(Lir::Ash(value, two));
+        check(&db, left, -180);
+
+        let right = db.alloc_lir(Lir::Ash(value, minus_two));
+        check(&db, right, -12);
+
+        let three = atom(&mut db, 3);
+        let logand = db.alloc_lir(Lir::Logand(value, three));
+        check(&db, logand, 3);
+
+        let too_far = atom(&mut db, 65536);
+        let out_of_range = db.alloc_lir(Lir::Ash(value, too_far));
+        assert_eq!(eval_const(&db, out_of_range), None);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let mut db = Database::default();
+        let seven = atom(&mut db, 7);
+        let two = atom(&mut db, 2);
+
+        let gt = db.alloc_lir(Lir::Gt(seven, two));
+        check(&db, gt, 1);
+
+        let not_gt = db.alloc_lir(Lir::Gt(two, seven));
+        check(&db, not_gt, 0);
+
+        let eq = db.alloc_lir(Lir::Eq(seven, seven));
+        check(&db, eq, 1);
+
+        let not = db.alloc_lir(Lir::Not(eq));
+        check(&db, not, 0);
+
+        let any = db.alloc_lir(Lir::Any(vec![not, eq]));
+        check(&db, any, 1);
+
+        let if_lir = db.alloc_lir(Lir::If(not, seven, two));
+        check(&db, if_lir, 2);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut db = Database::default();
+        let hello = bytes(&mut db, b"hello");
+        let world = bytes(&mut db, b" world");
+
+        let concat = db.alloc_lir(Lir::Concat(vec![hello, world]));
+        assert_eq!(eval_const(&db, concat), Some(b"hello world".to_vec()));
+
+        let strlen = db.alloc_lir(Lir::Strlen(concat));
+        check(&db, strlen, 11);
+
+        let one = atom(&mut db, 1);
+        let four = atom(&mut db, 4);
+        let substr = db.alloc_lir(Lir::Substr(hello, one, four));
+        assert_eq!(eval_const(&db, substr), Some(b"ell".to_vec()));
+
+        let out_of_range = db.alloc_lir(Lir::Substr(hello, one, strlen));
+        assert_eq!(eval_const(&db, out_of_range), None);
+
+        let abc = bytes(&mut db, b"abc");
+        let sha256 = db.alloc_lir(Lir::Sha256(abc));
+        assert_eq!(
+            eval_const(&db, sha256).map(hex::encode),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string())
+        );
+
+        let keccak256 = db.alloc_lir(Lir::Keccak256(abc));
+        assert_eq!(
+            eval_const(&db, keccak256).map(hex::encode),
+            Some("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let mut db = Database::default();
+        let one = atom(&mut db, 1);
+        let two = atom(&mut db, 2);
+        let pair = db.alloc_lir(Lir::Pair(one, two));
+
+        let first = db.alloc_lir(Lir::First(pair));
+        check(&db, first, 1);
+
+        let rest = db.alloc_lir(Lir::Rest(pair));
+        check(&db, rest, 2);
+
+        let is_cons = db.alloc_lir(Lir::IsCons(pair));
+        check(&db, is_cons, 1);
+
+        let is_atom = db.alloc_lir(Lir::IsCons(one));
+        check(&db, is_atom, 0);
+
+        assert_eq!(eval_const(&db, pair), None);
+
+        let message = bytes(&mut db, b"boom");
+        let raise = db.alloc_lir(Lir::Raise(Some(message)));
+        let failing_pair = db.alloc_lir(Lir::Pair(one, raise));
+        let first = db.alloc_lir(Lir::First(failing_pair));
+        assert_eq!(eval_const(&db, first), None);
+
+        let is_cons = db.alloc_lir(Lir::IsCons(failing_pair));
+        assert_eq!(eval_const(&db, is_cons), None);
+    }
+
+    #[test]
+    fn test_non_constant() {
+        let mut db = Database::default();
+        let path = db.alloc_lir(Lir::Path(2));
+        let one = atom(&mut db, 1);
+
+        let add = db.alloc_lir(Lir::Add(vec![one, path]));
+        assert_eq!(eval_const(&db, add), None);
+
+        let first = db.alloc_lir(Lir::First(path));
+        assert_eq!(eval_const(&db, first), None);
+
+        let raise = db.alloc_lir(Lir::Raise(Some(one)));
+        let if_lir = db.alloc_lir(Lir::If(one, raise, one));
+        assert_eq!(eval_const(&db, if_lir), None);
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use crate::{database::Database, lir::Lir, LirId};
+
+// The base costs of each operator, as charged by CLVM.
+const QUOTE_COST: u64 = 20;
+const APPLY_COST: u64 = 90;
+const IF_COST: u64 = 33;
+const CONS_COST: u64 = 50;
+const FIRST_COST: u64 = 30;
+const REST_COST: u64 = 30;
+const LISTP_COST: u64 = 19;
+const TRAVERSE_COST: u64 = 40;
+const EQ_COST: u64 = 117;
+const SHA256_COST: u64 = 87;
+const KECCAK256_COST: u64 = 50;
+const SUBSTR_COST: u64 = 1;
+const STRLEN_COST: u64 = 173;
+const CONCAT_COST: u64 = 142;
+const ARITH_COST: u64 = 99;
+const MUL_COST: u64 = 92;
+const DIV_COST: u64 = 988;
+const DIVMOD_COST: u64 = 1116;
+const GR_COST: u64 = 498;
+const ASH_COST: u64 = 596;
+const LOGAND_COST: u64 = 100;
+const BOOL_COST: u64 = 200;
+const SOFTFORK_COST: u64 = 140;
+
+/// Estimates the cost of running a program by adding up the base cost of every operator in it.
+/// Costs which depend on the size of values, and how many times each function is called, aren't
+/// taken into account, so this is only useful for comparing programs.
+pub(crate) fn estimate_cost(db: &Database, lir_id: LirId) -> u64 {
+    let mut estimator = CostEstimator {
+        db,
+        visited: HashSet::new(),
+    };
+    estimator.estimate(lir_id)
+}
+
+struct CostEstimator<'a> {
+    db: &'a Database,
+    visited: HashSet<LirId>,
+}
+
+impl<'a> CostEstimator<'a> {
+    fn estimate(&mut self, lir_id: LirId) -> u64 {
+        // Shared nodes, such as function definitions, are only generated once.
+        if !self.visited.insert(lir_id) {
+            return 0;
+        }
+
+        match self.db.lir(lir_id).clone() {
+            Lir::Atom(atom) => {
+                if atom.is_empty() {
+                    0
+                } else {
+                    QUOTE_COST
+                }
+            }
+            Lir::Path(..) => TRAVERSE_COST,
+            Lir::Pair(first, rest) => CONS_COST + self.estimate(first) + self.estimate(rest),
+            Lir::Run(program, args) => APPLY_COST + self.estimate(program) + self.estimate(args),
+            Lir::Curry(body, args) => {
+                let args_cost = self.estimate_all(&args);
+                APPLY_COST
+                    + QUOTE_COST
+                    + CONS_COST * args.len() as u64
+                    + TRAVERSE_COST
+                    + self.estimate(body)
+                    + args_cost
+            }
+            Lir::Closure(body, captures) => {
+                // The curried program is assembled at runtime, quoting each capture.
+                let captures_cost = self.estimate_all(&captures);
+                (3 * CONS_COST + 4 * QUOTE_COST)
+                    + (4 * CONS_COST + 2 * QUOTE_COST) * captures.len() as u64
+                    + self.estimate(body)
+                    + captures_cost
+            }
+            Lir::FunctionBody(body) => QUOTE_COST + self.estimate(body),
+            Lir::First(value) => FIRST_COST + self.estimate(value),
+            Lir::Rest(value) => REST_COST + self.estimate(value),
+            Lir::Raise(value) => value.map(|value| self.estimate(value)).unwrap_or(0),
+            Lir::Sha256(value) => SHA256_COST + self.estimate(value),
+            Lir::Keccak256(value) => KECCAK256_COST + self.estimate(value),
+            Lir::IsCons(value) => LISTP_COST + self.estimate(value),
+            Lir::Strlen(value) => STRLEN_COST + self.estimate(value),
+            Lir::Substr(value, start, end) => SUBSTR_COST + self.estimate_all(&[value, start, end]),
+            Lir::Softfork(cost, program) => {
+                SOFTFORK_COST + 2 * QUOTE_COST + self.estimate(cost) + self.estimate(program)
+            }
+            // The cost of a raw operator isn't known, so only its arguments are counted.
+            Lir::Op(_, args) => self.estimate_all(&args),
+            Lir::If(condition, then_branch, else_branch) => {
+                // Both branches are counted, since it isn't known which one is taken.
+                APPLY_COST
+                    + IF_COST
+                    + 2 * QUOTE_COST
+                    + self.estimate_all(&[condition, then_branch, else_branch])
+            }
+            Lir::Dispatch(path, branches) => {
+                // Every branch is counted, the same as for `if`.
+                2 * APPLY_COST + QUOTE_COST + self.estimate(path) + self.estimate_all(&branches)
+            }
+            Lir::Not(value) => BOOL_COST + self.estimate(value),
+            Lir::Any(values) => BOOL_COST + self.estimate_all(&values),
+            Lir::Concat(values) => CONCAT_COST + self.estimate_all(&values),
+            Lir::Add(values) | Lir::Sub(values) => ARITH_COST + self.estimate_all(&values),
+            Lir::Mul(values) => MUL_COST + self.estimate_all(&values),
+            Lir::Div(lhs, rhs) => DIV_COST + self.estimate_all(&[lhs, rhs]),
+            Lir::Divmod(lhs, rhs) => DIVMOD_COST + self.estimate_all(&[lhs, rhs]),
+            Lir::Eq(lhs, rhs) => EQ_COST + self.estimate_all(&[lhs, rhs]),
+            Lir::Gt(lhs, rhs) => GR_COST + self.estimate_all(&[lhs, rhs]),
+            Lir::Ash(value, shift) => ASH_COST + self.estimate_all(&[value, shift]),
+            Lir::Logand(lhs, rhs) => LOGAND_COST + self.estimate_all(&[lhs, rhs]),
+        }
+    }
+
+    fn estimate_all(&mut self, lir_ids: &[LirId]) -> u64 {
+        lir_ids.iter().map(|&lir_id| self.estimate(lir_id)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_more_operations_cost_more() {
+        let mut db = Database::default();
+        let value = db.alloc_lir(Lir::Path(2));
+        let one = db.alloc_lir(Lir::Atom(vec![1]));
+
+        let add = db.alloc_lir(Lir::Add(vec![value, one]));
+        let add_cost = estimate_cost(&db, add);
+        assert_eq!(add_cost, ARITH_COST + TRAVERSE_COST + QUOTE_COST);
+
+        let mul = db.alloc_lir(Lir::Mul(vec![add, one]));
+        assert_eq!(estimate_cost(&db, mul), add_cost + MUL_COST);
+    }
+
+    #[test]
+    fn test_shared_nodes() {
+        let mut db = Database::default();
+        let value = db.alloc_lir(Lir::Path(2));
+        let sha256 = db.alloc_lir(Lir::Sha256(value));
+
+        let concat = db.alloc_lir(Lir::Concat(vec![sha256, sha256]));
+        assert_eq!(
+            estimate_cost(&db, concat),
+            CONCAT_COST + SHA256_COST + TRAVERSE_COST
+        );
+    }
+}
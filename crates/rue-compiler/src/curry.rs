@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+use crate::{
+    database::{Database, ScopeId, SymbolId, TypeId},
+    lowerer::bigint_to_bytes,
+    symbol::Symbol,
+    ty::{type_name, Type},
+    Diagnostic, DiagnosticInfo, DiagnosticKind,
+};
+
+/// A constant which is curried into `main` at compile time, in place of one of its leading
+/// parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurriedValue {
+    Int(BigInt),
+    Bytes(Vec<u8>),
+}
+
+impl CurriedValue {
+    /// Parses a hex literal such as `0xcafe` as bytes, and anything else as a decimal integer.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(hex) = text.strip_prefix("0x") {
+            return hex::decode(hex).ok().map(Self::Bytes);
+        }
+        BigInt::from_str(text).ok().map(Self::Int)
+    }
+
+    /// The value as an atom, with integers minimally encoded.
+    pub(crate) fn to_atom(&self) -> Vec<u8> {
+        match self {
+            Self::Int(value) => bigint_to_bytes(value.clone()),
+            Self::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
+
+/// Checks that each curried value can be passed as the parameter of `main` in the same position.
+/// A spread parameter can't be curried, since it's the rest of the solution.
+pub(crate) fn check_curried_values(
+    db: &Database,
+    scope_id: ScopeId,
+    main_id: SymbolId,
+    values: &[CurriedValue],
+) -> Vec<Diagnostic> {
+    let Symbol::Function {
+        scope_id: function_scope_id,
+        ty,
+        ..
+    } = db.symbol(main_id)
+    else {
+        return Vec::new();
+    };
+
+    let function_scope = db.scope(*function_scope_id);
+    let names: Vec<&str> = function_scope
+        .local_symbols()
+        .into_iter()
+        .filter(|&symbol_id| db.symbol(symbol_id).is_parameter())
+        .filter_map(|symbol_id| function_scope.symbol_name(symbol_id))
+        .collect();
+
+    let mut parameter_types = ty.parameter_types();
+
+    if ty.varargs() {
+        parameter_types = &parameter_types[..parameter_types.len() - 1];
+    }
+
+    if values.len() > parameter_types.len() {
+        return vec![Diagnostic::new(
+            DiagnosticKind::Error,
+            DiagnosticInfo::TooManyCurriedValues {
+                expected: parameter_types.len(),
+                found: values.len(),
+            },
+            0..0,
+        )];
+    }
+
+    values
+        .iter()
+        .zip(parameter_types)
+        .zip(names)
+        .filter(|((value, &type_id), _)| !is_assignable(db, value, type_id))
+        .map(|((_, &type_id), name)| {
+            Diagnostic::new(
+                DiagnosticKind::Error,
+                DiagnosticInfo::CurriedTypeMismatch {
+                    name: name.to_string(),
+                    expected: type_name(db, &[scope_id], type_id),
+                },
+                0..0,
+            )
+        })
+        .collect()
+}
+
+fn is_assignable(db: &Database, value: &CurriedValue, type_id: TypeId) -> bool {
+    match (db.ty(type_id), value) {
+        (Type::Any, _) => true,
+        (Type::Nil, value) => value.to_atom().is_empty(),
+        (Type::Int, CurriedValue::Int(..)) => true,
+        (Type::Bool, CurriedValue::Int(value)) => *value == 0.into() || *value == 1.into(),
+        (Type::Bytes, CurriedValue::Bytes(..)) => true,
+        (Type::Bytes32, CurriedValue::Bytes(bytes)) => bytes.len() == 32,
+        (Type::PublicKey, CurriedValue::Bytes(bytes)) => bytes.len() == 48,
+        (Type::Signature, CurriedValue::Bytes(bytes)) => bytes.len() == 96,
+        _ => false,
+    }
+}
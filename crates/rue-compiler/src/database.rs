@@ -1,4 +1,7 @@
+use std::mem;
+
 use id_arena::{Arena, Id};
+use indexmap::{IndexMap, IndexSet};
 
 use crate::{hir::Hir, lir::Lir, scope::Scope, symbol::Symbol, ty::Type};
 
@@ -14,9 +17,23 @@ pub struct TypeId(Id<Type>);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct HirId(Id<Hir>);
 
+#[cfg(test)]
+impl HirId {
+    pub(crate) fn index(self) -> usize {
+        self.0.index()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct LirId(Id<Lir>);
 
+#[cfg(test)]
+impl LirId {
+    pub(crate) fn index(self) -> usize {
+        self.0.index()
+    }
+}
+
 #[derive(Default)]
 pub struct Database {
     scopes: Arena<Scope>,
@@ -24,9 +41,62 @@ pub struct Database {
     types: Arena<Type>,
     hir: Arena<Hir>,
     lir: Arena<Lir>,
+    /// Optimized function definitions, by a hash of the lowered function and everything its
+    /// definition depends on. These are kept from one compilation to the next.
+    definitions: IndexMap<u64, LirId>,
+    /// The keys of the definitions which have been used since the database was last cleared.
+    used_definitions: IndexSet<u64>,
+    /// How much LIR was kept the last time it was compacted.
+    compacted_lir: usize,
 }
 
 impl Database {
+    /// Removes everything from a previous compilation, so that the database can be reused.
+    /// Function definitions are kept, so that they don't need to be optimized again if they
+    /// haven't changed. Once the LIR has doubled in size since it was last compacted, only the
+    /// definitions which the previous compilation used are kept, and nothing else it allocated.
+    pub fn clear(&mut self) {
+        let mut lir = mem::take(&mut self.lir);
+        let mut definitions = mem::take(&mut self.definitions);
+        let mut compacted_lir = self.compacted_lir;
+
+        if lir.len() > 2 * compacted_lir {
+            // If the previous compilation didn't use any definitions, such as when it had errors,
+            // they're all kept for the next one.
+            let keep_all = self.used_definitions.is_empty();
+            let mut copies = vec![None; lir.len()];
+            let mut compacted = Arena::new();
+
+            definitions = definitions
+                .into_iter()
+                .filter(|(key, _)| keep_all || self.used_definitions.contains(key))
+                .map(|(key, lir_id)| (key, copy_lir(&lir, &mut compacted, &mut copies, lir_id)))
+                .collect();
+
+            lir = compacted;
+            compacted_lir = lir.len();
+        }
+
+        *self = Self {
+            lir,
+            definitions,
+            compacted_lir,
+            ..Self::default()
+        };
+    }
+
+    /// The definition with this key from this or an earlier compilation, if there is one.
+    pub(crate) fn cached_definition(&mut self, key: u64) -> Option<LirId> {
+        let lir_id = self.definitions.get(&key).copied()?;
+        self.used_definitions.insert(key);
+        Some(lir_id)
+    }
+
+    pub(crate) fn cache_definition(&mut self, key: u64, lir_id: LirId) {
+        self.definitions.insert(key, lir_id);
+        self.used_definitions.insert(key);
+    }
+
     pub(crate) fn alloc_scope(&mut self, scope: Scope) -> ScopeId {
         ScopeId(self.scopes.alloc(scope))
     }
@@ -78,6 +148,10 @@ impl Database {
         &self.lir[id.0]
     }
 
+    pub(crate) fn hir_mut(&mut self, id: HirId) -> &mut Hir {
+        &mut self.hir[id.0]
+    }
+
     pub(crate) fn scope_mut(&mut self, id: ScopeId) -> &mut Scope {
         &mut self.scopes[id.0]
     }
@@ -86,3 +160,21 @@ impl Database {
         &mut self.symbols[id.0]
     }
 }
+
+/// Copies a LIR node and everything it refers to into another arena. Nodes which are shared are
+/// only copied once.
+fn copy_lir(
+    from: &Arena<Lir>,
+    to: &mut Arena<Lir>,
+    copies: &mut [Option<LirId>],
+    lir_id: LirId,
+) -> LirId {
+    if let Some(copy) = copies[lir_id.0.index()] {
+        return copy;
+    }
+
+    let lir = from[lir_id.0].map_children(|child| copy_lir(from, to, copies, child));
+    let copy = LirId(to.alloc(lir));
+    copies[lir_id.0.index()] = Some(copy);
+    copy
+}
@@ -22,6 +22,10 @@ impl Diagnostic {
         &self.info
     }
 
+    pub fn code(&self) -> &'static str {
+        self.info.code()
+    }
+
     pub fn span(&self) -> &Range<usize> {
         &self.span
     }
@@ -41,6 +45,9 @@ pub enum DiagnosticInfo {
     #[error("undefined reference `{0}`")]
     UndefinedReference(String),
 
+    #[error("undefined reference `{name}`, did you mean `{suggestion}`?")]
+    UndefinedReferenceSuggestion { name: String, suggestion: String },
+
     #[error("undefined type `{0}`")]
     UndefinedType(String),
 
@@ -50,13 +57,22 @@ pub enum DiagnosticInfo {
     #[error("expected {expected} arguments, found {found}")]
     ArgumentMismatch { expected: usize, found: usize },
 
+    #[error("expected at least {expected} arguments, found {found}")]
+    TooFewArguments { expected: usize, found: usize },
+
     #[error("expected type `{expected}`, found `{found}`")]
     TypeMismatch { expected: String, found: String },
 
+    #[error("`if` branches have incompatible types, `{then_type}` and `{else_type}`")]
+    BranchTypeMismatch {
+        then_type: String,
+        else_type: String,
+    },
+
     #[error("cannot cast type `{found}` to `{expected}`")]
     CastMismatch { expected: String, found: String },
 
-    #[error("cannot call expression with type `{0}`")]
+    #[error("expression of type `{0}` is not callable")]
     UncallableType(String),
 
     #[error("uninitializable type `{0}`")]
@@ -121,6 +137,295 @@ pub enum DiagnosticInfo {
 
     #[error("explicit return is not allowed in expressions")]
     ExplicitReturnInExpr,
+
+    #[error("`{name}` is not available for target `{target}`")]
+    UnavailableBuiltin { name: String, target: String },
+
+    #[error("`{value}` is not the discriminant of a variant without fields in `{enum_name}`")]
+    InvalidDiscriminant { enum_name: String, value: String },
+
+    #[error("softfork cost must be a positive constant")]
+    NonConstantSoftforkCost,
+
+    #[error("function body must contain an expression")]
+    EmptyFunctionBody,
+
+    #[error("block must end with an expression")]
+    MissingBlockExpr,
+
+    #[error("cannot destructure type `{ty}` into {expected} values")]
+    DestructureMismatch { ty: String, expected: usize },
+
+    #[error("cannot match on non-enum type `{0}`")]
+    NonEnumMatch(String),
+
+    #[error("duplicate match arm for variant `{0}`")]
+    DuplicateMatchArm(String),
+
+    #[error("missing match arms: {}", join_names(.0))]
+    MissingMatchArms(Vec<String>),
+
+    #[error("variant `{variant}` has {expected} fields, but the pattern binds {found}")]
+    VariantPatternMismatch {
+        variant: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("unknown literal suffix `{0}`, expected `i` or `b`")]
+    UnknownLiteralSuffix(String),
+
+    #[error("hex literal must have an even number of digits")]
+    OddHexLength,
+
+    #[error("expected a hex literal argument to `{0}`")]
+    TypedLiteralArgument(String),
+
+    #[error("expected {expected} bytes for `{name}` literal, found {found}")]
+    TypedLiteralLength {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("static assertion failed: {0}")]
+    StaticAssertFailed(String),
+
+    #[error("static assertion condition is not a constant expression")]
+    NonConstantStaticAssert,
+
+    #[error("unknown attribute `{0}`")]
+    UnknownAttribute(String),
+
+    #[error("expected {expected} attribute arguments, found {found}")]
+    AttributeArgumentMismatch { expected: usize, found: usize },
+
+    #[error("undefined label `{0}`")]
+    UndefinedLabel(String),
+
+    #[error("cannot break out of `{0}` from inside an expression")]
+    BreakFromExpr(String),
+
+    #[error("byte width must be a constant from 1 to {0}")]
+    InvalidByteWidth(usize),
+
+    #[error("cannot implement methods for non-struct type `{0}`")]
+    ImplNonStruct(String),
+
+    #[error("`self` parameter is only allowed first in a method")]
+    UnexpectedSelf,
+
+    #[error("undefined method `{name}` on `{ty}`")]
+    UndefinedMethod { ty: String, name: String },
+
+    #[error("duplicate method `{0}`")]
+    DuplicateMethod(String),
+
+    #[error("opcode must be a positive constant")]
+    NonConstantOpcode,
+
+    #[error("arguments don't satisfy the `where` clause of `{0}`")]
+    GuardFailed(String),
+
+    #[error("expected at most {expected} curried values, found {found}")]
+    TooManyCurriedValues { expected: usize, found: usize },
+
+    #[error("curried value for `{name}` doesn't have the type `{expected}`")]
+    CurriedTypeMismatch { name: String, expected: String },
+
+    #[error("`{name}` is deprecated: {message}")]
+    DeprecatedFunction { name: String, message: String },
+
+    #[error("implicit conversion from `{from}` to `{to}`, use `as` to make it explicit")]
+    ImplicitConversion { from: String, to: String },
+
+    #[error("`try` can't catch errors from this expression, check for them with `guard` instead")]
+    CannotCatch,
+
+    #[error("`{0}` is private to the file it's declared in")]
+    PrivateItem(String),
+
+    #[error("tuple index {index} is out of range for a tuple of {len} items")]
+    TupleIndexOutOfRange { index: String, len: usize },
+
+    #[error("expected a list of {expected} items, found {found}")]
+    ArrayLengthMismatch { expected: usize, found: usize },
+
+    #[error("cannot cast {found} bytes to `{ty}`, which is {expected} bytes")]
+    InvalidCastLength {
+        ty: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("serialized length argument is not a constant expression")]
+    NonConstantSerializedLength,
+
+    #[error("compile-time evaluation too deep")]
+    EvalTooDeep,
+
+    #[error("`{0}` calls itself with the same arguments on every path, so it never returns")]
+    InfiniteRecursion(String),
+
+    #[error("a list indexed at runtime must be constant")]
+    NonConstantIndexedList,
+}
+
+impl DiagnosticInfo {
+    /// A stable code which identifies the kind of diagnostic, and can be passed to [`explain`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingMain { .. } => "E0001",
+            Self::UndefinedReference { .. } | Self::UndefinedReferenceSuggestion { .. } => "E0002",
+            Self::UndefinedType { .. } => "E0003",
+            Self::RecursiveTypeAlias { .. } => "E0004",
+            Self::ArgumentMismatch { .. } => "E0005",
+            Self::TooFewArguments { .. } => "E0006",
+            Self::TypeMismatch { .. } => "E0007",
+            Self::BranchTypeMismatch { .. } => "E0008",
+            Self::CastMismatch { .. } => "E0009",
+            Self::UncallableType { .. } => "E0010",
+            Self::UninitializableType { .. } => "E0011",
+            Self::DuplicateField { .. } => "E0012",
+            Self::UndefinedField { .. } => "E0013",
+            Self::MissingFields { .. } => "E0014",
+            Self::StructFieldAccess { .. } => "E0015",
+            Self::PairFieldAccess { .. } => "E0016",
+            Self::IndexAccess { .. } => "E0017",
+            Self::IndexOutOfBounds { .. } => "E0018",
+            Self::NonFinalSpread { .. } => "E0019",
+            Self::NonVarargSpread { .. } => "E0020",
+            Self::NonListVararg { .. } => "E0021",
+            Self::DuplicateEnumVariant { .. } => "E0022",
+            Self::PathNotAllowed { .. } => "E0023",
+            Self::PathIntoNonEnum { .. } => "E0024",
+            Self::UnknownEnumVariant { .. } => "E0025",
+            Self::UnsupportedTypeGuard { .. } => "E0026",
+            Self::NonAnyPairTypeGuard { .. } => "E0027",
+            Self::NonListPairTypeGuard { .. } => "E0028",
+            Self::RedundantTypeGuard { .. } => "E0029",
+            Self::ImplicitReturnInIf { .. } => "E0030",
+            Self::ExplicitReturnInExpr { .. } => "E0031",
+            Self::UnavailableBuiltin { .. } => "E0032",
+            Self::InvalidDiscriminant { .. } => "E0033",
+            Self::NonConstantSoftforkCost { .. } => "E0034",
+            Self::EmptyFunctionBody { .. } => "E0035",
+            Self::MissingBlockExpr { .. } => "E0036",
+            Self::DestructureMismatch { .. } => "E0037",
+            Self::NonEnumMatch { .. } => "E0038",
+            Self::DuplicateMatchArm { .. } => "E0039",
+            Self::MissingMatchArms { .. } => "E0040",
+            Self::VariantPatternMismatch { .. } => "E0041",
+            Self::UnknownLiteralSuffix { .. } => "E0042",
+            Self::OddHexLength { .. } => "E0043",
+            Self::TypedLiteralArgument { .. } => "E0044",
+            Self::TypedLiteralLength { .. } => "E0045",
+            Self::StaticAssertFailed { .. } => "E0046",
+            Self::NonConstantStaticAssert { .. } => "E0047",
+            Self::UnknownAttribute { .. } => "E0048",
+            Self::AttributeArgumentMismatch { .. } => "E0049",
+            Self::UndefinedLabel { .. } => "E0050",
+            Self::BreakFromExpr { .. } => "E0051",
+            Self::InvalidByteWidth { .. } => "E0052",
+            Self::ImplNonStruct { .. } => "E0053",
+            Self::UnexpectedSelf { .. } => "E0054",
+            Self::UndefinedMethod { .. } => "E0055",
+            Self::DuplicateMethod { .. } => "E0056",
+            Self::NonConstantOpcode { .. } => "E0057",
+            Self::GuardFailed { .. } => "E0058",
+            Self::TooManyCurriedValues { .. } => "E0059",
+            Self::CurriedTypeMismatch { .. } => "E0060",
+            Self::DeprecatedFunction { .. } => "E0061",
+            Self::ImplicitConversion { .. } => "E0062",
+            Self::CannotCatch => "E0063",
+            Self::PrivateItem { .. } => "E0064",
+            Self::TupleIndexOutOfRange { .. } => "E0065",
+            Self::ArrayLengthMismatch { .. } => "E0066",
+            Self::InvalidCastLength { .. } => "E0067",
+            Self::NonConstantSerializedLength => "E0068",
+            Self::EvalTooDeep => "E0069",
+            Self::InfiniteRecursion(..) => "E0070",
+            Self::NonConstantIndexedList => "E0071",
+        }
+    }
+}
+
+/// A longer explanation of the diagnostics with the given code, if there are any.
+pub fn explain(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "E0001" => "Every program needs a `main` function, which is the entrypoint that is run with the solution as its arguments. Add a function named `main` to the root of the program.",
+        "E0002" => "A name was used which isn't defined in the current scope or any enclosing scope. Check the spelling, and that the item or binding is declared before it goes out of scope.",
+        "E0003" => "A type name was used which isn't a builtin type, and isn't defined by a struct, enum, or type alias.",
+        "E0004" => "A type alias refers to itself, either directly or through other aliases, so it never resolves to a real type. Use a struct or enum for recursive data instead.",
+        "E0005" => "A function was called with a different number of arguments than it has parameters. Each parameter must be given exactly one argument, unless the function has a vararg parameter.",
+        "E0006" => "A builtin which takes a variable number of arguments, such as `min` or `max`, was called with fewer arguments than it needs.",
+        "E0007" => "A value was used where a different type is expected, such as passing `Bytes` to an `Int` parameter. Use a cast with `as` if the value is known to have the expected representation.",
+        "E0008" => "The two branches of an `if` expression produce values of different types, so the type of the whole expression is unclear. A branch which always raises is compatible with any type.",
+        "E0009" => "A value was cast to a type which it can't be represented as, such as casting a pair to an integer.",
+        "E0010" => "A call was made on a value which isn't a function. Only functions and lambdas can be called.",
+        "E0011" => "An initializer was used with a type which doesn't have named fields. Only structs and enum variants can be initialized with `{ ... }`.",
+        "E0012" => "A struct field was declared or initialized more than once. Each field can only appear once.",
+        "E0013" => "A field was initialized or accessed which isn't declared on the struct or enum variant.",
+        "E0014" => "An initializer didn't give a value for every field of the struct or enum variant. All fields must be initialized.",
+        "E0015" => "A named field was accessed on a value whose type has no named fields. Only structs, enum variants, and pairs have fields.",
+        "E0016" => "A field other than `first` or `rest` was accessed on a pair. Pairs only have these two fields.",
+        "E0017" => "A value was indexed with `[...]` which isn't a list. Only lists can be indexed.",
+        "E0018" => "A tuple or fixed size value was indexed past its end. The index must be less than the length.",
+        "E0019" => "The spread operator `...` was used on an element other than the last one. A spread must come last, since it becomes the rest of the list.",
+        "E0020" => "An argument was spread into a call to a function which has no vararg parameter. Spreading is only allowed for the final argument of a vararg function.",
+        "E0021" => "Arguments were passed individually to a vararg parameter whose type isn't a list. Spread the value into the call instead.",
+        "E0022" => "An enum declares two variants with the same name. Each variant name must be unique within its enum.",
+        "E0023" => "A path such as `Enum::Variant` was used where only a plain name is allowed, or it names a variant which has fields and must be initialized.",
+        "E0024" => "A path was used on a type which isn't an enum. Only enums have members which can be accessed with `::`.",
+        "E0025" => "A path names a variant which the enum doesn't declare.",
+        "E0026" => "An `is` check was made between two types which can't be told apart at runtime.",
+        "E0027" => "A value of type `Any` can only be checked against a pair whose first and rest types are both `Any`, since nothing more is known about its contents.",
+        "E0028" => "A list can only be checked against a pair of its item type and the list type itself, since that's the only shape a non-empty list can have.",
+        "E0029" => "An `is` check was made against the type the value already has, so it always succeeds. This is a warning, and the check can be removed.",
+        "E0030" => "An `if` statement without an `else` can't end with an expression, since there would be no value when the condition is false. Use an explicit `return` in the block instead.",
+        "E0031" => "A `return` statement was used inside a block which is an expression, such as an `if` expression. Return from the function at the statement level instead.",
+        "E0032" => "A builtin was used which relies on CLVM operators that the selected target doesn't provide. Choose a target which has the operator, such as with `--target keccak`.",
+        "E0033" => "A constant integer was cast to an enum, but it isn't the discriminant of any variant without fields, so the cast could never succeed.",
+        "E0034" => "The cost given to `softfork` must be a positive integer known at compile time, since CLVM requires it to match the cost of the guarded program exactly.",
+        "E0035" => "A function body has no final expression, so the function doesn't produce a value. End the body with an expression, or with a `return` or `raise` statement.",
+        "E0036" => "A block has no final expression, so it doesn't produce a value. End the block with an expression, or with a `return` or `raise` statement.",
+        "E0037" => "A destructuring pattern binds a different number of values than the type can provide.",
+        "E0038" => "A `match` expression was used on a value whose type isn't an enum. Only enums can be matched on.",
+        "E0039" => "Two arms of a `match` expression handle the same variant, so the later one could never be reached.",
+        "E0040" => "A `match` expression doesn't handle every variant of the enum. Add an arm for each missing variant.",
+        "E0041" => "A match arm binds a different number of names than the variant has fields. Bind exactly one name per field.",
+        "E0042" => "An integer literal has a suffix other than `i` for integers or `b` for bytes.",
+        "E0043" => "A hex literal has an odd number of digits, so it can't be split into whole bytes. Add a leading zero.",
+        "E0044" => "Typed literals such as `pubkey` and `signature` must be given a hex literal, so that their length can be checked at compile time.",
+        "E0045" => "A typed literal was given the wrong number of bytes. Public keys are 48 bytes and signatures are 96 bytes.",
+        "E0046" => "A `static_assert` condition evaluated to false at compile time.",
+        "E0047" => "A `static_assert` condition couldn't be evaluated at compile time. It may only use literals, constants, and calls to builtins.",
+        "E0048" => "An attribute was used which the compiler doesn't recognize.",
+        "E0049" => "An attribute was given a different number of arguments than it takes.",
+        "E0050" => "A `break` statement names a label which isn't on any block enclosing it.",
+        "E0051" => "A `break` statement can only leave a labeled block from its own statements, or from `if` statements nested inside them. It can't break out of a nested expression, such as another block, a lambda, or an `if` expression.",
+        "E0052" => "The width given to `le_bytes` must be a constant from 1 to 64, since a separate operation is generated for each byte. Similarly, the width given to `truncate` or `pad` must be a constant from 1 to 1024.",
+        "E0053" => "An `impl` block was written for a type which isn't a struct. Methods can only be declared on structs.",
+        "E0054" => "A `self` parameter without a type was used outside of an `impl` block, or after the first parameter. It takes the type of the struct, so it must come first in a method.",
+        "E0055" => "A path such as `Point::len` names a method which isn't declared in any `impl` block for the struct.",
+        "E0056" => "Two methods with the same name were declared for a struct. Each method name must be unique, even across `impl` blocks.",
+        "E0057" => "The opcode given to `op` must be a positive integer known at compile time, since it's emitted directly as the CLVM operator.",
+        "E0058" => "A function was called with constant arguments which make its `where` clause false. Calls with arguments that aren't constant are only checked at runtime, and only if runtime guards are enabled.",
+        "E0059" => "More values were curried into `main` than it has parameters. A spread parameter can't be curried, since it's the rest of the solution.",
+        "E0060" => "A value curried into `main` doesn't fit the type of its parameter. Integers can be passed for `Int` and `Bool` parameters, and hex for byte types, which must have the right length for `Bytes32`, `PublicKey`, and `Signature`.",
+        "E0061" => "A function marked with `@deprecated(\"...\")` was called. The call still compiles, and the message usually says what to use instead.",
+        "E0062" => "An `Int` was used where `Bytes` were expected, or the other way around. Both are atoms, so comparisons and `concat` accept either, but an integer's bytes are its minimal signed encoding, which may not be what was meant. Casting with `as` makes the conversion explicit and silences the warning. Arithmetic and ordering still require `Int`.",
+        "E0063" => "CLVM has no way to recover once a program raises, so `try(value, fallback)` can only be used when the value can't fail, and then the fallback is never used. Calls, division by a value which may be zero, `substr`, and explicit raises can all fail. Use `guard(condition, value, fallback)` to check that the value is safe to compute first, which only evaluates it when the condition is true.",
+        "E0064" => "An item from another file of the project was referenced, but it isn't exported. Items are private to the file they're declared in by default, and marking one as `pub`, as in `pub fun square(value: Int) -> Int`, makes it visible to the other files.",
+        "E0065" => "A tuple was indexed with `.N` past its last item. Indices start at zero, and a tuple's items are counted from its type, where the last item of a pair is read as more items if it's also a pair.",
+        "E0066" => "A list literal was given a fixed size list type, such as `Int[3]`, but has a different number of items. Fixed size lists must be built with exactly as many items as their type says, so that indexing them with a constant is checked at compile time.",
+        "E0067" => "A constant was cast to a fixed width type, such as `Bytes32`, but has a different number of bytes. A cast from `Bytes` checks the length, and raises at runtime if it's wrong, so a constant of the wrong length would always fail. Use `truncate` or `pad` to resize a value explicitly.",
+        "E0068" => "The argument of `serialized_length` must be a constant expression, such as a literal, a list of constants, or a `const` item, since its length is computed at compile time. Values which depend on the solution or on function calls aren't known until the program is run.",
+        "E0069" => "A value which is evaluated at compile time, such as a constant or the argument of `serialized_length`, is nested more deeply than the limit, which is usually because of a long chain of constants that each refer to the last. The limit keeps the compiler from running out of stack space, and can be raised with `--max-depth`.",
+        "E0070" => "Every path through a function ends in a call to itself with its own parameters, unchanged, so each call does exactly the same as the last and the program never finishes. Usually an argument was meant to change, such as `f(x - 1)` instead of `f(x)`, or a base case is missing. Only calls which pass the parameters directly are detected.",
+        "E0071" => "A list was indexed with a value which isn't an integer literal, such as `list[i]`. Reading an item at an index which is only known at runtime would take a loop, so this only works for lists whose items are known at compile time, like a `const` list or a list literal, where the index is compared with the position of each item instead. Use an integer literal to index other lists.",
+        _ => return None,
+    })
 }
 
 /// Join a list of names into a string, wrapped in backticks.
@@ -0,0 +1,217 @@
+use indexmap::IndexSet;
+
+use crate::{
+    database::{Database, HirId, SymbolId, TypeId},
+    hir::{Hir, HirBinaryOp},
+    scope::Scope,
+    symbol::Symbol,
+};
+
+/// Makes the operands of a function call or operator evaluate from left to right.
+///
+/// CLVM evaluates the arguments of an operator starting from the last one, which only matters
+/// if more than one of them can raise. In that case, every such operand except the last is bound
+/// up front in its own scope, in order, and the operation refers to the bound values instead.
+pub(crate) fn sequence_operands(db: &mut Database, hir_id: HirId, type_id: TypeId) {
+    let mut operands = operands(db, hir_id);
+
+    let raising: Vec<usize> = (0..operands.len())
+        .filter(|&i| may_raise(db, operands[i], &mut IndexSet::new()))
+        .collect();
+
+    if raising.len() < 2 {
+        return;
+    }
+
+    let mut symbol_ids = Vec::new();
+
+    // The last raising operand stays in place, since it's evaluated after the bound ones.
+    for &i in &raising[..raising.len() - 1] {
+        let symbol_id = db.alloc_symbol(Symbol::LetBinding {
+            type_id,
+            hir_id: operands[i],
+        });
+        operands[i] = db.alloc_hir(Hir::Reference(symbol_id));
+        symbol_ids.push(symbol_id);
+    }
+
+    let mut value = rebuild(db, hir_id, operands);
+
+    for symbol_id in symbol_ids.into_iter().rev() {
+        let mut scope = Scope::default();
+        scope.define_hidden_symbol(symbol_id);
+        let scope_id = db.alloc_scope(scope);
+        value = db.alloc_hir(Hir::Scope { scope_id, value });
+    }
+
+    *db.hir_mut(hir_id) = db.hir(value).clone();
+}
+
+/// The operands in evaluation order. For function calls, a spread argument is the final operand.
+fn operands(db: &Database, hir_id: HirId) -> Vec<HirId> {
+    match db.hir(hir_id) {
+        Hir::BinaryOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+        Hir::Op { args, .. } => args.clone(),
+        Hir::FunctionCall { args, .. } => {
+            let mut operands = Vec::new();
+            let mut args = *args;
+            while let Hir::Pair(first, rest) = db.hir(args) {
+                operands.push(*first);
+                args = *rest;
+            }
+            operands.push(args);
+            operands
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn rebuild(db: &mut Database, hir_id: HirId, mut operands: Vec<HirId>) -> HirId {
+    let hir = match db.hir(hir_id).clone() {
+        Hir::BinaryOp { op, .. } => Hir::BinaryOp {
+            op,
+            lhs: operands[0],
+            rhs: operands[1],
+        },
+        Hir::Op { opcode, .. } => Hir::Op {
+            opcode,
+            args: operands,
+        },
+        Hir::FunctionCall { callee, .. } => {
+            let mut args = operands.pop().unwrap();
+            for arg in operands.into_iter().rev() {
+                args = db.alloc_hir(Hir::Pair(arg, args));
+            }
+            Hir::FunctionCall { callee, args }
+        }
+        _ => unreachable!(),
+    };
+    db.alloc_hir(hir)
+}
+
+/// Whether evaluating the expression can reach an explicit `raise` or `assert`.
+/// Calls through anything other than a named function are assumed to raise.
+fn may_raise(db: &Database, hir_id: HirId, visited: &mut IndexSet<SymbolId>) -> bool {
+    match db.hir(hir_id).clone() {
+        Hir::Raise(..) => true,
+        Hir::Unknown | Hir::Atom(..) | Hir::Reference(..) | Hir::Env => false,
+        Hir::FunctionCall { callee, args } => {
+            callee_may_raise(db, callee, visited) || may_raise(db, args, visited)
+        }
+        Hir::Scope { scope_id, value } => {
+            // Bindings in the scope are evaluated on entry, before the value.
+            db.scope(scope_id)
+                .local_symbols()
+                .into_iter()
+                .any(|symbol_id| match db.symbol(symbol_id) {
+                    Symbol::LetBinding { hir_id, .. } => may_raise(db, *hir_id, visited),
+                    _ => false,
+                })
+                || may_raise(db, value, visited)
+        }
+        Hir::BinaryOp { lhs, rhs, .. } | Hir::Pair(lhs, rhs) => {
+            may_raise(db, lhs, visited) || may_raise(db, rhs, visited)
+        }
+        Hir::First(value)
+        | Hir::Rest(value)
+        | Hir::Not(value)
+        | Hir::Sha256(value)
+        | Hir::Keccak256(value)
+        | Hir::IsCons(value)
+        | Hir::Strlen(value) => may_raise(db, value, visited),
+        Hir::Softfork { cost, program } => {
+            may_raise(db, cost, visited) || may_raise(db, program, visited)
+        }
+        Hir::Op { args, .. } => args.iter().any(|&arg| may_raise(db, arg, visited)),
+        Hir::Substr { value, start, end } => {
+            may_raise(db, value, visited)
+                || may_raise(db, start, visited)
+                || may_raise(db, end, visited)
+        }
+        Hir::If {
+            condition,
+            then_block,
+            else_block,
+        } => {
+            may_raise(db, condition, visited)
+                || may_raise(db, then_block, visited)
+                || may_raise(db, else_block, visited)
+        }
+        Hir::Dispatch { index, branches } => {
+            may_raise(db, index, visited)
+                || branches
+                    .iter()
+                    .any(|&branch| may_raise(db, branch, visited))
+        }
+    }
+}
+
+fn callee_may_raise(db: &Database, callee: HirId, visited: &mut IndexSet<SymbolId>) -> bool {
+    let Hir::Reference(symbol_id) = db.hir(callee) else {
+        return true;
+    };
+
+    let Symbol::Function { hir_id, .. } = db.symbol(*symbol_id) else {
+        return true;
+    };
+
+    // A recursive call raises only if the rest of the function body does.
+    if !visited.insert(*symbol_id) {
+        return false;
+    }
+
+    may_raise(db, *hir_id, visited)
+}
+
+/// Whether evaluating the expression can fail in any way, which includes CLVM errors such as
+/// dividing by zero as well as an explicit `raise`. Calls and raw operators are assumed to fail,
+/// since the callee may not have been compiled yet, and CLVM has no way to catch either.
+pub(crate) fn may_fail(db: &Database, hir_id: HirId) -> bool {
+    match db.hir(hir_id).clone() {
+        Hir::Unknown | Hir::Atom(..) | Hir::Reference(..) | Hir::Env => false,
+        Hir::Raise(..)
+        | Hir::FunctionCall { .. }
+        | Hir::Substr { .. }
+        | Hir::Softfork { .. }
+        | Hir::Op { .. } => true,
+        Hir::Scope { scope_id, value } => {
+            db.scope(scope_id)
+                .local_symbols()
+                .into_iter()
+                .any(|symbol_id| match db.symbol(symbol_id) {
+                    Symbol::LetBinding { hir_id, .. } => may_fail(db, *hir_id),
+                    _ => false,
+                })
+                || may_fail(db, value)
+        }
+        Hir::BinaryOp { op, lhs, rhs } => {
+            // Dividing by zero and shifting too far both fail, unless the operand is a constant
+            // that can't.
+            let checks_rhs = match op {
+                HirBinaryOp::Divide | HirBinaryOp::Remainder | HirBinaryOp::Divmod => {
+                    !matches!(db.hir(rhs), Hir::Atom(atom) if atom.iter().any(|&byte| byte != 0))
+                }
+                HirBinaryOp::ShiftRight => !matches!(db.hir(rhs), Hir::Atom(..)),
+                _ => false,
+            };
+            checks_rhs || may_fail(db, lhs) || may_fail(db, rhs)
+        }
+        Hir::Pair(first, rest) => may_fail(db, first) || may_fail(db, rest),
+        // The type checker only allows `first` and `rest` on values which are known to be pairs.
+        Hir::First(value)
+        | Hir::Rest(value)
+        | Hir::Not(value)
+        | Hir::Sha256(value)
+        | Hir::Keccak256(value)
+        | Hir::IsCons(value)
+        | Hir::Strlen(value) => may_fail(db, value),
+        Hir::If {
+            condition,
+            then_block,
+            else_block,
+        } => may_fail(db, condition) || may_fail(db, then_block) || may_fail(db, else_block),
+        Hir::Dispatch { index, branches } => {
+            may_fail(db, index) || branches.iter().any(|&branch| may_fail(db, branch))
+        }
+    }
+}
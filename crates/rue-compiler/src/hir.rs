@@ -26,13 +26,36 @@ pub enum Hir {
     Not(HirId),
     Raise(Option<HirId>),
     Sha256(HirId),
+    Keccak256(HirId),
     IsCons(HirId),
     Strlen(HirId),
+    Substr {
+        value: HirId,
+        start: HirId,
+        end: HirId,
+    },
+    Softfork {
+        cost: HirId,
+        program: HirId,
+    },
+    /// A CLVM operator given by its raw opcode, applied to the arguments.
+    Op {
+        opcode: Vec<u8>,
+        args: Vec<HirId>,
+    },
+    /// The arguments of the enclosing function, as they were passed to it.
+    Env,
     If {
         condition: HirId,
         then_block: HirId,
         else_block: HirId,
     },
+    /// The branch at an index into a list of branches, where the index is known to be in range,
+    /// such as the tag of an enum value in an exhaustive `match`.
+    Dispatch {
+        index: HirId,
+        branches: Vec<HirId>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -49,6 +72,9 @@ pub enum HirBinaryOp {
     Equals,
     NotEquals,
     Concat,
+    Divmod,
+    ShiftRight,
+    BitwiseAnd,
 }
 
 impl From<BinaryOp> for HirBinaryOp {
@@ -0,0 +1,136 @@
+use indexmap::IndexMap;
+
+use crate::{
+    database::{Database, LirId},
+    lir::Lir,
+};
+
+/// Writes the LIR reachable from the root as JSON, for tools which analyze the program without
+/// linking the compiler. Nodes are numbered in the order they're first reached from the root,
+/// starting with the root itself, so the ids don't depend on allocation order. Nodes which are
+/// shared are only written once, and are referred to by id from each of their parents.
+pub(crate) fn dump_lir(db: &Database, root: LirId) -> String {
+    let mut ids = IndexMap::new();
+    number_nodes(db, root, &mut ids);
+
+    let nodes: Vec<String> = ids
+        .keys()
+        .map(|&lir_id| {
+            let lir = db.lir(lir_id);
+            let mut fields = vec![
+                format!("\"id\": {}", ids[&lir_id]),
+                format!("\"kind\": \"{}\"", kind(lir)),
+            ];
+
+            match lir {
+                Lir::Atom(atom) => fields.push(format!("\"value\": \"{}\"", hex::encode(atom))),
+                Lir::Path(path) => fields.push(format!("\"value\": {path}")),
+                Lir::Op(opcode, _) => {
+                    fields.push(format!("\"opcode\": \"{}\"", hex::encode(opcode)));
+                }
+                _ => {}
+            }
+
+            let operands: Vec<String> = operands(lir)
+                .into_iter()
+                .map(|operand| ids[&operand].to_string())
+                .collect();
+            fields.push(format!("\"operands\": [{}]", operands.join(", ")));
+
+            format!("    {{{}}}", fields.join(", "))
+        })
+        .collect();
+
+    format!(
+        "{{\n  \"root\": 0,\n  \"nodes\": [\n{}\n  ]\n}}\n",
+        nodes.join(",\n")
+    )
+}
+
+fn number_nodes(db: &Database, lir_id: LirId, ids: &mut IndexMap<LirId, usize>) {
+    if ids.contains_key(&lir_id) {
+        return;
+    }
+
+    ids.insert(lir_id, ids.len());
+
+    for operand in operands(db.lir(lir_id)) {
+        number_nodes(db, operand, ids);
+    }
+}
+
+fn kind(lir: &Lir) -> &'static str {
+    match lir {
+        Lir::Atom(..) => "Atom",
+        Lir::Pair(..) => "Pair",
+        Lir::Path(..) => "Path",
+        Lir::Run(..) => "Run",
+        Lir::Curry(..) => "Curry",
+        Lir::Closure(..) => "Closure",
+        Lir::FunctionBody(..) => "FunctionBody",
+        Lir::First(..) => "First",
+        Lir::Rest(..) => "Rest",
+        Lir::Raise(..) => "Raise",
+        Lir::Sha256(..) => "Sha256",
+        Lir::Keccak256(..) => "Keccak256",
+        Lir::IsCons(..) => "IsCons",
+        Lir::Strlen(..) => "Strlen",
+        Lir::Substr(..) => "Substr",
+        Lir::Softfork(..) => "Softfork",
+        Lir::Op(..) => "Op",
+        Lir::If(..) => "If",
+        Lir::Dispatch(..) => "Dispatch",
+        Lir::Not(..) => "Not",
+        Lir::Any(..) => "Any",
+        Lir::Concat(..) => "Concat",
+        Lir::Add(..) => "Add",
+        Lir::Sub(..) => "Sub",
+        Lir::Mul(..) => "Mul",
+        Lir::Div(..) => "Div",
+        Lir::Divmod(..) => "Divmod",
+        Lir::Eq(..) => "Eq",
+        Lir::Gt(..) => "Gt",
+        Lir::Ash(..) => "Ash",
+        Lir::Logand(..) => "Logand",
+    }
+}
+
+/// The nodes which a node refers to, in the order they appear in it.
+fn operands(lir: &Lir) -> Vec<LirId> {
+    match lir {
+        Lir::Atom(..) | Lir::Path(..) | Lir::Raise(None) => Vec::new(),
+        Lir::FunctionBody(value)
+        | Lir::First(value)
+        | Lir::Rest(value)
+        | Lir::Raise(Some(value))
+        | Lir::Sha256(value)
+        | Lir::Keccak256(value)
+        | Lir::IsCons(value)
+        | Lir::Strlen(value)
+        | Lir::Not(value) => vec![*value],
+        Lir::Pair(lhs, rhs)
+        | Lir::Run(lhs, rhs)
+        | Lir::Softfork(lhs, rhs)
+        | Lir::Div(lhs, rhs)
+        | Lir::Divmod(lhs, rhs)
+        | Lir::Eq(lhs, rhs)
+        | Lir::Gt(lhs, rhs)
+        | Lir::Ash(lhs, rhs)
+        | Lir::Logand(lhs, rhs) => vec![*lhs, *rhs],
+        Lir::Substr(value, start, end) => vec![*value, *start, *end],
+        Lir::If(condition, then_branch, else_branch) => {
+            vec![*condition, *then_branch, *else_branch]
+        }
+        Lir::Curry(body, args) | Lir::Closure(body, args) | Lir::Dispatch(body, args) => {
+            let mut operands = vec![*body];
+            operands.extend(args);
+            operands
+        }
+        Lir::Op(_, args)
+        | Lir::Any(args)
+        | Lir::Concat(args)
+        | Lir::Add(args)
+        | Lir::Sub(args)
+        | Lir::Mul(args) => args.clone(),
+    }
+}
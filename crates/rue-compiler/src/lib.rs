@@ -1,28 +1,53 @@
 use clvmr::{Allocator, NodePtr};
 use codegen::Codegen;
+use cost::estimate_cost;
+use curry::check_curried_values;
+use indexmap::{IndexMap, IndexSet};
+use ir_dump::dump_lir;
 use lowerer::Lowerer;
 use optimizer::Optimizer;
 use rue_parser::Root;
+use symbol_dump::dump_symbols;
 
+mod builtins;
 mod codegen;
+mod completions;
+mod const_eval;
+mod cost;
+mod curry;
 mod database;
 mod error;
+mod eval_order;
 mod hir;
+mod ir_dump;
 mod lir;
 mod lowerer;
 mod optimizer;
+mod recursion;
 mod scope;
 mod symbol;
+mod symbol_dump;
 mod ty;
 
+pub use builtins::*;
+pub use completions::*;
+pub use curry::CurriedValue;
 pub use database::*;
 pub use error::*;
 
 use scope::Scope;
+use symbol::Symbol;
+
+/// How deeply nested a value which is evaluated at compile time can be, unless it's configured.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
 
 pub struct Output {
     diagnostics: Vec<Diagnostic>,
     node_ptr: NodePtr,
+    estimated_cost: u64,
+    symbol_dump: Option<String>,
+    ir_json: Option<String>,
+    mod_arguments: Option<String>,
 }
 
 impl Output {
@@ -33,21 +58,240 @@ impl Output {
     pub fn node_ptr(&self) -> NodePtr {
         self.node_ptr
     }
+
+    /// A rough estimate of the cost of running the program, based on the operators it contains.
+    /// This is zero if the program wasn't generated.
+    pub fn estimated_cost(&self) -> u64 {
+        self.estimated_cost
+    }
+
+    /// The symbols and scopes of the program, if [`CompilerOptions::set_dump_symbols`] was used.
+    pub fn symbol_dump(&self) -> Option<&str> {
+        self.symbol_dump.as_deref()
+    }
+
+    /// The optimized LIR of the program as JSON, if [`CompilerOptions::set_dump_ir`] was used and
+    /// the program was generated.
+    pub fn ir_json(&self) -> Option<&str> {
+        self.ir_json.as_deref()
+    }
+
+    /// The parameters of `main` as the argument list of a Chialisp `mod`, such as `(a b . rest)`,
+    /// which is the order that they're read from the solution in.
+    pub fn mod_arguments(&self) -> Option<&str> {
+        self.mod_arguments.as_deref()
+    }
+}
+
+/// The programs compiled from the test blocks of a file, which are run by `rue test`.
+pub struct TestOutput {
+    diagnostics: Vec<Diagnostic>,
+    tests: Vec<Test>,
+}
+
+impl TestOutput {
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The compiled tests, in the order they're declared. This is empty if there are errors.
+    pub fn tests(&self) -> &[Test] {
+        &self.tests
+    }
+}
+
+/// A test block compiled as its own program, which passes if running it doesn't raise.
+pub struct Test {
+    name: String,
+    node_ptr: NodePtr,
+}
+
+impl Test {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn node_ptr(&self) -> NodePtr {
+        self.node_ptr
+    }
+}
+
+/// The set of CLVM operators available when the compiled program is run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// The operators of the original CLVM dialect.
+    #[default]
+    Base,
+    /// The base operators, as well as `keccak256`.
+    Keccak,
+}
+
+impl Target {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Base => "base",
+            Self::Keccak => "keccak",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "base" => Some(Self::Base),
+            "keccak" => Some(Self::Keccak),
+            _ => None,
+        }
+    }
+
+    pub fn has_keccak256(self) -> bool {
+        self == Self::Keccak
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CompilerOptions {
+    defines: IndexSet<String>,
+    target: Target,
+    dump_symbols: bool,
+    dump_ir: bool,
+    runtime_guards: bool,
+    jump_tables: bool,
+    max_depth: Option<usize>,
+    curried_values: Vec<CurriedValue>,
+    file_starts: Vec<usize>,
+}
+
+impl CompilerOptions {
+    /// Selects the operators which the compiled program is allowed to use.
+    pub fn set_target(&mut self, target: Target) {
+        self.target = target;
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    /// Writes out the resolved symbols and scopes alongside the compiled program, for debugging.
+    pub fn set_dump_symbols(&mut self, dump_symbols: bool) {
+        self.dump_symbols = dump_symbols;
+    }
+
+    pub fn dump_symbols(&self) -> bool {
+        self.dump_symbols
+    }
+
+    /// Writes out the optimized LIR as JSON alongside the compiled program, for external tools.
+    pub fn set_dump_ir(&mut self, dump_ir: bool) {
+        self.dump_ir = dump_ir;
+    }
+
+    pub fn dump_ir(&self) -> bool {
+        self.dump_ir
+    }
+
+    /// Checks the `where` clause of each function when it's called at runtime, in addition to
+    /// calls with constant arguments being checked at compile time.
+    pub fn set_runtime_guards(&mut self, runtime_guards: bool) {
+        self.runtime_guards = runtime_guards;
+    }
+
+    pub fn runtime_guards(&self) -> bool {
+        self.runtime_guards
+    }
+
+    /// Lowers chains of `if` expressions which compare a value against distinct integers to a
+    /// jump table, which uses fewer comparisons but usually costs more for short chains.
+    pub fn set_jump_tables(&mut self, jump_tables: bool) {
+        self.jump_tables = jump_tables;
+    }
+
+    pub fn jump_tables(&self) -> bool {
+        self.jump_tables
+    }
+
+    /// Limits how deeply nested a value which is evaluated at compile time can be, so that a
+    /// pathological program is reported as an error rather than overflowing the stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Curries a constant into `main` in place of its next leading parameter, so that the
+    /// program only takes the remaining parameters as its solution.
+    pub fn curry(&mut self, value: CurriedValue) {
+        self.curried_values.push(value);
+    }
+
+    pub fn curried_values(&self) -> &[CurriedValue] {
+        &self.curried_values
+    }
+
+    /// Splits the source into files which start at these offsets, in order. Items which aren't
+    /// `pub` can only be referenced from the file they're declared in.
+    pub fn set_file_starts(&mut self, file_starts: Vec<usize>) {
+        self.file_starts = file_starts;
+    }
+
+    /// Which file an offset into the source is in, which is always the first without any files.
+    pub fn file_index(&self, offset: usize) -> usize {
+        self.file_starts
+            .iter()
+            .filter(|&&start| start <= offset)
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// Enables items marked with `@cfg(name)`.
+    pub fn define(&mut self, name: impl Into<String>) {
+        self.defines.insert(name.into());
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains(name)
+    }
 }
 
-pub fn analyze(root: Root) -> Vec<Diagnostic> {
+pub fn analyze(root: Root, options: &CompilerOptions) -> Vec<Diagnostic> {
     let mut db = Database::default();
     let scope_id = db.alloc_scope(Scope::default());
-    let mut lowerer = Lowerer::new(&mut db);
+    let mut lowerer = Lowerer::new(&mut db, options);
     lowerer.compile_root(root, scope_id);
     lowerer.finish()
 }
 
-pub fn compile(allocator: &mut Allocator, root: Root, parsing_succeeded: bool) -> Output {
-    let mut db = Database::default();
+pub fn compile(
+    allocator: &mut Allocator,
+    root: Root,
+    parsing_succeeded: bool,
+    options: &CompilerOptions,
+) -> Output {
+    compile_with(
+        &mut Database::default(),
+        allocator,
+        root,
+        parsing_succeeded,
+        options,
+    )
+}
+
+/// Compiles using an existing database, which is cleared first. Long-running processes, such as a
+/// language server or watch mode, can keep one database around and pass it to each compilation,
+/// without memory growing from one compilation to the next. Functions which haven't changed since
+/// an earlier compilation reuse its optimized definitions.
+pub fn compile_with(
+    db: &mut Database,
+    allocator: &mut Allocator,
+    root: Root,
+    parsing_succeeded: bool,
+    options: &CompilerOptions,
+) -> Output {
+    db.clear();
+
     let scope_id = db.alloc_scope(Scope::default());
 
-    let mut lowerer = Lowerer::new(&mut db);
+    let mut lowerer = Lowerer::new(db, options);
     lowerer.compile_root(root, scope_id);
     let mut diagnostics = lowerer.finish();
 
@@ -61,25 +305,592 @@ pub fn compile(allocator: &mut Allocator, root: Root, parsing_succeeded: bool) -
         return Output {
             diagnostics,
             node_ptr: NodePtr::NIL,
+            estimated_cost: 0,
+            symbol_dump: options
+                .dump_symbols()
+                .then(|| dump_symbols(db, scope_id, &IndexMap::new())),
+            ir_json: None,
+            mod_arguments: None,
         };
     };
 
-    let node_ptr = if !diagnostics
+    diagnostics.extend(check_curried_values(
+        db,
+        scope_id,
+        main_id,
+        options.curried_values(),
+    ));
+
+    let mut captures = IndexMap::new();
+    let mut ir_json = None;
+
+    let (node_ptr, estimated_cost) = if !diagnostics
         .iter()
         .any(|diagnostic| diagnostic.kind() == DiagnosticKind::Error)
         && parsing_succeeded
     {
-        let mut optimizer = Optimizer::new(&mut db);
+        let mut optimizer = Optimizer::new(db, options);
         let lir_id = optimizer.opt_main(main_id);
+        captures = optimizer.into_captures();
+
+        let estimated_cost = estimate_cost(db, lir_id);
+
+        if options.dump_ir() {
+            ir_json = Some(dump_lir(db, lir_id));
+        }
 
-        let mut codegen = Codegen::new(&mut db, allocator);
-        codegen.gen_lir(lir_id)
+        let mut codegen = Codegen::new(db, allocator);
+        (codegen.gen_lir(lir_id), estimated_cost)
     } else {
-        NodePtr::NIL
+        (NodePtr::NIL, 0)
     };
 
+    let symbol_dump = options
+        .dump_symbols()
+        .then(|| dump_symbols(db, scope_id, &captures));
+
     Output {
         diagnostics,
         node_ptr,
+        estimated_cost,
+        symbol_dump,
+        ir_json,
+        mod_arguments: mod_arguments(db, main_id, options.curried_values().len()),
+    }
+}
+
+/// Compiles each test block as a separate program, rather than compiling `main`, which is
+/// optional for a file that only has tests.
+pub fn compile_tests(
+    allocator: &mut Allocator,
+    root: Root,
+    parsing_succeeded: bool,
+    options: &CompilerOptions,
+) -> TestOutput {
+    let mut db = Database::default();
+    let scope_id = db.alloc_scope(Scope::default());
+
+    let mut lowerer = Lowerer::new(&mut db, options);
+    lowerer.compile_root(root, scope_id);
+    let entrypoints = lowerer.tests().to_vec();
+    let diagnostics = lowerer.finish();
+
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.kind() == DiagnosticKind::Error)
+        || !parsing_succeeded
+    {
+        return TestOutput {
+            diagnostics,
+            tests: Vec::new(),
+        };
+    }
+
+    // Tests don't take a solution, so nothing is curried into them.
+    let mut options = options.clone();
+    options.curried_values.clear();
+
+    let tests = entrypoints
+        .into_iter()
+        .map(|(name, symbol_id)| {
+            let mut optimizer = Optimizer::new(&mut db, &options);
+            let lir_id = optimizer.opt_main(symbol_id);
+
+            let mut codegen = Codegen::new(&mut db, allocator);
+            let node_ptr = codegen.gen_lir(lir_id);
+
+            Test { name, node_ptr }
+        })
+        .collect();
+
+    TestOutput { diagnostics, tests }
+}
+
+/// The parameters which are left once the curried values are taken from the front.
+fn mod_arguments(db: &Database, main_id: SymbolId, curried: usize) -> Option<String> {
+    let Symbol::Function { scope_id, ty, .. } = db.symbol(main_id) else {
+        return None;
+    };
+
+    let scope = db.scope(*scope_id);
+    let mut names: Vec<&str> = scope
+        .local_symbols()
+        .into_iter()
+        .filter(|&symbol_id| db.symbol(symbol_id).is_parameter())
+        .filter_map(|symbol_id| scope.symbol_name(symbol_id))
+        .skip(curried)
+        .collect();
+
+    let rest = if ty.varargs() { names.pop() } else { None };
+
+    Some(match (names.is_empty(), rest) {
+        (true, Some(rest)) => rest.to_string(),
+        (false, Some(rest)) => format!("({} . {rest})", names.join(" ")),
+        (_, None) => format!("({})", names.join(" ")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use clvmr::{reduction::Reduction, run_program, serde::node_to_bytes, ChiaDialect, SExp};
+    use num_bigint::BigInt;
+    use rue_parser::parse;
+
+    use crate::{hir::Hir, lir::Lir};
+
+    use super::*;
+
+    #[test]
+    fn test_reused_database() {
+        let source = "fun main() -> Int { double(21) } fun double(value: Int) -> Int { value * 2 }";
+        let mut db = Database::default();
+
+        let mut next_indices = Vec::new();
+
+        for _ in 0..100 {
+            let (root, errors) = parse(source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile_with(
+                &mut db,
+                &mut allocator,
+                root,
+                true,
+                &CompilerOptions::default(),
+            );
+            assert!(output.diagnostics().is_empty());
+
+            next_indices.push((
+                db.alloc_hir(Hir::Unknown).index(),
+                db.alloc_lir(Lir::Path(1)).index(),
+            ));
+        }
+
+        let (hir_indices, lir_indices): (Vec<usize>, Vec<usize>) = next_indices.into_iter().unzip();
+
+        // The next HIR index only stays the same if none of it is retained from the last
+        // compilation. The LIR keeps the function definitions, but is compacted before it grows
+        // to more than twice their size.
+        assert!(hir_indices.iter().all(|&index| index == hir_indices[0]));
+        assert!(lir_indices.iter().all(|&index| index <= 2 * lir_indices[0]));
+    }
+
+    fn compile_source(db: &mut Database, allocator: &mut Allocator, source: &str) -> Vec<u8> {
+        let (root, errors) = parse(source);
+        let output = compile_with(
+            db,
+            allocator,
+            root,
+            errors.is_empty(),
+            &CompilerOptions::default(),
+        );
+        node_to_bytes(allocator, output.node_ptr()).unwrap()
+    }
+
+    /// A definition from an earlier compilation is only reused when it would be optimized to the
+    /// same LIR, so compiling every test program with one database gives the same output as
+    /// compiling each of them on its own.
+    #[test]
+    fn test_cached_definitions() {
+        let tests_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests");
+        let mut paths: Vec<PathBuf> = fs::read_dir(tests_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "rue"))
+            .collect();
+        paths.sort();
+
+        let sources: Vec<String> = paths
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+
+        let mut db = Database::default();
+        let mut allocator = Allocator::new();
+
+        for (path, source) in paths
+            .iter()
+            .zip(&sources)
+            .chain(paths.iter().zip(&sources).rev())
+        {
+            let cached = compile_source(&mut db, &mut allocator, source);
+            let fresh = compile_source(&mut Database::default(), &mut allocator, source);
+            assert_eq!(cached, fresh, "{}", path.display());
+        }
+    }
+
+    /// Recompiling after editing one function only optimizes that function and whatever calls
+    /// it, rather than every function in the program.
+    #[test]
+    fn test_recompile_after_edit() {
+        let source = |edited: usize| {
+            let mut source = String::from("fun main(value: Int) -> Int { ");
+            source.push_str(
+                &(0..16)
+                    .map(|i| format!("f{i}(value)"))
+                    .collect::<Vec<_>>()
+                    .join(" + "),
+            );
+            source.push_str(" }\n");
+
+            for i in 0..16 {
+                let body = (0..16)
+                    .map(|j| format!("value * {}", i + j + usize::from(i == edited)))
+                    .collect::<Vec<_>>()
+                    .join(" + ");
+                source.push_str(&format!("fun f{i}(value: Int) -> Int {{ {body} }}\n"));
+            }
+
+            source
+        };
+
+        let mut allocator = Allocator::new();
+
+        let mut fresh_db = Database::default();
+        let fresh = compile_source(&mut fresh_db, &mut allocator, &source(7));
+        let fresh_count = fresh_db.alloc_lir(Lir::Path(1)).index();
+
+        let mut db = Database::default();
+        compile_source(&mut db, &mut allocator, &source(usize::MAX));
+        db.clear();
+        let retained = db.alloc_lir(Lir::Path(1)).index();
+
+        let recompiled = compile_source(&mut db, &mut allocator, &source(7));
+        let recompiled_count = db.alloc_lir(Lir::Path(1)).index() - retained;
+
+        assert_eq!(recompiled, fresh);
+        assert!(
+            recompiled_count * 8 < fresh_count,
+            "{recompiled_count} of {fresh_count} LIR nodes were built again"
+        );
+    }
+
+    /// A function is optimized once, however many times it's called, so each extra call only adds
+    /// the LIR for the call itself rather than another copy of the function's body.
+    #[test]
+    fn test_memoized_definitions() {
+        let lir_count = |calls: usize| {
+            let body = (0..32)
+                .map(|i| format!("value * {i}"))
+                .collect::<Vec<_>>()
+                .join(" + ");
+            let calls = vec!["big(value)"; calls].join(" + ");
+            let source = format!(
+                "fun main(value: Int) -> Int {{ {calls} }} fun big(value: Int) -> Int {{ {body} }}"
+            );
+
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut db = Database::default();
+            let mut allocator = Allocator::new();
+            let output = compile_with(
+                &mut db,
+                &mut allocator,
+                root,
+                true,
+                &CompilerOptions::default(),
+            );
+            assert!(output.diagnostics().is_empty());
+
+            db.alloc_lir(Lir::Path(1)).index()
+        };
+
+        let one = lir_count(1);
+        let two = lir_count(2);
+        let many = lir_count(16);
+
+        let per_call = two - one;
+        assert!(
+            per_call * 4 < one,
+            "a call is as large as the body: {per_call} of {one}"
+        );
+        assert_eq!(many - one, per_call * 15);
+    }
+
+    fn estimated_cost(source: &str) -> u64 {
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(output.diagnostics().is_empty());
+
+        output.estimated_cost()
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let add = estimated_cost("fun main(value: Int) -> Int { value + 1 }");
+        let add_mul = estimated_cost("fun main(value: Int) -> Int { (value + 1) * value }");
+        assert!(add > 0);
+        assert!(add_mul > add);
+    }
+
+    /// The remainder in a constant is computed at compile time, and otherwise by `divmod` when the
+    /// program is run, so the two need to agree for every combination of signs.
+    #[test]
+    fn test_remainder_signs() {
+        for (lhs, rhs, expected) in [
+            (7, 2, 1),
+            (-7, 2, 1),
+            (7, -2, -1),
+            (-7, -2, -1),
+            (6, 3, 0),
+            (-6, 3, 0),
+            (0, -5, 0),
+        ] {
+            let literal = |value: i64| {
+                if value < 0 {
+                    format!("(0 - {})", -value)
+                } else {
+                    value.to_string()
+                }
+            };
+
+            let source = format!(
+                "const REMAINDER: Int = {} % {};
+
+                fun main(lhs: Int, rhs: Int) -> (Int, Int) {{
+                    (REMAINDER, lhs % rhs)
+                }}",
+                literal(lhs),
+                literal(rhs)
+            );
+
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            assert!(output.diagnostics().is_empty());
+
+            let lhs_ptr = allocator.new_number(lhs.into()).unwrap();
+            let rhs_ptr = allocator.new_number(rhs.into()).unwrap();
+            let solution = allocator.new_pair(rhs_ptr, NodePtr::NIL).unwrap();
+            let solution = allocator.new_pair(lhs_ptr, solution).unwrap();
+
+            let Reduction(_, result) = run_program(
+                &mut allocator,
+                &ChiaDialect::new(0),
+                output.node_ptr(),
+                solution,
+                u64::MAX,
+            )
+            .unwrap();
+
+            let SExp::Pair(constant, runtime) = allocator.sexp(result) else {
+                panic!("expected a pair");
+            };
+
+            let expected = BigInt::from(expected);
+            assert_eq!(allocator.number(constant), expected, "{lhs} % {rhs}");
+            assert_eq!(allocator.number(runtime), expected, "{lhs} % {rhs}");
+        }
+    }
+
+    #[test]
+    fn test_serialized_length() {
+        let long = format!("0x{}", "ab".repeat(100));
+
+        for value in [
+            "0x0102",
+            "0x7f",
+            "0x80",
+            "nil",
+            "[1, 2, 300]",
+            long.as_str(),
+        ] {
+            let source = format!(
+                "fun main() -> (Int, Any) {{
+                    (serialized_length({value}), {value})
+                }}"
+            );
+
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            assert!(output.diagnostics().is_empty(), "{value}");
+
+            let Reduction(_, result) = run_program(
+                &mut allocator,
+                &ChiaDialect::new(0),
+                output.node_ptr(),
+                NodePtr::NIL,
+                u64::MAX,
+            )
+            .unwrap();
+
+            let SExp::Pair(length, value_ptr) = allocator.sexp(result) else {
+                panic!("expected a pair");
+            };
+
+            let serialized = node_to_bytes(&allocator, value_ptr).unwrap();
+            assert_eq!(
+                allocator.number(length),
+                BigInt::from(serialized.len()),
+                "{value}"
+            );
+        }
+    }
+
+    /// A chain of constants which each refer to the last is evaluated recursively, so a long one
+    /// is reported rather than overflowing the stack.
+    #[test]
+    fn test_max_depth() {
+        let codes = |len: usize, max_depth: Option<usize>| {
+            let mut source = "const C0: Any = 1;".to_string();
+            for i in 1..len {
+                source.push_str(&format!("const C{i}: Any = (C{}, 1);", i - 1));
+            }
+            source.push_str(&format!(
+                "fun main() -> Int {{ serialized_length(C{}) }}",
+                len - 1
+            ));
+
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut options = CompilerOptions::default();
+            if let Some(max_depth) = max_depth {
+                options.set_max_depth(max_depth);
+            }
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &options);
+            output
+                .diagnostics()
+                .iter()
+                .map(Diagnostic::code)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(codes(50, Some(100)).is_empty());
+        assert_eq!(codes(200, Some(100)), ["E0069"]);
+        assert!(codes(500, None).is_empty());
+        assert_eq!(codes(5000, None), ["E0069"]);
+    }
+
+    #[test]
+    fn test_shared_let_bindings() {
+        // Each binding refers to the last twice, so checking whether `main` always recurses would
+        // take exponential time if each reference were followed separately.
+        let mut source = "fun main(x0: Int) -> Int {".to_string();
+        for i in 1..100 {
+            source.push_str(&format!("let x{i} = x{} + x{};", i - 1, i - 1));
+        }
+        source.push_str("x99 }");
+
+        let (root, errors) = parse(&source);
+        assert!(errors.is_empty());
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+        assert!(output.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_builtins_registered() {
+        assert!(BUILTINS
+            .windows(2)
+            .all(|pair| pair[0].name() < pair[1].name()));
+
+        // Each registered builtin can be called, whether it's defined in the root scope or calls
+        // to it are compiled directly, even if the arguments are wrong.
+        for builtin in BUILTINS {
+            let source = format!("fun main() -> Any {{ {}() }}", builtin.name());
+            let (root, errors) = parse(&source);
+            assert!(errors.is_empty());
+
+            let mut allocator = Allocator::new();
+            let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+            assert!(
+                output.diagnostics().iter().all(|diagnostic| !matches!(
+                    diagnostic.info(),
+                    DiagnosticInfo::UndefinedReference(..)
+                        | DiagnosticInfo::UndefinedReferenceSuggestion { .. }
+                )),
+                "{}",
+                builtin.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_deterministic_output() {
+        let source = "fun main(a: Int, b: Int, c: Int) -> Int {
+            let d = a + b;
+            let e = c * d;
+            fun inner(x: Int) -> Int { c + b + a + x }
+            let scale = fun(y: Int) => y * e * inner(d);
+            inner(1) + scale(2)
+        }";
+
+        // Each thread hashes with different keys, so the output would differ if it depended on
+        // the iteration order of a hashed collection.
+        let outputs: Vec<Vec<u8>> = (0..8)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let (root, errors) = parse(source);
+                    assert!(errors.is_empty());
+
+                    let mut allocator = Allocator::new();
+                    let output = compile(&mut allocator, root, true, &CompilerOptions::default());
+                    assert!(output.diagnostics().is_empty());
+
+                    node_to_bytes(&allocator, output.node_ptr()).unwrap()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+
+        assert!(outputs.iter().all(|output| output == &outputs[0]));
+    }
+
+    #[test]
+    fn test_dump_symbols() {
+        let source = "fun main(value: Int) -> Int {
+            let offset = value * 2;
+            fun add(amount: Int) -> Int { value + amount }
+            let scale = fun(factor: Int) => factor * offset;
+            add(1) + scale(3)
+        }";
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+
+        let mut options = CompilerOptions::default();
+        options.set_dump_symbols(true);
+
+        let mut allocator = Allocator::new();
+        let output = compile(&mut allocator, root, true, &options);
+        assert!(output.diagnostics().is_empty());
+
+        let expected = "\
+scope 0
+  function main: fun(Int) -> Int captures [<lambda>]
+    scope 1
+      parameter value: Int
+      function add: fun(Int) -> Int captures [value]
+        scope 2
+          parameter amount: Int
+      scope 3
+        let offset: Int
+        scope 4
+          let scale: fun(Int) -> Int
+            function <lambda>: fun(Int) -> Int captures [offset]
+              scope 5
+                parameter factor: Int
+";
+        assert_eq!(output.symbol_dump(), Some(expected));
     }
 }
@@ -13,9 +13,16 @@ pub enum Lir {
     Rest(LirId),
     Raise(Option<LirId>),
     Sha256(LirId),
+    Keccak256(LirId),
     IsCons(LirId),
     Strlen(LirId),
+    Substr(LirId, LirId, LirId),
+    Softfork(LirId, LirId),
+    Op(Vec<u8>, Vec<LirId>),
     If(LirId, LirId, LirId),
+    /// Runs the branch at the given path into a constant list of branches, with the current
+    /// environment, such as for a chain of `if` expressions which compare against constants.
+    Dispatch(LirId, Vec<LirId>),
     Not(LirId),
     Any(Vec<LirId>),
     Concat(Vec<LirId>),
@@ -26,4 +33,51 @@ pub enum Lir {
     Divmod(LirId, LirId),
     Eq(LirId, LirId),
     Gt(LirId, LirId),
+    Ash(LirId, LirId),
+    Logand(LirId, LirId),
+}
+
+impl Lir {
+    /// The same node, with each of its children replaced.
+    pub(crate) fn map_children(&self, mut f: impl FnMut(LirId) -> LirId) -> Self {
+        fn map_all(ids: &[LirId], f: &mut impl FnMut(LirId) -> LirId) -> Vec<LirId> {
+            ids.iter().map(|&id| f(id)).collect()
+        }
+
+        match self {
+            Self::Atom(atom) => Self::Atom(atom.clone()),
+            Self::Path(path) => Self::Path(*path),
+            Self::Pair(first, rest) => Self::Pair(f(*first), f(*rest)),
+            Self::Run(program, args) => Self::Run(f(*program), f(*args)),
+            Self::Curry(body, args) => Self::Curry(f(*body), map_all(args, &mut f)),
+            Self::Closure(body, captures) => Self::Closure(f(*body), map_all(captures, &mut f)),
+            Self::FunctionBody(body) => Self::FunctionBody(f(*body)),
+            Self::First(value) => Self::First(f(*value)),
+            Self::Rest(value) => Self::Rest(f(*value)),
+            Self::Raise(value) => Self::Raise(value.map(f)),
+            Self::Sha256(value) => Self::Sha256(f(*value)),
+            Self::Keccak256(value) => Self::Keccak256(f(*value)),
+            Self::IsCons(value) => Self::IsCons(f(*value)),
+            Self::Strlen(value) => Self::Strlen(f(*value)),
+            Self::Substr(value, start, end) => Self::Substr(f(*value), f(*start), f(*end)),
+            Self::Softfork(cost, program) => Self::Softfork(f(*cost), f(*program)),
+            Self::Op(opcode, args) => Self::Op(opcode.clone(), map_all(args, &mut f)),
+            Self::If(condition, then_branch, else_branch) => {
+                Self::If(f(*condition), f(*then_branch), f(*else_branch))
+            }
+            Self::Dispatch(path, branches) => Self::Dispatch(f(*path), map_all(branches, &mut f)),
+            Self::Not(value) => Self::Not(f(*value)),
+            Self::Any(values) => Self::Any(map_all(values, &mut f)),
+            Self::Concat(values) => Self::Concat(map_all(values, &mut f)),
+            Self::Add(values) => Self::Add(map_all(values, &mut f)),
+            Self::Sub(values) => Self::Sub(map_all(values, &mut f)),
+            Self::Mul(values) => Self::Mul(map_all(values, &mut f)),
+            Self::Div(lhs, rhs) => Self::Div(f(*lhs), f(*rhs)),
+            Self::Divmod(lhs, rhs) => Self::Divmod(f(*lhs), f(*rhs)),
+            Self::Eq(lhs, rhs) => Self::Eq(f(*lhs), f(*rhs)),
+            Self::Gt(lhs, rhs) => Self::Gt(f(*lhs), f(*rhs)),
+            Self::Ash(value, shift) => Self::Ash(f(*value), f(*shift)),
+            Self::Logand(lhs, rhs) => Self::Logand(f(*lhs), f(*rhs)),
+        }
+    }
 }
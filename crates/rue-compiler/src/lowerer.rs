@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
-    fmt,
+    fmt, mem,
     str::FromStr,
 };
 
@@ -9,31 +9,87 @@ use num_bigint::BigInt;
 use rowan::TextRange;
 use rue_parser::{
     AstNode, BinaryExpr, BinaryOp, Block, CastExpr, ConstItem, EnumItem, Expr, FieldAccess,
-    FunctionCall, FunctionItem, FunctionType as AstFunctionType, GroupExpr, GuardExpr, IfExpr,
-    IndexAccess, InitializerExpr, InitializerField, Item, LambdaExpr, LetStmt, ListExpr, ListType,
-    LiteralExpr, PairExpr, PairType, Path, PrefixExpr, PrefixOp, Root, Stmt, StructField,
-    StructItem, SyntaxKind, SyntaxToken, Type as AstType, TypeAliasItem,
+    FunctionCall, FunctionCallArg, FunctionItem, FunctionType as AstFunctionType, GroupExpr,
+    GuardExpr, IfExpr, ImplItem, IndexAccess, InitializerExpr, InitializerField, Item,
+    LabeledBlock, LambdaExpr, LetStmt, ListExpr, ListType, LiteralExpr, MatchExpr, PairExpr, Path,
+    PrefixExpr, PrefixOp, Root, StaticAssertItem, Stmt, StructField, StructItem, StructPattern,
+    SyntaxKind, SyntaxToken, TestItem, TuplePattern, TupleType, Type as AstType, TypeAliasItem,
 };
 
 use crate::{
+    builtins::{builtin, Builtin, Lowering, Op, OperandType},
+    const_eval::{eval_hir, eval_hir_with_params, eval_serialized_length, TooDeep},
     database::{Database, HirId, ScopeId, SymbolId, TypeId},
+    eval_order::{may_fail, sequence_operands},
     hir::{Hir, HirBinaryOp},
+    optimizer::{MAX_JUMP_TABLE_SIZE, MIN_JUMP_TABLE_ARMS},
+    recursion::always_recurses,
     scope::Scope,
     symbol::Symbol,
-    ty::{EnumType, EnumVariant, FunctionType, Guard, StructType, Type, Value},
-    Diagnostic, DiagnosticInfo, DiagnosticKind,
+    ty::{type_name, EnumType, EnumVariant, FunctionType, Guard, StructType, Type, Value},
+    CompilerOptions, Diagnostic, DiagnosticInfo, DiagnosticKind,
 };
 
+/// The widest encoding `le_bytes` can produce, since it generates code for each byte.
+const MAX_LE_BYTES_WIDTH: usize = 64;
+
+/// The widest value `truncate` and `pad` can produce, since `pad` includes that many zero bytes.
+const MAX_RESIZE_WIDTH: usize = 1024;
+
 pub struct Lowerer<'a> {
     db: &'a mut Database,
+    options: &'a CompilerOptions,
     scope_stack: Vec<ScopeId>,
     type_guards: Vec<HashMap<SymbolId, TypeId>>,
     diagnostics: Vec<Diagnostic>,
+    operations: Vec<HirId>,
+    /// The labels of every labeled block enclosing the current one, within the current function.
+    labels: Vec<String>,
+    /// The label which a `break` statement can target from where it is, if any.
+    break_label: Option<String>,
+    /// Whether the body being compiled is a test block, which doesn't need a trailing expression.
+    in_test: bool,
+    /// The name and entrypoint of each test block, in the order they're declared.
+    tests: Vec<(String, SymbolId)>,
+    /// Builtins which use operators that the target doesn't have.
+    unavailable_builtins: HashSet<SymbolId>,
+    /// The functions which `contains` and `index_of` call, once their arguments are type checked.
+    contains_function: SymbolId,
+    index_of_function: SymbolId,
+    /// The same, for items which are lists or pairs, and so are compared by their contents.
+    structured_contains_function: SymbolId,
+    structured_index_of_function: SymbolId,
+    /// The functions which `zip` and `enumerate` call, once their arguments are type checked.
+    zip_function: SymbolId,
+    enumerate_function: SymbolId,
+    /// The functions which `head`, `tail` and `last` call, which check for an empty list first.
+    head_function: SymbolId,
+    tail_function: SymbolId,
+    last_function: SymbolId,
+    /// The function which `reverse` calls, which moves each item onto the front of a result.
+    reverse_function: SymbolId,
+    /// The function which `nth` and `nth_opt` call to skip the items before the index.
+    skip_function: SymbolId,
+    /// The function which `==` and `!=` call to compare lists and pairs by their contents.
+    equals_function: SymbolId,
+    /// The functions declared in `impl` blocks, by the struct they're declared for.
+    methods: HashMap<TypeId, IndexMap<String, SymbolId>>,
+    /// The name and `where` clause conditions of each function which has any.
+    guards: HashMap<SymbolId, (String, Vec<HirId>)>,
+    /// The name and message of each function marked with `@deprecated`.
+    deprecated: HashMap<SymbolId, (String, String)>,
+    /// Where each item which isn't `pub` is declared, since it can only be used from that file.
+    private_symbols: HashMap<SymbolId, usize>,
+    private_types: HashMap<TypeId, usize>,
+    /// Calls to functions, with their arguments, to check against any `where` clause at the end.
+    guarded_calls: Vec<(SymbolId, HirId, TextRange)>,
     any_type: TypeId,
     int_type: TypeId,
     bool_type: TypeId,
     bytes_type: TypeId,
     bytes32_type: TypeId,
+    public_key_type: TypeId,
+    signature_type: TypeId,
     nil_type: TypeId,
     nil_hir: HirId,
     unknown_type: TypeId,
@@ -41,11 +97,13 @@ pub struct Lowerer<'a> {
 }
 
 impl<'a> Lowerer<'a> {
-    pub fn new(db: &'a mut Database) -> Self {
+    pub fn new(db: &'a mut Database, options: &'a CompilerOptions) -> Self {
         let int_type = db.alloc_type(Type::Int);
         let bool_type = db.alloc_type(Type::Bool);
         let bytes_type = db.alloc_type(Type::Bytes);
         let bytes32_type = db.alloc_type(Type::Bytes32);
+        let public_key_type = db.alloc_type(Type::PublicKey);
+        let signature_type = db.alloc_type(Type::Signature);
         let any_type = db.alloc_type(Type::Any);
         let nil_type = db.alloc_type(Type::Nil);
         let nil_hir = db.alloc_hir(Hir::Atom(Vec::new()));
@@ -58,47 +116,282 @@ impl<'a> Lowerer<'a> {
         builtins.define_type("Bool".to_string(), bool_type);
         builtins.define_type("Bytes".to_string(), bytes_type);
         builtins.define_type("Bytes32".to_string(), bytes32_type);
+        builtins.define_type("PublicKey".to_string(), public_key_type);
+        builtins.define_type("Signature".to_string(), signature_type);
         builtins.define_type("Any".to_string(), any_type);
 
         {
+            // Clamps both indices into the value, and the end to be no less than the start,
+            // so that the slice can never raise.
             let mut scope = Scope::default();
-            let param = db.alloc_symbol(Symbol::Parameter {
+            let value = db.alloc_symbol(Symbol::Parameter {
                 type_id: bytes_type,
             });
-            scope.define_symbol("bytes".to_string(), param);
-            let param_ref = db.alloc_hir(Hir::Reference(param));
-            let hir_id = db.alloc_hir(Hir::Sha256(param_ref));
+            let start = db.alloc_symbol(Symbol::Parameter { type_id: int_type });
+            let end = db.alloc_symbol(Symbol::Parameter { type_id: int_type });
+            scope.define_symbol("value".to_string(), value);
+            scope.define_symbol("start".to_string(), start);
+            scope.define_symbol("end".to_string(), end);
+            let value_ref = db.alloc_hir(Hir::Reference(value));
+            let start_ref = db.alloc_hir(Hir::Reference(start));
+            let end_ref = db.alloc_hir(Hir::Reference(end));
+
+            let length_hir = db.alloc_hir(Hir::Strlen(value_ref));
+            let length = db.alloc_symbol(Symbol::LetBinding {
+                type_id: int_type,
+                hir_id: length_hir,
+            });
+            let length_ref = db.alloc_hir(Hir::Reference(length));
+
+            let clamped_start_hir = clamp(db, start_ref, nil_hir, length_ref);
+            let clamped_start = db.alloc_symbol(Symbol::LetBinding {
+                type_id: int_type,
+                hir_id: clamped_start_hir,
+            });
+            let clamped_start_ref = db.alloc_hir(Hir::Reference(clamped_start));
+
+            let clamped_end_hir = clamp(db, end_ref, clamped_start_ref, length_ref);
+            let clamped_end = db.alloc_symbol(Symbol::LetBinding {
+                type_id: int_type,
+                hir_id: clamped_end_hir,
+            });
+            let clamped_end_ref = db.alloc_hir(Hir::Reference(clamped_end));
+
+            let mut hir_id = db.alloc_hir(Hir::Substr {
+                value: value_ref,
+                start: clamped_start_ref,
+                end: clamped_end_ref,
+            });
+
+            for symbol_id in [clamped_end, clamped_start, length] {
+                let mut binding_scope = Scope::default();
+                binding_scope.define_hidden_symbol(symbol_id);
+                let scope_id = db.alloc_scope(binding_scope);
+                hir_id = db.alloc_hir(Hir::Scope {
+                    scope_id,
+                    value: hir_id,
+                });
+            }
+
             let scope_id = db.alloc_scope(scope);
 
             builtins.define_symbol(
-                "sha256".to_string(),
+                "substr_clamped".to_string(),
                 db.alloc_symbol(Symbol::Function {
                     scope_id,
                     hir_id,
-                    ty: FunctionType::new(vec![bytes_type], bytes32_type, false),
+                    ty: FunctionType::new(vec![bytes_type, int_type, int_type], bytes_type, false),
                 }),
             );
         }
 
+        // Each condition is a list of its opcode followed by its arguments, as output by a puzzle.
+        let condition_type = db.alloc_type(Type::List(any_type));
+
+        for (name, params) in [
+            (
+                "agg_sig_unsafe",
+                vec![("public_key", public_key_type), ("message", bytes_type)],
+            ),
+            (
+                "agg_sig_me",
+                vec![("public_key", public_key_type), ("message", bytes_type)],
+            ),
+            (
+                "create_coin",
+                vec![("puzzle_hash", bytes32_type), ("amount", int_type)],
+            ),
+            ("reserve_fee", vec![("amount", int_type)]),
+            ("create_coin_announcement", vec![("message", bytes_type)]),
+            (
+                "assert_coin_announcement",
+                vec![("announcement_id", bytes32_type)],
+            ),
+            ("create_puzzle_announcement", vec![("message", bytes_type)]),
+            (
+                "assert_puzzle_announcement",
+                vec![("announcement_id", bytes32_type)],
+            ),
+            ("assert_my_coin_id", vec![("coin_id", bytes32_type)]),
+            ("assert_my_parent_id", vec![("parent_id", bytes32_type)]),
+            ("assert_my_puzzle_hash", vec![("puzzle_hash", bytes32_type)]),
+            ("assert_my_amount", vec![("amount", int_type)]),
+            ("assert_seconds_relative", vec![("seconds", int_type)]),
+            ("assert_seconds_absolute", vec![("seconds", int_type)]),
+            ("assert_height_relative", vec![("height", int_type)]),
+            ("assert_height_absolute", vec![("height", int_type)]),
+        ] {
+            let Some(Lowering::Condition(opcode)) = builtin(name).map(Builtin::lowering) else {
+                unreachable!("`{name}` isn't registered as a condition");
+            };
+            let symbol_id = condition_constructor(db, opcode, &params, condition_type, nil_hir);
+            builtins.define_symbol(name.to_string(), symbol_id);
+        }
+
+        let equals_function = structural_equals(db, any_type, bool_type, nil_hir);
+        let contains_function = list_contains(db, any_type, bool_type, None, nil_hir);
+        let index_of_function = list_index_of(db, any_type, int_type, None, nil_hir);
+        let structured_contains_function =
+            list_contains(db, any_type, bool_type, Some(equals_function), nil_hir);
+        let structured_index_of_function =
+            list_index_of(db, any_type, int_type, Some(equals_function), nil_hir);
+        let zip_function = list_zip(db, any_type, nil_hir);
+        let enumerate_function = list_enumerate(db, any_type, int_type, nil_hir);
+        let head_function = list_head(db, any_type, nil_hir);
+        let tail_function = list_tail(db, any_type, nil_hir);
+        let last_function = list_last(db, any_type, nil_hir);
+        let reverse_function = list_reverse(db, any_type, nil_hir);
+        let skip_function = list_skip(db, any_type, int_type, nil_hir);
+
         let builtins_id = db.alloc_scope(builtins);
 
-        Self {
+        let mut lowerer = Self {
             db,
+            options,
             scope_stack: vec![builtins_id],
             type_guards: Vec::new(),
             diagnostics: Vec::new(),
+            operations: Vec::new(),
+            labels: Vec::new(),
+            break_label: None,
+            in_test: false,
+            tests: Vec::new(),
+            unavailable_builtins: HashSet::new(),
+            contains_function,
+            index_of_function,
+            structured_contains_function,
+            structured_index_of_function,
+            zip_function,
+            enumerate_function,
+            head_function,
+            tail_function,
+            last_function,
+            reverse_function,
+            skip_function,
+            equals_function,
+            methods: HashMap::new(),
+            guards: HashMap::new(),
+            deprecated: HashMap::new(),
+            private_symbols: HashMap::new(),
+            private_types: HashMap::new(),
+            guarded_calls: Vec::new(),
             any_type,
             int_type,
             bool_type,
             bytes_type,
             bytes32_type,
+            public_key_type,
+            signature_type,
             nil_type,
             nil_hir,
             unknown_type,
             unknown_hir,
+        };
+
+        lowerer.define_operator_builtins();
+
+        debug_assert!(
+            lowerer
+                .db
+                .scope(builtins_id)
+                .local_symbols()
+                .into_iter()
+                .filter_map(|symbol_id| lowerer.db.scope(builtins_id).symbol_name(symbol_id))
+                .all(|name| builtin(name).is_some()),
+            "every builtin must be registered"
+        );
+
+        lowerer
+    }
+
+    /// Defines the builtin functions which are compiled to a single operator, with the parameter
+    /// and return types of that operator in the table.
+    fn define_operator_builtins(&mut self) {
+        let builtins_id = self.scope_stack[0];
+
+        for (name, params) in [
+            ("sha256", &["bytes"][..]),
+            ("keccak256", &["bytes"]),
+            ("apply", &["program", "env"]),
+            ("substr", &["value", "start", "end"]),
+            ("divmod", &["lhs", "rhs"]),
+        ] {
+            let Some(Lowering::Operator(op)) = builtin(name).map(Builtin::lowering) else {
+                unreachable!("`{name}` isn't registered as an operator");
+            };
+            let operator = op.operator();
+            debug_assert_eq!(params.len(), operator.arity(), "{name}");
+
+            let mut scope = Scope::default();
+            let mut param_types = Vec::new();
+            let mut args = Vec::new();
+
+            for (&param, &arg_type) in params.iter().zip(operator.arg_types()) {
+                let type_id = self.operand_type(arg_type);
+                let symbol_id = self.db.alloc_symbol(Symbol::Parameter { type_id });
+                scope.define_symbol(param.to_string(), symbol_id);
+                param_types.push(type_id);
+                args.push(self.db.alloc_hir(Hir::Reference(symbol_id)));
+            }
+
+            // The program passed to `apply` is run as is, so neither it nor its environment or
+            // result are typed.
+            let hir = match (op, args.as_slice()) {
+                (Op::Sha256, &[bytes]) => Hir::Sha256(bytes),
+                (Op::Keccak256, &[bytes]) => Hir::Keccak256(bytes),
+                (Op::Apply, &[program, env]) => Hir::FunctionCall {
+                    callee: program,
+                    args: env,
+                },
+                (Op::Substr, &[value, start, end]) => Hir::Substr { value, start, end },
+                (Op::Divmod, &[lhs, rhs]) => Hir::BinaryOp {
+                    op: HirBinaryOp::Divmod,
+                    lhs,
+                    rhs,
+                },
+                _ => unreachable!("`{name}` can't be defined as a function"),
+            };
+            let hir_id = self.db.alloc_hir(hir);
+            let scope_id = self.db.alloc_scope(scope);
+            let return_type = self.operand_type(operator.result_type());
+
+            let symbol_id = self.db.alloc_symbol(Symbol::Function {
+                scope_id,
+                hir_id,
+                ty: FunctionType::new(param_types, return_type, false),
+            });
+            self.db
+                .scope_mut(builtins_id)
+                .define_symbol(name.to_string(), symbol_id);
+
+            if op == Op::Keccak256 && !self.options.target().has_keccak256() {
+                self.unavailable_builtins.insert(symbol_id);
+            }
+        }
+    }
+
+    /// The builtin type which an operand type in the operator table refers to.
+    fn operand_type(&mut self, ty: OperandType) -> TypeId {
+        match ty {
+            OperandType::Any => self.any_type,
+            OperandType::Bool => self.bool_type,
+            OperandType::Int => self.int_type,
+            OperandType::Bytes => self.bytes_type,
+            OperandType::Bytes32 => self.bytes32_type,
+            OperandType::Nil => self.nil_type,
+            OperandType::Pair(first, rest) => {
+                let first = self.operand_type(*first);
+                let rest = self.operand_type(*rest);
+                self.db.alloc_type(Type::Pair(first, rest))
+            }
         }
     }
 
+    /// The name and entrypoint of each test block which has been compiled.
+    pub fn tests(&self) -> &[(String, SymbolId)] {
+        &self.tests
+    }
+
     pub fn finish(self) -> Vec<Diagnostic> {
         self.diagnostics
     }
@@ -107,9 +400,24 @@ impl<'a> Lowerer<'a> {
         self.scope_stack.push(scope_id);
         self.compile_items(root.items());
         self.scope_stack.pop();
+
+        for (symbol_id, args, range) in mem::take(&mut self.guarded_calls) {
+            self.check_guard(symbol_id, args, range);
+        }
+
+        // Every function body is known by now, so calls can be checked for raises.
+        for hir_id in mem::take(&mut self.operations) {
+            sequence_operands(self.db, hir_id, self.unknown_type);
+        }
     }
 
     fn compile_items(&mut self, items: Vec<Item>) {
+        // Items which are configured out are dropped before anything is declared.
+        let items: Vec<Item> = items
+            .into_iter()
+            .filter(|item| self.is_item_enabled(item))
+            .collect();
+
         let mut type_ids = Vec::new();
         let mut symbol_ids = Vec::new();
 
@@ -130,6 +438,31 @@ impl<'a> Lowerer<'a> {
             }
         }
 
+        let type_items = items.iter().filter(|item| {
+            matches!(
+                item,
+                Item::TypeAliasItem(..) | Item::StructItem(..) | Item::EnumItem(..)
+            )
+        });
+
+        for (item, &type_id) in type_items.zip(&type_ids) {
+            if !item.is_pub() {
+                self.private_types
+                    .insert(type_id, item.syntax().text_range().start().into());
+            }
+        }
+
+        let symbol_items = items
+            .iter()
+            .filter(|item| matches!(item, Item::FunctionItem(..) | Item::ConstItem(..)));
+
+        for (item, &symbol_id) in symbol_items.zip(&symbol_ids) {
+            if !item.is_pub() {
+                self.private_symbols
+                    .insert(symbol_id, item.syntax().text_range().start().into());
+            }
+        }
+
         for item in items.clone() {
             match item {
                 Item::TypeAliasItem(ty) => {
@@ -145,7 +478,20 @@ impl<'a> Lowerer<'a> {
             }
         }
 
-        for item in items {
+        // Methods are declared once the struct they're for is known.
+        let mut methods = Vec::new();
+
+        for item in items.clone() {
+            if let Item::ImplItem(impl_item) = item {
+                methods.extend(self.declare_impl(impl_item));
+            }
+        }
+
+        for (function, symbol_id) in methods {
+            self.compile_function(function, symbol_id);
+        }
+
+        for item in items.clone() {
             match item {
                 Item::FunctionItem(function) => {
                     self.compile_function(function, symbol_ids.remove(0));
@@ -156,9 +502,90 @@ impl<'a> Lowerer<'a> {
                 _ => {}
             }
         }
+
+        // Static assertions are checked last, so that they can reference any constant.
+        for item in items.clone() {
+            if let Item::StaticAssertItem(static_assert) = item {
+                self.compile_static_assert(static_assert);
+            }
+        }
+
+        for item in items {
+            if let Item::TestItem(test) = item {
+                self.compile_test(test);
+            }
+        }
+    }
+
+    fn is_item_enabled(&mut self, item: &Item) -> bool {
+        let mut enabled = true;
+
+        for attribute in item.attributes() {
+            let Some(name) = attribute.name() else {
+                continue;
+            };
+
+            match name.text() {
+                "cfg" => {
+                    let args = attribute.args();
+
+                    if args.len() != 1 {
+                        self.error(
+                            DiagnosticInfo::AttributeArgumentMismatch {
+                                expected: 1,
+                                found: args.len(),
+                            },
+                            attribute.syntax().text_range(),
+                        );
+                        continue;
+                    }
+
+                    if !self.options.is_defined(args[0].text()) {
+                        enabled = false;
+                    }
+                }
+                // The message is read when the function is declared, so it's only checked here.
+                "deprecated" => {
+                    let args = attribute.args();
+
+                    if args.len() != 1 {
+                        self.error(
+                            DiagnosticInfo::AttributeArgumentMismatch {
+                                expected: 1,
+                                found: args.len(),
+                            },
+                            attribute.syntax().text_range(),
+                        );
+                    }
+                }
+                _ => {
+                    self.error(
+                        DiagnosticInfo::UnknownAttribute(name.to_string()),
+                        name.text_range(),
+                    );
+                }
+            }
+        }
+
+        enabled
     }
 
     fn declare_function(&mut self, function_item: FunctionItem) -> SymbolId {
+        let symbol_id = self.function_symbol(&function_item, None);
+
+        if let Some(name) = function_item.name() {
+            self.scope_mut().define_symbol(name.to_string(), symbol_id);
+        }
+
+        symbol_id
+    }
+
+    /// Allocates the symbol for a function, where `self_type` is the struct it's a method of.
+    fn function_symbol(
+        &mut self,
+        function_item: &FunctionItem,
+        self_type: Option<TypeId>,
+    ) -> SymbolId {
         let mut scope = Scope::default();
 
         let return_type = function_item
@@ -171,10 +598,17 @@ impl<'a> Lowerer<'a> {
         let len = function_item.params().len();
 
         for (i, param) in function_item.params().into_iter().enumerate() {
-            let type_id = param
-                .ty()
-                .map(|ty| self.compile_type(ty))
-                .unwrap_or(self.unknown_type);
+            let type_id = match (param.ty(), self_type) {
+                (Some(ty), _) => self.compile_type(ty),
+                (None, Some(self_type)) if i == 0 && param.spread().is_none() => self_type,
+                (None, _) => {
+                    // Only `self` can be written without a type, otherwise it's a parser error.
+                    if param.name().is_some_and(|name| name.text() == "self") {
+                        self.error(DiagnosticInfo::UnexpectedSelf, param.syntax().text_range());
+                    }
+                    self.unknown_type
+                }
+            };
 
             parameter_types.push(type_id);
 
@@ -204,13 +638,87 @@ impl<'a> Lowerer<'a> {
             ty,
         });
 
-        if let Some(name) = function_item.name() {
-            self.scope_mut().define_symbol(name.to_string(), symbol_id);
+        if let (Some(name), Some(message)) = (
+            function_item.name(),
+            deprecation(&Item::FunctionItem(function_item.clone())),
+        ) {
+            self.deprecated
+                .insert(symbol_id, (name.to_string(), message));
         }
 
         symbol_id
     }
 
+    fn declare_impl(&mut self, impl_item: ImplItem) -> Vec<(FunctionItem, SymbolId)> {
+        let Some(name) = impl_item.name() else {
+            return Vec::new();
+        };
+
+        let Some(type_id) = self
+            .scope_stack
+            .iter()
+            .rev()
+            .find_map(|&scope_id| self.db.scope(scope_id).type_alias(name.text()))
+        else {
+            self.error(
+                DiagnosticInfo::UndefinedType(name.to_string()),
+                name.text_range(),
+            );
+            return Vec::new();
+        };
+
+        if !matches!(self.db.ty(type_id), Type::Struct(..)) {
+            self.error(
+                DiagnosticInfo::ImplNonStruct(self.type_name(type_id)),
+                name.text_range(),
+            );
+            return Vec::new();
+        }
+
+        let struct_type = self.resolve_aliases(type_id);
+        let mut methods = Vec::new();
+
+        for function in impl_item.functions() {
+            if !self.is_item_enabled(&Item::FunctionItem(function.clone())) {
+                continue;
+            }
+
+            let symbol_id = self.function_symbol(&function, Some(type_id));
+
+            if let Some(name) = function.name() {
+                let declared = self.methods.entry(struct_type).or_default();
+
+                if declared.contains_key(name.text()) {
+                    self.error(
+                        DiagnosticInfo::DuplicateMethod(name.to_string()),
+                        name.text_range(),
+                    );
+                } else {
+                    declared.insert(name.to_string(), symbol_id);
+                }
+            }
+
+            methods.push((function, symbol_id));
+        }
+
+        methods
+    }
+
+    /// Looks up a method declared in an `impl` block for the type of a value.
+    fn method(&self, type_id: TypeId, name: &str) -> Option<SymbolId> {
+        self.methods
+            .get(&self.resolve_aliases(type_id))?
+            .get(name)
+            .copied()
+    }
+
+    fn resolve_aliases(&self, mut type_id: TypeId) -> TypeId {
+        while let Type::Alias(alias) = self.db.ty_raw(type_id) {
+            type_id = *alias;
+        }
+        type_id
+    }
+
     fn declare_const(&mut self, const_item: ConstItem) -> SymbolId {
         let type_id = const_item
             .ty()
@@ -278,21 +786,128 @@ impl<'a> Lowerer<'a> {
             unreachable!();
         };
 
+        // Functions nested in a labeled block can't break out of it.
+        let labels = mem::take(&mut self.labels);
+        let break_label = self.break_label.take();
+        let in_test = mem::take(&mut self.in_test);
+
         self.scope_stack.push(scope_id);
+        let mut guards = Vec::new();
+
+        for condition in function
+            .where_clause()
+            .map(|where_clause| where_clause.conditions())
+            .unwrap_or_default()
+        {
+            let value = self.compile_expr(condition.clone(), Some(self.bool_type));
+            self.type_check(value.ty(), self.bool_type, condition.syntax().text_range());
+            guards.push(value.hir());
+        }
+
         let (output, _explicit_return) =
             self.compile_block_expr(body, None, Some(ty.return_type()));
         self.scope_stack.pop().unwrap();
 
+        self.labels = labels;
+        self.break_label = break_label;
+        self.in_test = in_test;
+
         self.type_check(
             output.ty(),
             ty.return_type(),
             function.body().unwrap().syntax().text_range(),
         );
 
+        if let Some(name) = function.name() {
+            let params: Vec<SymbolId> = self
+                .db
+                .scope(scope_id)
+                .local_symbols()
+                .into_iter()
+                .filter(|&symbol_id| self.db.symbol(symbol_id).is_parameter())
+                .collect();
+
+            if always_recurses(self.db, symbol_id, &params, ty.varargs(), output.hir()) {
+                self.warning(
+                    DiagnosticInfo::InfiniteRecursion(name.to_string()),
+                    name.text_range(),
+                );
+            }
+        }
+
+        let mut body = output.hir();
+
+        if self.options.runtime_guards() {
+            for &guard in guards.iter().rev() {
+                let raise = self.db.alloc_hir(Hir::Raise(None));
+                body = self.db.alloc_hir(Hir::If {
+                    condition: guard,
+                    then_block: body,
+                    else_block: raise,
+                });
+            }
+        }
+
+        if let (Some(name), false) = (function.name(), guards.is_empty()) {
+            self.guards.insert(symbol_id, (name.to_string(), guards));
+        }
+
         let Symbol::Function { hir_id, .. } = self.db.symbol_mut(symbol_id) else {
             unreachable!();
         };
-        *hir_id = output.hir();
+        *hir_id = body;
+    }
+
+    /// Reports a call whose constant arguments make a `where` clause condition of the function
+    /// false. Conditions which depend on an argument that isn't constant can't be checked here.
+    fn check_guard(&mut self, symbol_id: SymbolId, mut args: HirId, range: TextRange) {
+        let Some((name, guards)) = self.guards.get(&symbol_id).cloned() else {
+            return;
+        };
+
+        let Symbol::Function { scope_id, ty, .. } = self.db.symbol(symbol_id).clone() else {
+            return;
+        };
+
+        if ty.varargs() {
+            return;
+        }
+
+        let mut values = HashMap::new();
+
+        for param in self.db.scope(scope_id).local_symbols() {
+            if !self.db.symbol(param).is_parameter() {
+                continue;
+            }
+
+            let Hir::Pair(first, rest) = self.db.hir(args).clone() else {
+                break;
+            };
+            args = rest;
+
+            if let Some(value) = self.const_value(first, range) {
+                values.insert(param, value);
+            }
+        }
+
+        let mut is_violated = false;
+
+        for guard in guards {
+            let result = eval_hir_with_params(
+                self.db,
+                self.scope_stack[0],
+                self.options.max_depth(),
+                guard,
+                values.clone(),
+            );
+            is_violated |= self
+                .check_depth(result, range)
+                .is_some_and(|value| value.is_empty());
+        }
+
+        if is_violated {
+            self.error(DiagnosticInfo::GuardFailed(name), range);
+        }
     }
 
     fn compile_const(&mut self, const_item: ConstItem, symbol_id: SymbolId) {
@@ -308,10 +923,97 @@ impl<'a> Lowerer<'a> {
 
         self.type_check(output.ty(), type_id, const_item.syntax().text_range());
 
+        // Constants which can be evaluated now are stored as their value, so that calls such as
+        // `sha256` with constant arguments don't need to be computed at runtime.
+        let value = match self.const_value(output.hir(), const_item.syntax().text_range()) {
+            Some(atom) => self.db.alloc_hir(Hir::Atom(atom)),
+            None => output.hir(),
+        };
+
         let Symbol::ConstBinding { hir_id, .. } = self.db.symbol_mut(symbol_id) else {
             unreachable!();
         };
-        *hir_id = output.hir();
+        *hir_id = value;
+    }
+
+    /// Compiles a test block as a function without parameters, which is its own entrypoint. The
+    /// test passes if running it doesn't raise, so its value is ignored.
+    fn compile_test(&mut self, test: TestItem) {
+        let Some(body) = test.body() else {
+            return;
+        };
+
+        let name = test
+            .name()
+            .map(|name| unquote(name.text()))
+            .unwrap_or_default();
+
+        let scope_id = self.db.alloc_scope(Scope::default());
+
+        let labels = mem::take(&mut self.labels);
+        let break_label = self.break_label.take();
+        let in_test = mem::replace(&mut self.in_test, true);
+
+        self.scope_stack.push(scope_id);
+        let (output, _explicit_return) = self.compile_block_expr(body, None, None);
+        self.scope_stack.pop().unwrap();
+
+        self.labels = labels;
+        self.break_label = break_label;
+        self.in_test = in_test;
+
+        let symbol_id = self.db.alloc_symbol(Symbol::Function {
+            scope_id,
+            hir_id: output.hir(),
+            ty: FunctionType::new(Vec::new(), output.ty(), false),
+        });
+
+        self.tests.push((name, symbol_id));
+    }
+
+    fn compile_static_assert(&mut self, static_assert: StaticAssertItem) {
+        let Some(condition) = static_assert.condition() else {
+            return;
+        };
+
+        let value = self.compile_expr(condition.clone(), Some(self.bool_type));
+        self.type_check(value.ty(), self.bool_type, condition.syntax().text_range());
+
+        if matches!(self.db.hir(value.hir()), Hir::Unknown) {
+            return;
+        }
+
+        let result = eval_hir(
+            self.db,
+            self.scope_stack[0],
+            self.options.max_depth(),
+            value.hir(),
+        );
+
+        let Ok(result) = result else {
+            self.error(DiagnosticInfo::EvalTooDeep, condition.syntax().text_range());
+            return;
+        };
+
+        let Some(result) = result else {
+            self.error(
+                DiagnosticInfo::NonConstantStaticAssert,
+                condition.syntax().text_range(),
+            );
+            return;
+        };
+
+        if result.is_empty() {
+            let message = static_assert
+                .message()
+                .map(|message| unquote(message.text()))
+                .unwrap_or_default();
+
+            self.error(
+                DiagnosticInfo::StaticAssertFailed(message),
+                static_assert.syntax().text_range(),
+            );
+        }
     }
 
     fn compile_type_alias(&mut self, ty: TypeAliasItem, alias_type_id: TypeId) {
@@ -388,8 +1090,18 @@ impl<'a> Lowerer<'a> {
         }
     }
 
-    fn compile_let_stmt(&mut self, let_stmt: LetStmt) -> Option<ScopeId> {
-        let expected_type = let_stmt.ty().map(|ty| self.compile_type(ty));
+    fn compile_let_stmt(&mut self, let_stmt: LetStmt) -> Vec<ScopeId> {
+        // The struct in a pattern is the type of the value, unless another one is given.
+        let struct_pattern = let_stmt.struct_pattern();
+        let pattern_type = struct_pattern.as_ref().map(|pattern| match pattern.path() {
+            Some(path) => self.compile_path_type(path),
+            None => self.unknown_type,
+        });
+
+        let expected_type = let_stmt
+            .ty()
+            .map(|ty| self.compile_type(ty))
+            .or(pattern_type);
 
         let value = let_stmt
             .expr()
@@ -400,13 +1112,24 @@ impl<'a> Lowerer<'a> {
             self.type_check(value.ty(), expected_type, let_stmt.syntax().text_range());
         }
 
-        let Some(name) = let_stmt.name() else {
-            return None;
-        };
+        let type_id = expected_type.unwrap_or(value.ty());
 
-        let symbol_id = self.db.alloc_symbol(Symbol::LetBinding {
-            type_id: expected_type.unwrap_or(value.ty()),
-            hir_id: value.hir(),
+        if let Some(tuple_pattern) = let_stmt.tuple_pattern() {
+            return self.compile_tuple_pattern(tuple_pattern, value.hir(), type_id);
+        }
+
+        if let (Some(struct_pattern), Some(pattern_type)) = (struct_pattern, pattern_type) {
+            self.type_check(type_id, pattern_type, let_stmt.syntax().text_range());
+            return self.compile_struct_pattern(struct_pattern, value.hir(), pattern_type);
+        }
+
+        let Some(name) = let_stmt.name() else {
+            return Vec::new();
+        };
+
+        let symbol_id = self.db.alloc_symbol(Symbol::LetBinding {
+            type_id,
+            hir_id: value.hir(),
         });
 
         let mut let_scope = Scope::default();
@@ -414,7 +1137,135 @@ impl<'a> Lowerer<'a> {
         let scope_id = self.db.alloc_scope(let_scope);
         self.scope_stack.push(scope_id);
 
-        Some(scope_id)
+        vec![scope_id]
+    }
+
+    fn compile_tuple_pattern(
+        &mut self,
+        tuple_pattern: TuplePattern,
+        hir_id: HirId,
+        type_id: TypeId,
+    ) -> Vec<ScopeId> {
+        // The value is bound once up front, so that it isn't recomputed for each item.
+        let tuple_symbol_id = self.db.alloc_symbol(Symbol::LetBinding { type_id, hir_id });
+
+        let mut tuple_scope = Scope::default();
+        tuple_scope.define_hidden_symbol(tuple_symbol_id);
+        let tuple_scope_id = self.db.alloc_scope(tuple_scope);
+        self.scope_stack.push(tuple_scope_id);
+
+        let names = tuple_pattern.names();
+        let mut item_scope = Scope::default();
+        let mut item_hir = self.db.alloc_hir(Hir::Reference(tuple_symbol_id));
+        let mut item_type = type_id;
+        let mut mismatch = false;
+
+        for (i, name) in names.iter().enumerate() {
+            let (hir_id, type_id) = if i + 1 == names.len() {
+                (item_hir, item_type)
+            } else {
+                match self.db.ty(item_type).clone() {
+                    Type::Pair(first, rest) => {
+                        let first_hir = self.db.alloc_hir(Hir::First(item_hir));
+                        item_hir = self.db.alloc_hir(Hir::Rest(item_hir));
+                        item_type = rest;
+                        (first_hir, first)
+                    }
+                    Type::Unknown => (self.unknown_hir, self.unknown_type),
+                    _ => {
+                        mismatch = true;
+                        (self.unknown_hir, self.unknown_type)
+                    }
+                }
+            };
+
+            let symbol_id = self.db.alloc_symbol(Symbol::LetBinding { type_id, hir_id });
+            item_scope.define_symbol(name.to_string(), symbol_id);
+        }
+
+        if mismatch {
+            self.error(
+                DiagnosticInfo::DestructureMismatch {
+                    ty: self.type_name(type_id),
+                    expected: names.len(),
+                },
+                tuple_pattern.syntax().text_range(),
+            );
+        }
+
+        let item_scope_id = self.db.alloc_scope(item_scope);
+        self.scope_stack.push(item_scope_id);
+
+        vec![tuple_scope_id, item_scope_id]
+    }
+
+    /// Binds each field in the pattern to a variable with the same name. Fields which aren't
+    /// named in the pattern are left out.
+    fn compile_struct_pattern(
+        &mut self,
+        struct_pattern: StructPattern,
+        hir_id: HirId,
+        type_id: TypeId,
+    ) -> Vec<ScopeId> {
+        // The value is bound once up front, so that it isn't recomputed for each field.
+        let struct_symbol_id = self.db.alloc_symbol(Symbol::LetBinding { type_id, hir_id });
+
+        let mut struct_scope = Scope::default();
+        struct_scope.define_hidden_symbol(struct_symbol_id);
+        let struct_scope_id = self.db.alloc_scope(struct_scope);
+        self.scope_stack.push(struct_scope_id);
+
+        let struct_type = match self.db.ty(type_id).clone() {
+            Type::Struct(struct_type) => Some(struct_type),
+            Type::Unknown => None,
+            _ => {
+                self.error(
+                    DiagnosticInfo::StructFieldAccess(self.type_name(type_id)),
+                    struct_pattern.syntax().text_range(),
+                );
+                None
+            }
+        };
+
+        let reference = self.db.alloc_hir(Hir::Reference(struct_symbol_id));
+        let mut field_scope = Scope::default();
+        let mut visited_fields = IndexSet::new();
+
+        for name in struct_pattern.fields() {
+            if !visited_fields.insert(name.to_string()) {
+                self.error(
+                    DiagnosticInfo::DuplicateField(name.to_string()),
+                    name.text_range(),
+                );
+                continue;
+            }
+
+            let field = struct_type.as_ref().and_then(|struct_type| {
+                let field = struct_type.fields().get_full(name.text());
+                if field.is_none() {
+                    self.error(
+                        DiagnosticInfo::UndefinedField(name.to_string()),
+                        name.text_range(),
+                    );
+                }
+                field.map(|(index, _name, &field_type)| (index, field_type))
+            });
+
+            let (hir_id, type_id) = match field {
+                Some((index, field_type)) => {
+                    (self.compile_index(reference, index, false), field_type)
+                }
+                None => (self.unknown_hir, self.unknown_type),
+            };
+
+            let symbol_id = self.db.alloc_symbol(Symbol::LetBinding { type_id, hir_id });
+            field_scope.define_symbol(name.to_string(), symbol_id);
+        }
+
+        let field_scope_id = self.db.alloc_scope(field_scope);
+        self.scope_stack.push(field_scope_id);
+
+        vec![struct_scope_id, field_scope_id]
     }
 
     fn compile_block_expr(
@@ -441,10 +1292,9 @@ impl<'a> Lowerer<'a> {
         for stmt in block.stmts() {
             match stmt {
                 Stmt::LetStmt(let_stmt) => {
-                    let Some(scope_id) = self.compile_let_stmt(let_stmt) else {
-                        continue;
-                    };
-                    statements.push(Statement::Let(scope_id));
+                    for scope_id in self.compile_let_stmt(let_stmt) {
+                        statements.push(Statement::Let(scope_id));
+                    }
                 }
                 Stmt::IfStmt(if_stmt) => {
                     let condition = if_stmt
@@ -504,6 +1354,14 @@ impl<'a> Lowerer<'a> {
                         .map(|expr| self.compile_expr(expr, expected_type))
                         .unwrap_or_else(|| self.unknown());
 
+                    // The statements of a labeled block would treat this as a break.
+                    if self.break_label.is_some() {
+                        self.error(
+                            DiagnosticInfo::ExplicitReturnInExpr,
+                            return_stmt.syntax().text_range(),
+                        );
+                    }
+
                     explicit_return = true;
 
                     statements.push(Statement::Return(value));
@@ -523,6 +1381,13 @@ impl<'a> Lowerer<'a> {
                         .map(|condition| self.compile_expr(condition, Some(self.bool_type)))
                         .unwrap_or_else(|| self.unknown());
 
+                    // The payload is only evaluated if the assertion fails.
+                    self.type_guards.push(condition.else_guards());
+                    let payload = assert_stmt
+                        .payload()
+                        .map(|payload| self.compile_expr(payload, Some(self.bytes_type)).hir());
+                    self.type_guards.pop().unwrap();
+
                     self.type_guards.push(condition.then_guards());
 
                     self.type_check(
@@ -532,17 +1397,47 @@ impl<'a> Lowerer<'a> {
                     );
 
                     let not_condition = self.db.alloc_hir(Hir::Not(condition.hir()));
-                    let raise = self.db.alloc_hir(Hir::Raise(None));
+                    let raise = self.db.alloc_hir(Hir::Raise(payload));
 
                     statements.push(Statement::If(not_condition, raise))
                 }
+                Stmt::BreakStmt(break_stmt) => {
+                    if let Some(label) = break_stmt.label() {
+                        self.check_break_label(label);
+                    }
+
+                    let value = break_stmt
+                        .expr()
+                        .map(|expr| self.compile_expr(expr, expected_type))
+                        .unwrap_or_else(|| Value::typed(self.nil_hir, self.nil_type));
+
+                    // Breaking leaves the rest of the labeled block, the same way as returning.
+                    explicit_return = true;
+
+                    statements.push(Statement::Return(value));
+                }
             }
         }
 
-        let mut body = block
-            .expr()
-            .map(|expr| self.compile_expr(expr, expected_type))
-            .unwrap_or(self.unknown());
+        let ends_with_return = matches!(statements.last(), Some(Statement::Return(..)));
+
+        let mut body = match block.expr() {
+            Some(expr) => self.compile_expr(expr, expected_type),
+            // A test block only needs to run its statements, so it has no value by default.
+            None if scope_id.is_none() && self.in_test => Value::typed(self.nil_hir, self.nil_type),
+            None => {
+                if !ends_with_return {
+                    // Function bodies are the only blocks compiled without a new scope.
+                    let info = if scope_id.is_none() {
+                        DiagnosticInfo::EmptyFunctionBody
+                    } else {
+                        DiagnosticInfo::MissingBlockExpr
+                    };
+                    self.error(info, block.syntax().text_range());
+                }
+                self.unknown()
+            }
+        };
 
         for statement in statements.into_iter().rev() {
             match statement {
@@ -581,11 +1476,59 @@ impl<'a> Lowerer<'a> {
         (body, explicit_return)
     }
 
+    fn check_break_label(&mut self, label: SyntaxToken) {
+        let name = label.text();
+
+        if self.break_label.as_deref() == Some(name) {
+            return;
+        }
+
+        let info = if self.labels.iter().any(|label| label == name) {
+            DiagnosticInfo::BreakFromExpr(name.to_string())
+        } else {
+            DiagnosticInfo::UndefinedLabel(name.to_string())
+        };
+
+        self.error(info, label.text_range());
+    }
+
+    fn compile_labeled_block(
+        &mut self,
+        labeled_block: LabeledBlock,
+        expected_type: Option<TypeId>,
+    ) -> Value {
+        let Some(block) = labeled_block.block() else {
+            return self.unknown();
+        };
+
+        let label = labeled_block.label().map(|label| label.to_string());
+
+        if let Some(label) = label.clone() {
+            self.labels.push(label);
+        }
+
+        // Each break becomes an early return from the block's statements.
+        self.break_label = label.clone();
+
+        let scope_id = self.db.alloc_scope(Scope::default());
+        let (value, _explicit_break) =
+            self.compile_block_expr(block, Some(scope_id), expected_type);
+
+        if label.is_some() {
+            self.labels.pop().unwrap();
+        }
+
+        value
+    }
+
     fn compile_expr(&mut self, expr: Expr, expected_type: Option<TypeId>) -> Value {
-        match expr {
+        // Breaks can only leave a labeled block from its statements, not from nested expressions.
+        let break_label = self.break_label.take();
+
+        let value = match expr {
             Expr::Path(path) => self.compile_path_expr(path),
             Expr::InitializerExpr(initializer) => self.compile_initializer_expr(initializer),
-            Expr::LiteralExpr(literal) => self.compile_literal_expr(literal),
+            Expr::LiteralExpr(literal) => self.compile_literal_expr(literal, expected_type),
             Expr::ListExpr(list) => self.compile_list_expr(list, expected_type),
             Expr::PairExpr(pair) => self.compile_pair_expr(pair, expected_type),
             Expr::Block(block) => {
@@ -607,10 +1550,45 @@ impl<'a> Lowerer<'a> {
             Expr::CastExpr(cast) => self.compile_cast_expr(cast, expected_type),
             Expr::GuardExpr(guard) => self.compile_guard_expr(guard, expected_type),
             Expr::IfExpr(if_expr) => self.compile_if_expr(if_expr, expected_type),
+            Expr::MatchExpr(match_expr) => self.compile_match_expr(match_expr, expected_type),
             Expr::FunctionCall(call) => self.compile_function_call(call),
             Expr::FieldAccess(field_access) => self.compile_field_access(field_access),
             Expr::IndexAccess(index_access) => self.compile_index_access(index_access),
+            Expr::LabeledBlock(labeled_block) => {
+                self.compile_labeled_block(labeled_block, expected_type)
+            }
+        };
+
+        self.break_label = break_label;
+        self.wrap_union(value, expected_type)
+    }
+
+    /// Tags a value with the index of the first member of the expected union type it can be
+    /// assigned to, so that it can be told apart from the other members at runtime.
+    fn wrap_union(&mut self, value: Value, expected_type: Option<TypeId>) -> Value {
+        let Some(expected_type) = expected_type else {
+            return value;
+        };
+
+        let Type::Union(members) = self.db.ty(expected_type).clone() else {
+            return value;
+        };
+
+        if matches!(self.db.ty(value.ty()), Type::Unknown | Type::Union(..)) {
+            return value;
         }
+
+        let Some(index) = members.iter().position(|&member| {
+            self.is_assignable_to(value.ty(), member, false, &mut HashSet::new())
+        }) else {
+            return value;
+        };
+
+        let tag = self
+            .db
+            .alloc_hir(Hir::Atom(bigint_to_bytes(BigInt::from(index))));
+        let hir_id = self.db.alloc_hir(Hir::Pair(tag, value.hir()));
+        Value::typed(hir_id, expected_type)
     }
 
     fn compile_initializer_expr(&mut self, initializer: InitializerExpr) -> Value {
@@ -731,6 +1709,10 @@ impl<'a> Lowerer<'a> {
             return self.unknown();
         };
 
+        self.compile_field(value, field_name)
+    }
+
+    fn compile_field(&mut self, value: Value, field_name: SyntaxToken) -> Value {
         match self.db.ty(value.ty()).clone() {
             Type::Struct(struct_type) => {
                 if let Some((index, _name, &field_type)) =
@@ -745,6 +1727,9 @@ impl<'a> Lowerer<'a> {
                     self.unknown()
                 }
             }
+            Type::Pair(..) if field_name.kind() == SyntaxKind::Int => {
+                self.compile_tuple_index(value, field_name)
+            }
             Type::Pair(left, right) => match field_name.text() {
                 "first" => Value::typed(self.db.alloc_hir(Hir::First(value.hir())), left),
                 "rest" => Value::typed(self.db.alloc_hir(Hir::Rest(value.hir())), right),
@@ -766,6 +1751,60 @@ impl<'a> Lowerer<'a> {
         }
     }
 
+    /// Tuples are nested pairs which aren't nil terminated, so item `i` is the first of the pair
+    /// reached after `i` rests, except for the last item, which is the rest itself. A tuple which
+    /// is written out is indexed directly, as long as the items which are skipped can't fail.
+    fn compile_tuple_index(&mut self, value: Value, index_token: SyntaxToken) -> Value {
+        let text = index_token.text();
+        let index: usize = text.parse().unwrap_or(usize::MAX);
+
+        let mut hir_id = value.hir();
+        let mut type_id = value.ty();
+        let mut len = 1;
+
+        while let Type::Pair(_, rest) = self.db.ty(type_id) {
+            type_id = *rest;
+            len += 1;
+        }
+
+        if index >= len {
+            self.error(
+                DiagnosticInfo::TupleIndexOutOfRange {
+                    index: text.to_string(),
+                    len,
+                },
+                index_token.text_range(),
+            );
+            return self.unknown();
+        }
+
+        type_id = value.ty();
+
+        for i in 0..=index {
+            let Type::Pair(first, rest) = self.db.ty(type_id).clone() else {
+                break;
+            };
+
+            let is_item = i == index;
+
+            let pair = match self.db.hir(hir_id) {
+                Hir::Pair(first, rest) => Some((*first, *rest)),
+                _ => None,
+            };
+
+            hir_id = match pair {
+                Some((first, rest)) if is_item && !may_fail(self.db, rest) => first,
+                Some((first, rest)) if !is_item && !may_fail(self.db, first) => rest,
+                _ if is_item => self.db.alloc_hir(Hir::First(hir_id)),
+                _ => self.db.alloc_hir(Hir::Rest(hir_id)),
+            };
+
+            type_id = if is_item { first } else { rest };
+        }
+
+        Value::typed(hir_id, type_id)
+    }
+
     fn compile_index_access(&mut self, index_access: IndexAccess) -> Value {
         let Some(value) = index_access
             .expr()
@@ -775,21 +1814,114 @@ impl<'a> Lowerer<'a> {
         };
 
         let Some(index_token) = index_access.index() else {
-            return self.unknown();
+            return match index_access.index_expr() {
+                Some(index) => self.compile_select_index(
+                    value,
+                    index,
+                    index_access.expr().unwrap().syntax().text_range(),
+                ),
+                None => self.unknown(),
+            };
         };
         let index = self.compile_int_raw(index_token.clone());
 
-        let Type::List(item_type) = self.db.ty(value.ty()).clone() else {
-            self.error(
-                DiagnosticInfo::IndexAccess(self.type_name(value.ty())),
-                index_access.expr().unwrap().syntax().text_range(),
-            );
-            return self.unknown();
+        let item_type = match self.db.ty(value.ty()).clone() {
+            Type::List(item_type) => item_type,
+            Type::Array(item_type, len) => {
+                if index >= len {
+                    self.error(
+                        DiagnosticInfo::IndexOutOfBounds(
+                            u32::try_from(index).unwrap_or(u32::MAX),
+                            u32::try_from(len).unwrap_or(u32::MAX),
+                        ),
+                        index_token.text_range(),
+                    );
+                    return self.unknown();
+                }
+                item_type
+            }
+            _ => {
+                self.error(
+                    DiagnosticInfo::IndexAccess(self.type_name(value.ty())),
+                    index_access.expr().unwrap().syntax().text_range(),
+                );
+                return self.unknown();
+            }
         };
 
         Value::typed(self.compile_index(value.hir(), index, false), item_type)
     }
 
+    /// Indexes a list with a value which isn't known until runtime. Reading an item at a dynamic
+    /// index would need a loop, so the list must be constant, and the index is compared with the
+    /// position of each item in turn instead. An index which is out of range raises.
+    fn compile_select_index(&mut self, list: Value, index: Expr, range: TextRange) -> Value {
+        let index_range = index.syntax().text_range();
+        let index = self.compile_expr(index, Some(self.int_type));
+        self.type_check(index.ty(), self.int_type, index_range);
+
+        let item_type = match self.db.ty(list.ty()) {
+            Type::List(item_type) | Type::Array(item_type, _) => *item_type,
+            _ => {
+                self.error(
+                    DiagnosticInfo::IndexAccess(self.type_name(list.ty())),
+                    range,
+                );
+                return self.unknown();
+            }
+        };
+
+        let Some(items) = self.constant_items(list.hir()) else {
+            self.error(DiagnosticInfo::NonConstantIndexedList, range);
+            return self.unknown();
+        };
+
+        let (scopes, [index]) = self.bind_operands([index.hir()]);
+        let mut hir_id = self.db.alloc_hir(Hir::Raise(None));
+
+        for (position, item) in items.into_iter().enumerate().rev() {
+            let position = self
+                .db
+                .alloc_hir(Hir::Atom(bigint_to_bytes(BigInt::from(position))));
+            let condition = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Equals,
+                lhs: index,
+                rhs: position,
+            });
+            hir_id = self.db.alloc_hir(Hir::If {
+                condition,
+                then_block: item,
+                else_block: hir_id,
+            });
+        }
+
+        Value::typed(self.wrap_scopes(scopes, hir_id), item_type)
+    }
+
+    /// The items of a list whose length is known at compile time, following references to
+    /// constants, such as a list literal without a spread.
+    fn constant_items(&self, mut hir_id: HirId) -> Option<Vec<HirId>> {
+        let mut items = Vec::new();
+
+        loop {
+            match self.db.hir(hir_id) {
+                Hir::Reference(symbol_id) => {
+                    let Symbol::ConstBinding { hir_id: value, .. } = self.db.symbol(*symbol_id)
+                    else {
+                        return None;
+                    };
+                    hir_id = *value;
+                }
+                Hir::Pair(first, rest) => {
+                    items.push(*first);
+                    hir_id = *rest;
+                }
+                Hir::Atom(atom) if atom.is_empty() => return Some(items),
+                _ => return None,
+            }
+        }
+    }
+
     fn compile_index(&mut self, value: HirId, index: usize, rest: bool) -> HirId {
         let mut result = value;
         for _ in 0..index {
@@ -806,9 +1938,11 @@ impl<'a> Lowerer<'a> {
             return self.unknown();
         };
 
-        let expr = self.compile_expr(expr, None);
+        // The operand of `!` must be a `Bool`, rather than any atom which CLVM's `not` accepts.
+        let range = expr.syntax().text_range();
+        let expr = self.compile_expr(expr, Some(self.bool_type));
 
-        self.type_check(expr.ty(), self.bool_type, prefix_expr.syntax().text_range());
+        self.type_check(expr.ty(), self.bool_type, range);
 
         Value::typed(
             match prefix_expr.op() {
@@ -833,16 +1967,35 @@ impl<'a> Lowerer<'a> {
             .map(|rhs| rhs.ty())
             .unwrap_or(self.unknown_type);
 
+        if matches!(binary.op(), Some(BinaryOp::Equals | BinaryOp::NotEquals))
+            && (self.is_structured(lhs_ty) || self.is_structured(rhs_ty))
+        {
+            return self.compile_structural_equals(binary, lhs, rhs);
+        }
+
         let mut op = binary.op().map(HirBinaryOp::from);
 
-        let ty = if binary.op() == Some(BinaryOp::Add)
-            && self.is_assignable_to(lhs_ty, self.bytes_type, false, &mut HashSet::new())
-        {
-            self.type_check(rhs_ty, self.bytes_type, binary.syntax().text_range());
+        let lhs_is_bytes =
+            self.is_assignable_to(lhs_ty, self.bytes_type, false, &mut HashSet::new());
+
+        let ty = if binary.op() == Some(BinaryOp::Add) && lhs_is_bytes {
+            self.coercion_check(rhs_ty, self.bytes_type, binary.syntax().text_range());
 
             op = Some(HirBinaryOp::Concat);
 
             Some(self.bytes_type)
+        } else if matches!(binary.op(), Some(BinaryOp::Equals | BinaryOp::NotEquals)) {
+            // Atoms are compared byte for byte, so bytes can be compared with each other, and
+            // with integers, with a warning unless the conversion is made explicit.
+            if lhs_is_bytes {
+                self.coercion_check(rhs_ty, self.bytes_type, binary.syntax().text_range());
+            } else {
+                self.type_check(lhs_ty, self.int_type, binary.syntax().text_range());
+
+                self.coercion_check(rhs_ty, self.int_type, binary.syntax().text_range());
+            }
+
+            None
         } else {
             self.type_check(lhs_ty, self.int_type, binary.syntax().text_range());
 
@@ -871,18 +2024,55 @@ impl<'a> Lowerer<'a> {
         });
 
         match (lhs, rhs, op) {
-            (Some(lhs), Some(rhs), Some(op)) => Value::typed(
-                self.db.alloc_hir(Hir::BinaryOp {
+            (Some(lhs), Some(rhs), Some(op)) => {
+                let hir_id = self.db.alloc_hir(Hir::BinaryOp {
                     op,
                     lhs: lhs.hir(),
                     rhs: rhs.hir(),
-                }),
-                ty,
-            ),
+                });
+                self.operations.push(hir_id);
+                Value::typed(hir_id, ty)
+            }
             _ => Value::typed(self.unknown_hir, ty),
         }
     }
 
+    fn is_structured(&self, type_id: TypeId) -> bool {
+        matches!(
+            self.db.ty(type_id),
+            Type::List(..) | Type::Array(..) | Type::Pair(..)
+        )
+    }
+
+    /// CLVM's `=` operator only compares atoms, so lists and pairs are compared item by item
+    /// with a recursive function instead.
+    fn compile_structural_equals(
+        &mut self,
+        binary: BinaryExpr,
+        lhs: Option<Value>,
+        rhs: Option<Value>,
+    ) -> Value {
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return Value::typed(self.unknown_hir, self.bool_type);
+        };
+
+        if !self.is_assignable_to(lhs.ty(), rhs.ty(), false, &mut HashSet::new()) {
+            self.type_check(rhs.ty(), lhs.ty(), binary.syntax().text_range());
+        }
+
+        let args = self.db.alloc_hir(Hir::Pair(rhs.hir(), self.nil_hir));
+        let args = self.db.alloc_hir(Hir::Pair(lhs.hir(), args));
+        let callee = self.db.alloc_hir(Hir::Reference(self.equals_function));
+        let mut hir_id = self.db.alloc_hir(Hir::FunctionCall { callee, args });
+        self.operations.push(hir_id);
+
+        if binary.op() == Some(BinaryOp::NotEquals) {
+            hir_id = self.db.alloc_hir(Hir::Not(hir_id));
+        }
+
+        Value::typed(hir_id, self.bool_type)
+    }
+
     fn compile_group_expr(
         &mut self,
         group_expr: GroupExpr,
@@ -911,37 +2101,160 @@ impl<'a> Lowerer<'a> {
             .map(|ty| self.compile_type(ty))
             .unwrap_or(self.unknown_type);
 
-        self.cast_check(expr.ty(), ty, cast.expr().unwrap().syntax().text_range());
+        let range = cast.expr().unwrap().syntax().text_range();
+
+        match (self.db.ty(expr.ty()).clone(), self.db.ty(ty).clone()) {
+            (Type::Enum(..) | Type::EnumVariant(..), Type::Int) => {
+                let hir_id = self.db.alloc_hir(Hir::First(expr.hir()));
+                return Value::typed(hir_id, ty);
+            }
+            (Type::Int, Type::Enum(enum_type)) => {
+                return self.compile_enum_from_int(expr, ty, enum_type, range);
+            }
+            (Type::Bytes, Type::Bytes32) => return self.compile_sized_cast(expr, ty, 32, range),
+            (Type::Bytes, Type::PublicKey) => return self.compile_sized_cast(expr, ty, 48, range),
+            (Type::Bytes, Type::Signature) => return self.compile_sized_cast(expr, ty, 96, range),
+            _ => {}
+        }
+
+        self.cast_check(expr.ty(), ty, range);
 
         Value::typed(expr.hir(), ty)
     }
 
-    fn compile_guard_expr(&mut self, guard: GuardExpr, expected_type: Option<TypeId>) -> Value {
-        let Some(expr) = guard
-            .expr()
-            .map(|expr| self.compile_expr(expr, expected_type))
-        else {
-            return self.unknown();
-        };
+    /// Casts an integer to a variant of the enum without fields, by its discriminant. The value is
+    /// checked at compile time if it's constant, and otherwise raises at runtime if it's invalid.
+    fn compile_enum_from_int(
+        &mut self,
+        value: Value,
+        type_id: TypeId,
+        enum_type: EnumType,
+        range: TextRange,
+    ) -> Value {
+        let discriminants: Vec<HirId> = enum_type
+            .variants()
+            .values()
+            .filter_map(|&variant_type| match self.db.ty(variant_type) {
+                Type::EnumVariant(enum_variant) if enum_variant.fields().is_empty() => {
+                    Some(enum_variant.discriminant())
+                }
+                _ => None,
+            })
+            .collect();
 
-        let ty = guard
-            .ty()
-            .map(|ty| self.compile_type(ty))
-            .unwrap_or(self.unknown_type);
+        let builtins = self.scope_stack[0];
+        let max_depth = self.options.max_depth();
 
-        let Some((guard, hir_id)) =
-            self.guard_into(expr.ty(), ty, expr.hir(), guard.syntax().text_range())
-        else {
-            return Value::typed(self.unknown_hir, ty);
-        };
+        if let Some(atom) = self.const_value(value.hir(), range) {
+            if !discriminants.iter().any(|&discriminant| {
+                eval_hir(self.db, builtins, max_depth, discriminant) == Ok(Some(atom.clone()))
+            }) {
+                self.error(
+                    DiagnosticInfo::InvalidDiscriminant {
+                        enum_name: self.type_name(type_id),
+                        value: BigInt::from_signed_bytes_be(&atom).to_string(),
+                    },
+                    range,
+                );
+            }
 
-        let mut value = Value::typed(hir_id, self.bool_type);
+            let hir_id = self.db.alloc_hir(Hir::Pair(value.hir(), self.nil_hir));
+            return Value::typed(hir_id, type_id);
+        }
 
-        if let Hir::Reference(symbol_id) = self.db.hir(expr.hir()) {
-            value.guard(*symbol_id, guard);
+        let (scopes, [value]) = self.bind_operands([value.hir()]);
+
+        let pair = self.db.alloc_hir(Hir::Pair(value, self.nil_hir));
+        let mut hir_id = self.db.alloc_hir(Hir::Raise(None));
+
+        for discriminant in discriminants.into_iter().rev() {
+            let condition = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Equals,
+                lhs: value,
+                rhs: discriminant,
+            });
+
+            hir_id = self.db.alloc_hir(Hir::If {
+                condition,
+                then_block: pair,
+                else_block: hir_id,
+            });
         }
 
-        value
+        Value::typed(self.wrap_scopes(scopes, hir_id), type_id)
+    }
+
+    /// Casts bytes to a fixed width type, such as `Bytes32`. The length is checked at compile time
+    /// if the value is constant, and otherwise raises at runtime if it's wrong, rather than
+    /// silently using a value of the wrong size.
+    fn compile_sized_cast(
+        &mut self,
+        value: Value,
+        type_id: TypeId,
+        length: usize,
+        range: TextRange,
+    ) -> Value {
+        if let Some(atom) = self.const_value(value.hir(), range) {
+            if atom.len() != length {
+                self.error(
+                    DiagnosticInfo::InvalidCastLength {
+                        ty: self.type_name(type_id),
+                        expected: length,
+                        found: atom.len(),
+                    },
+                    range,
+                );
+            }
+            return Value::typed(value.hir(), type_id);
+        }
+
+        let (scopes, [value]) = self.bind_operands([value.hir()]);
+
+        let strlen = self.db.alloc_hir(Hir::Strlen(value));
+        let length = self
+            .db
+            .alloc_hir(Hir::Atom(BigInt::from(length).to_signed_bytes_be()));
+        let condition = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::Equals,
+            lhs: strlen,
+            rhs: length,
+        });
+        let raise = self.db.alloc_hir(Hir::Raise(None));
+        let hir_id = self.db.alloc_hir(Hir::If {
+            condition,
+            then_block: value,
+            else_block: raise,
+        });
+
+        Value::typed(self.wrap_scopes(scopes, hir_id), type_id)
+    }
+
+    fn compile_guard_expr(&mut self, guard: GuardExpr, expected_type: Option<TypeId>) -> Value {
+        let Some(expr) = guard
+            .expr()
+            .map(|expr| self.compile_expr(expr, expected_type))
+        else {
+            return self.unknown();
+        };
+
+        let ty = guard
+            .ty()
+            .map(|ty| self.compile_type(ty))
+            .unwrap_or(self.unknown_type);
+
+        let Some((guard, hir_id)) =
+            self.guard_into(expr.ty(), ty, expr.hir(), guard.syntax().text_range())
+        else {
+            return Value::typed(self.unknown_hir, ty);
+        };
+
+        let mut value = Value::typed(hir_id, self.bool_type);
+
+        if let Hir::Reference(symbol_id) = self.db.hir(expr.hir()) {
+            value.guard(*symbol_id, guard);
+        }
+
+        value
     }
 
     fn guard_into(
@@ -996,9 +2309,47 @@ impl<'a> Lowerer<'a> {
                 let hir_id = self.db.alloc_hir(Hir::Not(is_cons));
                 Some((Guard::new(to, pair_type), hir_id))
             }
-            (Type::Bytes, Type::Bytes32) => {
+            (Type::Union(members), _) => {
+                let Some(index) = members
+                    .iter()
+                    .position(|&member| self.types_equal(member, to))
+                else {
+                    self.error(
+                        DiagnosticInfo::UnsupportedTypeGuard {
+                            from: self.type_name(from),
+                            to: self.type_name(to),
+                        },
+                        text_range,
+                    );
+                    return None;
+                };
+
+                // Once all but one member has been ruled out, the value is that member.
+                let else_type = match members.as_slice() {
+                    [_, second] if index == 0 => *second,
+                    [first, _] => *first,
+                    _ => from,
+                };
+
+                let tag = self.db.alloc_hir(Hir::First(hir_id));
+                let index = self
+                    .db
+                    .alloc_hir(Hir::Atom(bigint_to_bytes(BigInt::from(index))));
+                let hir_id = self.db.alloc_hir(Hir::BinaryOp {
+                    op: HirBinaryOp::Equals,
+                    lhs: tag,
+                    rhs: index,
+                });
+                Some((Guard::new(to, else_type), hir_id))
+            }
+            (Type::Bytes, to_type @ (Type::Bytes32 | Type::PublicKey | Type::Signature)) => {
+                let length = match to_type {
+                    Type::Bytes32 => 32,
+                    Type::PublicKey => 48,
+                    _ => 96,
+                };
                 let strlen = self.db.alloc_hir(Hir::Strlen(hir_id));
-                let length = self.db.alloc_hir(Hir::Atom(vec![32]));
+                let length = self.db.alloc_hir(Hir::Atom(vec![length]));
                 let hir_id = self.db.alloc_hir(Hir::BinaryOp {
                     op: HirBinaryOp::Equals,
                     lhs: strlen,
@@ -1019,13 +2370,35 @@ impl<'a> Lowerer<'a> {
         }
     }
 
-    fn compile_literal_expr(&mut self, literal: LiteralExpr) -> Value {
+    fn compile_literal_expr(
+        &mut self,
+        literal: LiteralExpr,
+        expected_type: Option<TypeId>,
+    ) -> Value {
         let Some(value) = literal.value() else {
             return self.unknown();
         };
 
         match value.kind() {
             SyntaxKind::Int => self.compile_int(value),
+            // Where an `Int` is expected, hex digits are an unsigned integer, as if they had the `i` suffix.
+            SyntaxKind::Hex
+                if expected_type.is_some_and(|ty| self.resolve_aliases(ty) == self.int_type) =>
+            {
+                self.compile_int(value)
+            }
+            SyntaxKind::Hex => {
+                let bytes = self.compile_hex(value);
+
+                Value::typed(
+                    self.db.alloc_hir(Hir::Atom(bytes.clone())),
+                    if bytes.len() == 32 {
+                        self.bytes32_type
+                    } else {
+                        self.bytes_type
+                    },
+                )
+            }
             SyntaxKind::String => self.compile_string(value),
             SyntaxKind::True => Value::typed(self.db.alloc_hir(Hir::Atom(vec![1])), self.bool_type),
             SyntaxKind::False => {
@@ -1052,6 +2425,17 @@ impl<'a> Lowerer<'a> {
             _ => None,
         });
 
+        // A fixed size list is built like any other, but must have exactly as many items.
+        let mut array_len = None;
+
+        if let Some(Type::Array(array_item_type, len)) =
+            expected_expr_type.map(|ty| self.db.ty(ty).clone())
+        {
+            array_len = Some(len);
+            item_type = Some(array_item_type);
+            list_type = Some(self.db.alloc_type(Type::List(array_item_type)));
+        }
+
         let len = list_expr.items().len();
 
         for (i, item) in list_expr.items().into_iter().enumerate() {
@@ -1104,6 +2488,19 @@ impl<'a> Lowerer<'a> {
             }
         }
 
+        if let (Some(expected), true) = (array_len, nil_terminated) {
+            if len != expected {
+                self.error(
+                    DiagnosticInfo::ArrayLengthMismatch {
+                        expected,
+                        found: len,
+                    },
+                    list_expr.syntax().text_range(),
+                );
+            }
+            return Value::typed(hir_id, expected_expr_type.unwrap());
+        }
+
         Value::typed(
             hir_id,
             list_type.unwrap_or_else(|| self.db.alloc_type(Type::List(self.unknown_type))),
@@ -1111,44 +2508,50 @@ impl<'a> Lowerer<'a> {
     }
 
     fn compile_pair_expr(&mut self, pair_expr: PairExpr, expected_type: Option<TypeId>) -> Value {
-        let expected_first = expected_type.and_then(|ty| match self.db.ty(ty) {
-            Type::Pair(first, _) => Some(*first),
-            _ => None,
-        });
+        let items = pair_expr.items();
+        let mut expected_type = expected_type;
+        let mut values = Vec::new();
 
-        let expected_rest = expected_type.and_then(|ty| match self.db.ty(ty) {
-            Type::Pair(_, rest) => Some(*rest),
-            _ => None,
-        });
+        // Each item is the first of a pair, except for the last, which is the rest of the innermost.
+        for (i, item) in items.iter().enumerate() {
+            let is_rest = i > 0 && i + 1 == items.len();
 
-        let first = if let Some(first) = pair_expr.first() {
-            let value = self.compile_expr(first.clone(), expected_first);
-            self.type_check(
-                value.ty(),
-                expected_first.unwrap_or(self.unknown_type),
-                first.syntax().text_range(),
-            );
-            value
-        } else {
-            self.unknown()
-        };
+            let expected_item = if is_rest {
+                expected_type
+            } else {
+                expected_type.and_then(|ty| match self.db.ty(ty) {
+                    Type::Pair(first, _) => Some(*first),
+                    _ => None,
+                })
+            };
 
-        let rest = if let Some(rest) = pair_expr.rest() {
-            let value = self.compile_expr(rest.clone(), expected_rest);
+            let value = self.compile_expr(item.clone(), expected_item);
             self.type_check(
                 value.ty(),
-                expected_rest.unwrap_or(self.unknown_type),
-                rest.syntax().text_range(),
+                expected_item.unwrap_or(self.unknown_type),
+                item.syntax().text_range(),
             );
-            value
-        } else {
-            self.unknown()
-        };
+            values.push(value);
+
+            expected_type = expected_type.and_then(|ty| match self.db.ty(ty) {
+                Type::Pair(_, rest) => Some(*rest),
+                _ => None,
+            });
+        }
+
+        while values.len() < 2 {
+            values.push(self.unknown());
+        }
 
-        let hir_id = self.db.alloc_hir(Hir::Pair(first.hir(), rest.hir()));
-        let type_id = self.db.alloc_type(Type::Pair(first.ty(), rest.ty()));
+        let mut value = values.pop().unwrap();
 
-        Value::typed(hir_id, type_id)
+        for first in values.into_iter().rev() {
+            let hir_id = self.db.alloc_hir(Hir::Pair(first.hir(), value.hir()));
+            let type_id = self.db.alloc_type(Type::Pair(first.ty(), value.ty()));
+            value = Value::typed(hir_id, type_id);
+        }
+
+        value
     }
 
     fn compile_lambda_expr(
@@ -1273,20 +2676,33 @@ impl<'a> Lowerer<'a> {
             );
         }
 
-        if let (Some(then_block), Some(else_block)) = (&then_block, &else_block) {
-            self.type_check(
-                else_block.ty(),
-                then_block.ty(),
-                if_expr.else_block().unwrap().syntax().text_range(),
-            );
-        }
-
-        let ty = then_block
-            .as_ref()
-            .or(else_block.as_ref())
+        // A branch which always raises has an unknown type, so the other branch decides the type.
+        let mut ty = [&then_block, &else_block]
+            .into_iter()
+            .flatten()
             .map(|block| block.ty())
+            .find(|&ty| !matches!(self.db.ty(ty), Type::Unknown))
             .unwrap_or(self.unknown_type);
 
+        if let (Some(then_block), Some(else_block)) = (&then_block, &else_block) {
+            let (then_type, else_type) = (then_block.ty(), else_block.ty());
+
+            if !self.is_assignable_to(else_type, then_type, false, &mut HashSet::new()) {
+                if self.is_assignable_to(then_type, else_type, false, &mut HashSet::new()) {
+                    // The `then` branch is narrower, such as `nil` with a list in the `else` branch.
+                    ty = else_type;
+                } else {
+                    self.error(
+                        DiagnosticInfo::BranchTypeMismatch {
+                            then_type: self.type_name(then_type),
+                            else_type: self.type_name(else_type),
+                        },
+                        if_expr.else_block().unwrap().syntax().text_range(),
+                    );
+                }
+            }
+        }
+
         let value = condition.and_then(|condition| {
             then_block.and_then(|then_block| {
                 else_block.map(|else_block| {
@@ -1302,148 +2718,2024 @@ impl<'a> Lowerer<'a> {
         Value::typed(value.unwrap_or(self.unknown_hir), ty)
     }
 
-    fn compile_int_raw<T, E>(&mut self, int: SyntaxToken) -> T
-    where
-        T: FromStr<Err = E>,
-        E: fmt::Debug,
-    {
-        int.text()
-            .replace('_', "")
-            .parse()
-            .expect("failed to parse into BigInt")
-    }
+    fn compile_match_expr(
+        &mut self,
+        match_expr: MatchExpr,
+        expected_type: Option<TypeId>,
+    ) -> Value {
+        let Some(value) = match_expr.expr().map(|expr| self.compile_expr(expr, None)) else {
+            return self.unknown();
+        };
 
-    fn compile_int(&mut self, int: SyntaxToken) -> Value {
-        let num = self.compile_int_raw(int);
+        let enum_type_id = match self.db.ty(value.ty()).clone() {
+            Type::Enum(..) => value.ty(),
+            Type::EnumVariant(enum_variant) => enum_variant.enum_type(),
+            Type::Unknown => return self.unknown(),
+            _ => {
+                self.error(
+                    DiagnosticInfo::NonEnumMatch(self.type_name(value.ty())),
+                    match_expr.expr().unwrap().syntax().text_range(),
+                );
+                return self.unknown();
+            }
+        };
 
-        Value::typed(
-            self.db.alloc_hir(Hir::Atom(bigint_to_bytes(num))),
-            self.int_type,
-        )
-    }
+        let Type::Enum(enum_type) = self.db.ty(enum_type_id).clone() else {
+            unreachable!();
+        };
 
-    fn compile_string(&mut self, string: SyntaxToken) -> Value {
-        let text = string.text();
-        let quote = text.chars().next().unwrap();
-        let after_prefix = &text[1..];
-        let before_suffix = after_prefix.strip_suffix(quote).unwrap_or(after_prefix);
+        // The value is bound once up front, so that it isn't recomputed for each arm.
+        let value_symbol_id = self.db.alloc_symbol(Symbol::LetBinding {
+            type_id: value.ty(),
+            hir_id: value.hir(),
+        });
 
-        let bytes = before_suffix.as_bytes();
+        let mut match_scope = Scope::default();
+        match_scope.define_hidden_symbol(value_symbol_id);
+        let match_scope_id = self.db.alloc_scope(match_scope);
+        self.scope_stack.push(match_scope_id);
 
-        Value::typed(
-            self.db.alloc_hir(Hir::Atom(bytes.to_vec())),
-            if bytes.len() == 32 {
-                self.bytes32_type
+        let value_hir = self.db.alloc_hir(Hir::Reference(value_symbol_id));
+
+        let mut arms = Vec::new();
+        let mut visited_variants = IndexSet::new();
+        let mut expected_type = expected_type;
+        let mut result_type = None;
+
+        for arm in match_expr.arms() {
+            let Some(pattern) = arm.pattern() else {
+                continue;
+            };
+
+            let Some(name) = pattern.name() else {
+                continue;
+            };
+
+            let Some(&variant_type) = enum_type.variants().get(name.text()) else {
+                self.error(
+                    DiagnosticInfo::UnknownEnumVariant(name.to_string()),
+                    name.text_range(),
+                );
+                continue;
+            };
+
+            if !visited_variants.insert(name.to_string()) {
+                self.error(
+                    DiagnosticInfo::DuplicateMatchArm(name.to_string()),
+                    name.text_range(),
+                );
+                continue;
+            }
+
+            let Type::EnumVariant(enum_variant) = self.db.ty(variant_type).clone() else {
+                continue;
+            };
+
+            let mut arm_scope = Scope::default();
+
+            if let Some(bindings) = pattern.bindings() {
+                if bindings.len() != enum_variant.fields().len() {
+                    self.error(
+                        DiagnosticInfo::VariantPatternMismatch {
+                            variant: name.to_string(),
+                            expected: enum_variant.fields().len(),
+                            found: bindings.len(),
+                        },
+                        pattern.syntax().text_range(),
+                    );
+                }
+
+                let fields_hir = self.db.alloc_hir(Hir::Rest(value_hir));
+
+                for (i, (binding, &type_id)) in bindings
+                    .iter()
+                    .zip(enum_variant.fields().values())
+                    .enumerate()
+                {
+                    let hir_id = self.compile_index(fields_hir, i, false);
+                    let symbol_id = self.db.alloc_symbol(Symbol::LetBinding { type_id, hir_id });
+                    arm_scope.define_symbol(binding.to_string(), symbol_id);
+                }
+            }
+
+            let arm_scope_id = self.db.alloc_scope(arm_scope);
+            self.scope_stack.push(arm_scope_id);
+
+            let body = arm
+                .expr()
+                .map(|expr| self.compile_expr(expr, expected_type))
+                .unwrap_or(self.unknown());
+
+            self.scope_stack.pop().unwrap();
+
+            if let Some(expected_type) = expected_type {
+                self.type_check(body.ty(), expected_type, arm.syntax().text_range());
             } else {
-                self.bytes_type
-            },
-        )
-    }
+                expected_type = Some(body.ty());
+            }
 
-    fn compile_path_expr(&mut self, path: Path) -> Value {
-        let mut idents = path.idents();
+            result_type.get_or_insert(body.ty());
 
-        if idents.len() > 1 {
-            self.error(DiagnosticInfo::PathNotAllowed, path.syntax().text_range());
-            return self.unknown();
+            let hir_id = self.db.alloc_hir(Hir::Scope {
+                scope_id: arm_scope_id,
+                value: body.hir(),
+            });
+
+            arms.push((enum_variant.discriminant(), hir_id));
         }
 
-        let name = idents.remove(0);
+        self.scope_stack.pop().unwrap();
 
-        let Some(symbol_id) = self
-            .scope_stack
-            .iter()
-            .rev()
-            .find_map(|&scope_id| self.db.scope(scope_id).symbol(name.text()))
-        else {
+        let is_exhaustive = visited_variants.len() == enum_type.variants().len();
+
+        let missing_variants: Vec<String> = enum_type
+            .variants()
+            .keys()
+            .filter(|name| !visited_variants.contains(*name))
+            .cloned()
+            .collect();
+
+        if !missing_variants.is_empty() {
             self.error(
-                DiagnosticInfo::UndefinedReference(name.to_string()),
-                name.text_range(),
+                DiagnosticInfo::MissingMatchArms(missing_variants),
+                match_expr.syntax().text_range(),
             );
-            return self.unknown();
-        };
-
-        Value::typed(
-            self.db.alloc_hir(Hir::Reference(symbol_id)),
-            self.symbol_type(symbol_id)
-                .unwrap_or_else(|| match self.db.symbol(symbol_id) {
-                    Symbol::Function { ty, .. } => self.db.alloc_type(Type::Function(ty.clone())),
-                    Symbol::Parameter { type_id } => *type_id,
-                    Symbol::LetBinding { type_id, .. } => *type_id,
-                    Symbol::ConstBinding { type_id, .. } => *type_id, //todo
-                }),
-        )
-    }
+        }
 
-    fn expected_param_type(
-        &self,
-        function_type: FunctionType,
-        index: usize,
-        spread: bool,
-    ) -> Option<TypeId> {
-        let params = function_type.parameter_types();
-        let len = params.len();
+        let ty = result_type.unwrap_or(self.unknown_type);
 
-        if index + 1 < len {
-            return Some(params[index]);
+        if self.options.jump_tables() && is_exhaustive {
+            if let Some(hir_id) = self.match_jump_table(value_hir, &arms) {
+                let hir_id = self.db.alloc_hir(Hir::Scope {
+                    scope_id: match_scope_id,
+                    value: hir_id,
+                });
+                return Value::typed(hir_id, ty);
+            }
         }
 
-        if !function_type.varargs() {
-            if index + 1 == len {
-                return Some(params[index]);
-            } else {
-                return None;
-            }
+        // Since the match is exhaustive, the last arm doesn't need to check the discriminant.
+        let Some((_, mut hir_id)) = arms.pop() else {
+            return Value::typed(self.unknown_hir, ty);
+        };
+
+        for (discriminant, then_block) in arms.into_iter().rev() {
+            let tag = self.db.alloc_hir(Hir::First(value_hir));
+            let condition = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Equals,
+                lhs: tag,
+                rhs: discriminant,
+            });
+            hir_id = self.db.alloc_hir(Hir::If {
+                condition,
+                then_block,
+                else_block: hir_id,
+            });
         }
 
-        if spread {
-            return Some(params[len - 1]);
+        let hir_id = self.db.alloc_hir(Hir::Scope {
+            scope_id: match_scope_id,
+            value: hir_id,
+        });
+
+        Value::typed(hir_id, ty)
+    }
+
+    /// Looks up the arm of an exhaustive `match` by the tag of the value, when the discriminants
+    /// are consecutive integers, so each arm is at the index of its tag less the lowest one.
+    /// Enums with gaps between their discriminants keep the chain of comparisons.
+    fn match_jump_table(&mut self, value: HirId, arms: &[(HirId, HirId)]) -> Option<HirId> {
+        if !(MIN_JUMP_TABLE_ARMS..=MAX_JUMP_TABLE_SIZE).contains(&arms.len()) {
+            return None;
         }
 
-        match self.db.ty(params[len - 1]) {
-            Type::List(list_type) => Some(*list_type),
-            _ => None,
+        let mut arms = arms
+            .iter()
+            .map(|&(discriminant, arm)| match self.db.hir(discriminant) {
+                Hir::Atom(atom) => Some((BigInt::from_signed_bytes_be(atom), arm)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        arms.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if arms.windows(2).any(|pair| &pair[0].0 + 1 != pair[1].0) {
+            return None;
         }
-    }
 
-    fn compile_function_call(&mut self, call: FunctionCall) -> Value {
-        let Some(callee) = call.callee() else {
-            return self.unknown();
-        };
+        let low = arms[0].0.clone();
+        let tag = self.db.alloc_hir(Hir::First(value));
+
+        let index = if low == BigInt::ZERO {
+            tag
+        } else {
+            let low = self.db.alloc_hir(Hir::Atom(bigint_to_bytes(low)));
+            self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Subtract,
+                lhs: tag,
+                rhs: low,
+            })
+        };
+
+        let branches = arms.into_iter().map(|(_, arm)| arm).collect();
+        Some(self.db.alloc_hir(Hir::Dispatch { index, branches }))
+    }
+
+    fn compile_int_raw<T, E>(&mut self, int: SyntaxToken) -> T
+    where
+        T: FromStr<Err = E>,
+        E: fmt::Debug,
+    {
+        let (digits, _suffix) = split_int_suffix(int.text());
+
+        decimal_digits(digits)
+            .parse()
+            .expect("failed to parse into BigInt")
+    }
+
+    fn compile_int(&mut self, int: SyntaxToken) -> Value {
+        let num = self.compile_int_raw(int.clone());
+
+        let type_id = match split_int_suffix(int.text()).1 {
+            "" | "i" => self.int_type,
+            "b" => self.bytes_type,
+            suffix => {
+                self.error(
+                    DiagnosticInfo::UnknownLiteralSuffix(suffix.to_string()),
+                    int.text_range(),
+                );
+                self.unknown_type
+            }
+        };
+
+        Value::typed(self.db.alloc_hir(Hir::Atom(bigint_to_bytes(num))), type_id)
+    }
+
+    /// The bytes are kept exactly as written, including leading zeros, unlike integer literals.
+    fn compile_hex(&mut self, hex: SyntaxToken) -> Vec<u8> {
+        let digits = hex.text()[2..].replace('_', "");
+
+        hex::decode(digits).unwrap_or_else(|_| {
+            self.error(DiagnosticInfo::OddHexLength, hex.text_range());
+            Vec::new()
+        })
+    }
+
+    /// Compiles `pubkey(0x...)` and `signature(0x...)`, which check the length of the literal.
+    /// Returns `None` if the call isn't one of these, or the name has been shadowed.
+    fn compile_typed_literal(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let (type_id, length) = match name.text() {
+            "pubkey" => (self.public_key_type, 48),
+            "signature" => (self.signature_type, 96),
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        let hex = match args.as_slice() {
+            [arg] if arg.spread().is_none() => match arg.expr() {
+                Some(Expr::LiteralExpr(literal)) => literal
+                    .value()
+                    .filter(|value| value.kind() == SyntaxKind::Hex),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let Some(hex) = hex else {
+            self.error(
+                DiagnosticInfo::TypedLiteralArgument(name.to_string()),
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, type_id));
+        };
+
+        let bytes = self.compile_hex(hex.clone());
+
+        if bytes.len() != length {
+            self.error(
+                DiagnosticInfo::TypedLiteralLength {
+                    name: name.to_string(),
+                    expected: length,
+                    found: bytes.len(),
+                },
+                hex.text_range(),
+            );
+        }
+
+        Some(Value::typed(self.db.alloc_hir(Hir::Atom(bytes)), type_id))
+    }
+
+    /// Lowers `min` and `max` directly to comparisons, folding more than two arguments pairwise.
+    fn compile_min_max(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let op = match name.text() {
+            "min" => HirBinaryOp::LessThan,
+            "max" => HirBinaryOp::GreaterThan,
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() < 2 {
+            self.error(
+                DiagnosticInfo::TooFewArguments {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+        }
+
+        let mut values = self.compile_int_args(&args).into_iter();
+
+        let Some(mut hir_id) = values.next() else {
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        };
+
+        for value in values {
+            // Each side is used twice, so both are bound to be evaluated once and in order.
+            let (scopes, [current, next]) = self.bind_operands([hir_id, value]);
+
+            let condition = self.db.alloc_hir(Hir::BinaryOp {
+                op,
+                lhs: next,
+                rhs: current,
+            });
+
+            hir_id = self.db.alloc_hir(Hir::If {
+                condition,
+                then_block: next,
+                else_block: current,
+            });
+
+            hir_id = self.wrap_scopes(scopes, hir_id);
+        }
+
+        Some(Value::typed(hir_id, self.int_type))
+    }
+
+    /// Lowers `clamp(value, low, high)` to `max(low, min(high, value))`, so the result is `low`
+    /// whenever the bounds are the wrong way around.
+    fn compile_clamp(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "clamp" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 3 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 3,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        }
+
+        let values = self.compile_int_args(&args);
+
+        // The arguments are bound in the order they're written, so they're evaluated that way.
+        let (scopes, [value, low, high]) = self.bind_operands([values[0], values[1], values[2]]);
+
+        let is_above = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::LessThan,
+            lhs: high,
+            rhs: value,
+        });
+
+        let upper_bounded = self.db.alloc_hir(Hir::If {
+            condition: is_above,
+            then_block: high,
+            else_block: value,
+        });
+
+        // The upper bounded value is compared and used as the result, so it's bound as well.
+        let (inner_scopes, [upper_bounded]) = self.bind_operands([upper_bounded]);
+
+        let is_below = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::GreaterThan,
+            lhs: low,
+            rhs: upper_bounded,
+        });
+
+        let hir_id = self.db.alloc_hir(Hir::If {
+            condition: is_below,
+            then_block: low,
+            else_block: upper_bounded,
+        });
+
+        let hir_id = self.wrap_scopes(inner_scopes, hir_id);
+        let hir_id = self.wrap_scopes(scopes, hir_id);
+
+        Some(Value::typed(hir_id, self.int_type))
+    }
+
+    /// Lowers `abs` and `sign` directly to comparisons against zero.
+    fn compile_abs_sign(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if !matches!(name.text(), "abs" | "sign") {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 1 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 1,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+        }
+
+        let Some(&value) = self.compile_int_args(&args).first() else {
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        };
+
+        let (scopes, [value]) = self.bind_operands([value]);
+
+        let is_negative = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::LessThan,
+            lhs: value,
+            rhs: self.nil_hir,
+        });
+
+        let hir_id = if name.text() == "abs" {
+            let negated = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Subtract,
+                lhs: self.nil_hir,
+                rhs: value,
+            });
+
+            self.db.alloc_hir(Hir::If {
+                condition: is_negative,
+                then_block: negated,
+                else_block: value,
+            })
+        } else {
+            let is_positive = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::GreaterThan,
+                lhs: value,
+                rhs: self.nil_hir,
+            });
+            let one = self.db.alloc_hir(Hir::Atom(vec![1]));
+            let minus_one = self.db.alloc_hir(Hir::Atom(vec![0xff]));
+
+            let non_positive = self.db.alloc_hir(Hir::If {
+                condition: is_negative,
+                then_block: minus_one,
+                else_block: self.nil_hir,
+            });
+
+            self.db.alloc_hir(Hir::If {
+                condition: is_positive,
+                then_block: one,
+                else_block: non_positive,
+            })
+        };
+
+        Some(Value::typed(
+            self.wrap_scopes(scopes, hir_id),
+            self.int_type,
+        ))
+    }
+
+    /// Lowers `bit(value, index)` to `(value >> index) & 1`, which is the bit at that index.
+    fn compile_bit(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "bit" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.bool_type));
+        }
+
+        let [value, index] = self.compile_int_args(&args).try_into().unwrap();
+
+        let shifted = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::ShiftRight,
+            lhs: value,
+            rhs: index,
+        });
+        let one = self.db.alloc_hir(Hir::Atom(vec![1]));
+
+        let hir_id = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::BitwiseAnd,
+            lhs: shifted,
+            rhs: one,
+        });
+
+        Some(Value::typed(hir_id, self.bool_type))
+    }
+
+    /// Lowers `checked_div(a, b)` to `if b == 0 { nil } else { [a / b] }`, so that dividing by
+    /// zero gives an empty list instead of raising.
+    fn compile_checked_div(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "checked_div" {
+            return None;
+        }
+
+        let list_type = self.db.alloc_type(Type::List(self.int_type));
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, list_type));
+        }
+
+        let operands: [HirId; 2] = self.compile_int_args(&args).try_into().unwrap();
+        let (scopes, [lhs, rhs]) = self.bind_operands(operands);
+
+        let is_zero = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::Equals,
+            lhs: rhs,
+            rhs: self.nil_hir,
+        });
+        let quotient = self.db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::Divide,
+            lhs,
+            rhs,
+        });
+        let some = self.db.alloc_hir(Hir::Pair(quotient, self.nil_hir));
+
+        let hir_id = self.db.alloc_hir(Hir::If {
+            condition: is_zero,
+            then_block: self.nil_hir,
+            else_block: some,
+        });
+
+        Some(Value::typed(self.wrap_scopes(scopes, hir_id), list_type))
+    }
+
+    /// Lowers `try(value, fallback)` and `guard(condition, value, fallback)`. CLVM can't catch
+    /// errors, so `try` is only allowed when the value can't fail, and is then just the value.
+    /// A `guard` only evaluates the value if the condition is true, and is otherwise the fallback.
+    fn compile_try_guard(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let expected = match name.text() {
+            "try" => 2,
+            "guard" => 3,
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != expected {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        }
+
+        let condition = (expected == 3).then(|| {
+            let condition = args[0]
+                .expr()
+                .map(|expr| self.compile_expr(expr, Some(self.bool_type)))
+                .unwrap_or_else(|| self.unknown());
+            self.type_check(
+                condition.ty(),
+                self.bool_type,
+                args[0].syntax().text_range(),
+            );
+            condition
+        });
+
+        let value_arg = &args[expected - 2];
+        let fallback_arg = &args[expected - 1];
+
+        // The value is only evaluated if the condition is true, so its guards apply.
+        if let Some(condition) = condition.as_ref() {
+            self.type_guards.push(condition.then_guards());
+        }
+
+        let value = value_arg
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        if condition.is_some() {
+            self.type_guards.pop().unwrap();
+        }
+
+        if let Some(condition) = condition.as_ref() {
+            self.type_guards.push(condition.else_guards());
+        }
+
+        let fallback = fallback_arg
+            .expr()
+            .map(|expr| self.compile_expr(expr, Some(value.ty())))
+            .unwrap_or_else(|| self.unknown());
+
+        if condition.is_some() {
+            self.type_guards.pop().unwrap();
+        }
+
+        self.type_check(
+            fallback.ty(),
+            value.ty(),
+            fallback_arg.syntax().text_range(),
+        );
+
+        let Some(condition) = condition else {
+            if may_fail(self.db, value.hir()) {
+                self.error(DiagnosticInfo::CannotCatch, value_arg.syntax().text_range());
+            }
+            return Some(value);
+        };
+
+        let hir_id = self.db.alloc_hir(Hir::If {
+            condition: condition.hir(),
+            then_block: value.hir(),
+            else_block: fallback.hir(),
+        });
+
+        Some(Value::typed(hir_id, value.ty()))
+    }
+
+    /// Lowers `le_bytes(value, width)` to the little-endian encoding of the value in `width`
+    /// bytes. Each byte is `(value >> 8i) & 255`, offset by 256 so that its atom is always two
+    /// bytes long, and the second of those is concatenated in order.
+    fn compile_le_bytes(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "le_bytes" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.bytes_type));
+        }
+
+        let [value, width] = self.compile_int_args(&args).try_into().unwrap();
+
+        let width = self
+            .const_value(width, args[1].syntax().text_range())
+            .map(|width| BigInt::from_signed_bytes_be(&width))
+            .and_then(|width| usize::try_from(width).ok())
+            .filter(|width| (1..=MAX_LE_BYTES_WIDTH).contains(width));
+
+        let Some(width) = width else {
+            self.error(
+                DiagnosticInfo::InvalidByteWidth(MAX_LE_BYTES_WIDTH),
+                args[1].syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.bytes_type));
+        };
+
+        let (scopes, [value]) = self.bind_operands([value]);
+
+        let mask = self.db.alloc_hir(Hir::Atom(vec![0x00, 0xff]));
+        let offset = self.db.alloc_hir(Hir::Atom(vec![0x01, 0x00]));
+        let one = self.db.alloc_hir(Hir::Atom(vec![1]));
+        let two = self.db.alloc_hir(Hir::Atom(vec![2]));
+
+        let mut hir_id = None;
+
+        for index in 0..width {
+            let shifted = if index == 0 {
+                value
+            } else {
+                let shift = BigInt::from(index * 8).to_signed_bytes_be();
+                let shift = self.db.alloc_hir(Hir::Atom(shift));
+                self.db.alloc_hir(Hir::BinaryOp {
+                    op: HirBinaryOp::ShiftRight,
+                    lhs: value,
+                    rhs: shift,
+                })
+            };
+
+            let byte = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::BitwiseAnd,
+                lhs: shifted,
+                rhs: mask,
+            });
+            let byte = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Add,
+                lhs: byte,
+                rhs: offset,
+            });
+            let byte = self.db.alloc_hir(Hir::Substr {
+                value: byte,
+                start: one,
+                end: two,
+            });
+
+            hir_id = Some(match hir_id {
+                Some(bytes) => self.db.alloc_hir(Hir::BinaryOp {
+                    op: HirBinaryOp::Concat,
+                    lhs: bytes,
+                    rhs: byte,
+                }),
+                None => byte,
+            });
+        }
+
+        let ty = if width == 32 {
+            self.bytes32_type
+        } else {
+            self.bytes_type
+        };
+
+        Some(Value::typed(self.wrap_scopes(scopes, hir_id.unwrap()), ty))
+    }
+
+    /// Lowers `truncate(value, width)`, which is the first `width` bytes of the value, and
+    /// `pad(value, width)`, which adds zero bytes to the start of the value until it's `width`
+    /// bytes long. Both raise if the value can't be resized without losing or inventing bytes
+    /// on the other side, so the result is always exactly `width` bytes.
+    fn compile_resize(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if !matches!(name.text(), "truncate" | "pad") {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.bytes_type));
+        }
+
+        for arg in &args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+        }
+
+        let value = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, Some(self.bytes_type)))
+            .unwrap_or_else(|| self.unknown());
+        self.type_check(value.ty(), self.bytes_type, args[0].syntax().text_range());
+
+        let width = args[1]
+            .expr()
+            .map(|expr| self.compile_expr(expr, Some(self.int_type)))
+            .unwrap_or_else(|| self.unknown());
+        self.type_check(width.ty(), self.int_type, args[1].syntax().text_range());
+
+        let width = self
+            .const_value(width.hir(), args[1].syntax().text_range())
+            .map(|width| BigInt::from_signed_bytes_be(&width))
+            .and_then(|width| usize::try_from(width).ok())
+            .filter(|width| (1..=MAX_RESIZE_WIDTH).contains(width));
+
+        let Some(width) = width else {
+            self.error(
+                DiagnosticInfo::InvalidByteWidth(MAX_RESIZE_WIDTH),
+                args[1].syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.bytes_type));
+        };
+
+        let (scopes, [value]) = self.bind_operands([value.hir()]);
+
+        let width_atom = self
+            .db
+            .alloc_hir(Hir::Atom(BigInt::from(width).to_signed_bytes_be()));
+        let strlen = self.db.alloc_hir(Hir::Strlen(value));
+
+        let hir_id = if name.text() == "truncate" {
+            // The `substr` operator raises if the value is too short.
+            let zero = self.db.alloc_hir(Hir::Atom(Vec::new()));
+            self.db.alloc_hir(Hir::Substr {
+                value,
+                start: zero,
+                end: width_atom,
+            })
+        } else {
+            // The last `width` bytes of the zeros followed by the value.
+            let zeros = self.db.alloc_hir(Hir::Atom(vec![0; width]));
+            let padded = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Concat,
+                lhs: zeros,
+                rhs: value,
+            });
+            let end = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Add,
+                lhs: strlen,
+                rhs: width_atom,
+            });
+            let padded = self.db.alloc_hir(Hir::Substr {
+                value: padded,
+                start: strlen,
+                end,
+            });
+            let too_long = self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::GreaterThan,
+                lhs: strlen,
+                rhs: width_atom,
+            });
+            let raise = self.db.alloc_hir(Hir::Raise(None));
+            self.db.alloc_hir(Hir::If {
+                condition: too_long,
+                then_block: raise,
+                else_block: padded,
+            })
+        };
+
+        let ty = if width == 32 {
+            self.bytes32_type
+        } else {
+            self.bytes_type
+        };
+
+        Some(Value::typed(self.wrap_scopes(scopes, hir_id), ty))
+    }
+
+    /// Evaluates `serialized_length(value)` at compile time to the number of bytes the constant
+    /// takes once it's serialized, so that it can be used in other constants and static asserts.
+    fn compile_serialized_length(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "serialized_length" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 1 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 1,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        }
+
+        if args[0].spread().is_some() {
+            self.error(
+                DiagnosticInfo::NonVarargSpread,
+                args[0].syntax().text_range(),
+            );
+        }
+
+        let value = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        let length = eval_serialized_length(
+            self.db,
+            self.scope_stack[0],
+            self.options.max_depth(),
+            value.hir(),
+        );
+
+        let Ok(length) = length else {
+            self.error(DiagnosticInfo::EvalTooDeep, args[0].syntax().text_range());
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        };
+
+        let Some(length) = length else {
+            self.error(
+                DiagnosticInfo::NonConstantSerializedLength,
+                args[0].syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.int_type));
+        };
+
+        let hir_id = self
+            .db
+            .alloc_hir(Hir::Atom(bigint_to_bytes(BigInt::from(length))));
+
+        Some(Value::typed(hir_id, self.int_type))
+    }
+
+    /// Lowers `contains(list, value)` and `index_of(list, value)` to calls to recursive functions
+    /// which compare each item with the value like `==` does, which is with `=` for atoms and by
+    /// their contents for lists and pairs. The value is checked against the list's item type.
+    fn compile_list_search(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let (is_index_of, type_id) = match name.text() {
+            "contains" => (false, self.bool_type),
+            "index_of" => (true, self.db.alloc_type(Type::List(self.int_type))),
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, type_id));
+        }
+
+        for arg in &args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+        }
+
+        let list = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        let item_type = match self.db.ty(list.ty()) {
+            Type::List(item_type) => Some(*item_type),
+            Type::Nil | Type::Unknown => None,
+            _ => {
+                let any_list_type = self.db.alloc_type(Type::List(self.any_type));
+                self.type_check(list.ty(), any_list_type, args[0].syntax().text_range());
+                None
+            }
+        };
+
+        let value = args[1]
+            .expr()
+            .map(|expr| self.compile_expr(expr, item_type))
+            .unwrap_or_else(|| self.unknown());
+
+        if let Some(item_type) = item_type {
+            self.type_check(value.ty(), item_type, args[1].syntax().text_range());
+        }
+
+        let is_structured = item_type.is_some_and(|item_type| self.is_structured(item_type))
+            || self.is_structured(value.ty());
+
+        let function = match (is_index_of, is_structured) {
+            (false, false) => self.contains_function,
+            (true, false) => self.index_of_function,
+            (false, true) => self.structured_contains_function,
+            (true, true) => self.structured_index_of_function,
+        };
+
+        // The index of the first item is passed along, so that each call can add one to it.
+        let mut hir_id = self.nil_hir;
+        if is_index_of {
+            hir_id = self.db.alloc_hir(Hir::Pair(self.nil_hir, hir_id));
+        }
+        hir_id = self.db.alloc_hir(Hir::Pair(value.hir(), hir_id));
+        hir_id = self.db.alloc_hir(Hir::Pair(list.hir(), hir_id));
+
+        let callee = self.db.alloc_hir(Hir::Reference(function));
+        let hir_id = self.db.alloc_hir(Hir::FunctionCall {
+            callee,
+            args: hir_id,
+        });
+        self.operations.push(hir_id);
+
+        Some(Value::typed(hir_id, type_id))
+    }
+
+    /// Lowers `head(list)`, `tail(list)` and `last(list)` to calls of functions which check whether
+    /// the list is empty before reading from it. Each result is a list of the same item type, which
+    /// is nil for an empty list. Similarly, `reverse(list)` is the items of the list in reverse
+    /// order, which has the same type as the list.
+    fn compile_list_access(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let function = match name.text() {
+            "head" => self.head_function,
+            "tail" => self.tail_function,
+            "last" => self.last_function,
+            "reverse" => self.reverse_function,
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != 1 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 1,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        }
+
+        if args[0].spread().is_some() {
+            self.error(
+                DiagnosticInfo::NonVarargSpread,
+                args[0].syntax().text_range(),
+            );
+        }
+
+        let list = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        let type_id = match self.db.ty(list.ty()) {
+            Type::List(..) => list.ty(),
+            // Reversing a fixed size list keeps its size.
+            Type::Array(..) if function == self.reverse_function => list.ty(),
+            Type::Array(item_type, _) => self.db.alloc_type(Type::List(*item_type)),
+            Type::Nil | Type::Unknown => self.db.alloc_type(Type::List(self.any_type)),
+            _ => {
+                let any_list_type = self.db.alloc_type(Type::List(self.any_type));
+                self.type_check(list.ty(), any_list_type, args[0].syntax().text_range());
+                any_list_type
+            }
+        };
+
+        let mut args = self.nil_hir;
+
+        // The items are reversed onto an empty list.
+        if function == self.reverse_function {
+            args = self.db.alloc_hir(Hir::Pair(self.nil_hir, args));
+        }
+
+        let args = self.db.alloc_hir(Hir::Pair(list.hir(), args));
+        let callee = self.db.alloc_hir(Hir::Reference(function));
+        let hir_id = self.db.alloc_hir(Hir::FunctionCall { callee, args });
+        self.operations.push(hir_id);
+
+        Some(Value::typed(hir_id, type_id))
+    }
+
+    /// Lowers `nth(list, index)` to the first item left after skipping `index` items, which raises
+    /// like reading `.first` of nil if the list is too short. Similarly, `nth_opt(list, index)` is
+    /// a list of only that item, or nil if there isn't one, like `head`.
+    fn compile_list_nth(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let is_opt = match name.text() {
+            "nth" => false,
+            "nth_opt" => true,
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        }
+
+        for arg in &args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+        }
+
+        let list = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        let item_type = match self.db.ty(list.ty()) {
+            Type::List(item_type) | Type::Array(item_type, _) => *item_type,
+            Type::Nil | Type::Unknown => self.any_type,
+            _ => {
+                let any_list_type = self.db.alloc_type(Type::List(self.any_type));
+                self.type_check(list.ty(), any_list_type, args[0].syntax().text_range());
+                self.any_type
+            }
+        };
+
+        let index = args[1]
+            .expr()
+            .map(|expr| self.compile_expr(expr, Some(self.int_type)))
+            .unwrap_or_else(|| self.unknown());
+        self.type_check(index.ty(), self.int_type, args[1].syntax().text_range());
+
+        let args = self.db.alloc_hir(Hir::Pair(index.hir(), self.nil_hir));
+        let args = self.db.alloc_hir(Hir::Pair(list.hir(), args));
+        let callee = self.db.alloc_hir(Hir::Reference(self.skip_function));
+        let skipped = self.db.alloc_hir(Hir::FunctionCall { callee, args });
+        self.operations.push(skipped);
+
+        if !is_opt {
+            let hir_id = self.db.alloc_hir(Hir::First(skipped));
+            return Some(Value::typed(hir_id, item_type));
+        }
+
+        let args = self.db.alloc_hir(Hir::Pair(skipped, self.nil_hir));
+        let callee = self.db.alloc_hir(Hir::Reference(self.head_function));
+        let hir_id = self.db.alloc_hir(Hir::FunctionCall { callee, args });
+        self.operations.push(hir_id);
+
+        let type_id = self.db.alloc_type(Type::List(item_type));
+        Some(Value::typed(hir_id, type_id))
+    }
+
+    /// Lowers `zip(a, b)` and `enumerate(list)` to calls of recursive functions which pair up the
+    /// items of the lists. The result is typed from the item types of the lists passed in.
+    fn compile_list_pairs(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let (function, expected) = match name.text() {
+            "zip" => (self.zip_function, 2),
+            "enumerate" => (self.enumerate_function, 1),
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != expected {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        }
+
+        let mut lists = Vec::new();
+        let mut item_types = Vec::new();
+
+        for arg in &args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+
+            let list = arg
+                .expr()
+                .map(|expr| self.compile_expr(expr, None))
+                .unwrap_or_else(|| self.unknown());
+
+            // The items of an empty list could be anything, since there aren't any.
+            let item_type = match self.db.ty(list.ty()) {
+                Type::List(item_type) if !matches!(self.db.ty(*item_type), Type::Unknown) => {
+                    *item_type
+                }
+                Type::List(..) | Type::Nil | Type::Unknown => self.any_type,
+                _ => {
+                    let any_list_type = self.db.alloc_type(Type::List(self.any_type));
+                    self.type_check(list.ty(), any_list_type, arg.syntax().text_range());
+                    self.any_type
+                }
+            };
+
+            lists.push(list.hir());
+            item_types.push(item_type);
+        }
+
+        // The index of the first item is passed along, so that each call can add one to it.
+        let (pair_type, args) = match (lists.as_slice(), item_types.as_slice()) {
+            ([a, b], [a_type, b_type]) => (Type::Pair(*a_type, *b_type), [*a, *b]),
+            ([list], [item_type]) => (Type::Pair(self.int_type, *item_type), [*list, self.nil_hir]),
+            _ => unreachable!(),
+        };
+
+        let mut hir_id = self.nil_hir;
+        for arg in args.into_iter().rev() {
+            hir_id = self.db.alloc_hir(Hir::Pair(arg, hir_id));
+        }
+
+        let callee = self.db.alloc_hir(Hir::Reference(function));
+        let hir_id = self.db.alloc_hir(Hir::FunctionCall {
+            callee,
+            args: hir_id,
+        });
+        self.operations.push(hir_id);
+
+        let pair_type = self.db.alloc_type(pair_type);
+        let type_id = self.db.alloc_type(Type::List(pair_type));
+
+        Some(Value::typed(hir_id, type_id))
+    }
+
+    /// Lowers `softfork(cost, program)` to the softfork operator, which runs the program as an
+    /// extension of the current environment. Its result is discarded, so the call is always nil.
+    fn compile_softfork(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "softfork" {
+            return None;
+        }
+
+        let operator = Op::Softfork.operator();
+        let result_type = self.operand_type(operator.result_type());
+        let args = call.args();
+
+        if args.len() != 2 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 2,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, result_type));
+        }
+
+        // The cost and program are the first two operands, and the extension and environment are
+        // filled in by codegen.
+        let [cost, program] = [0, 1].map(|index| {
+            let arg = &args[index];
+
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+
+            let arg_type = self.operand_type(operator.arg_types()[index]);
+
+            let value = arg
+                .expr()
+                .map(|expr| self.compile_expr(expr, Some(arg_type)))
+                .unwrap_or_else(|| self.unknown());
+
+            self.type_check(value.ty(), arg_type, arg.syntax().text_range());
+            value.hir()
+        });
+
+        let is_positive = self
+            .const_value(cost, args[0].syntax().text_range())
+            .is_some_and(|cost| BigInt::from_signed_bytes_be(&cost) > BigInt::ZERO);
+
+        if !is_positive {
+            self.error(
+                DiagnosticInfo::NonConstantSoftforkCost,
+                args[0].syntax().text_range(),
+            );
+        }
+
+        let hir_id = self.db.alloc_hir(Hir::Softfork { cost, program });
+
+        Some(Value::typed(hir_id, result_type))
+    }
+
+    /// Lowers `env()` to the arguments of the enclosing function, exactly as they were passed.
+    /// This bypasses the parameter types, so the value is untyped.
+    fn compile_env(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "env" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if !args.is_empty() {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 0,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+        }
+
+        Some(Value::typed(self.db.alloc_hir(Hir::Env), self.any_type))
+    }
+
+    /// Lowers `dbg(value)` to the value itself, unless `debug` is defined with `-D debug`, in which
+    /// case it raises with the value so that it can be inspected when the program is run.
+    fn compile_dbg(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "dbg" {
+            return None;
+        }
+
+        let args = call.args();
+
+        if args.len() != 1 {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 1,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        }
+
+        if args[0].spread().is_some() {
+            self.error(
+                DiagnosticInfo::NonVarargSpread,
+                args[0].syntax().text_range(),
+            );
+        }
+
+        let value = args[0]
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+            .unwrap_or_else(|| self.unknown());
+
+        if !self.options.is_defined("debug") {
+            return Some(value);
+        }
+
+        let hir_id = self.db.alloc_hir(Hir::Raise(Some(value.hir())));
+        Some(Value::typed(hir_id, value.ty()))
+    }
+
+    /// Lowers `op(opcode, args...)` to the CLVM operator with that opcode, applied to the
+    /// arguments. The operator isn't known to the compiler, so the arguments and result are untyped.
+    fn compile_op(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "op" {
+            return None;
+        }
+
+        let args = call.args();
+
+        let Some((opcode_arg, args)) = args.split_first() else {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected: 1,
+                    found: 0,
+                },
+                call.syntax().text_range(),
+            );
+            return Some(self.unknown());
+        };
+
+        let opcode = self.compile_int_args(std::slice::from_ref(opcode_arg))[0];
+
+        let opcode = self
+            .const_value(opcode, opcode_arg.syntax().text_range())
+            .filter(|opcode| BigInt::from_signed_bytes_be(opcode) > BigInt::ZERO);
+
+        if opcode.is_none() {
+            self.error(
+                DiagnosticInfo::NonConstantOpcode,
+                opcode_arg.syntax().text_range(),
+            );
+        }
+
+        let mut values = Vec::new();
+
+        for arg in args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+
+            let value = arg
+                .expr()
+                .map(|expr| self.compile_expr(expr, None))
+                .unwrap_or_else(|| self.unknown());
+            values.push(value.hir());
+        }
+
+        let Some(opcode) = opcode else {
+            return Some(self.unknown());
+        };
+
+        let hir_id = self.db.alloc_hir(Hir::Op {
+            opcode,
+            args: values,
+        });
+        self.operations.push(hir_id);
+
+        Some(Value::typed(hir_id, self.any_type))
+    }
+
+    /// Lowers `concat(a, b, ...)` to a concatenation of every argument, which is folded into a
+    /// single atom by the optimizer where the arguments are constant.
+    fn compile_concat(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        if name.text() != "concat" {
+            return None;
+        }
+
+        let operator = Op::Concat.operator();
+        let mut hir_id = None;
+
+        for (index, arg) in call.args().into_iter().enumerate() {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+
+            let arg_type = operator
+                .arg_type(index)
+                .map_or(self.unknown_type, |ty| self.operand_type(ty));
+
+            let value = arg
+                .expr()
+                .map(|expr| self.compile_expr(expr, Some(arg_type)))
+                .unwrap_or_else(|| self.unknown());
+
+            self.coercion_check(value.ty(), arg_type, arg.syntax().text_range());
+
+            hir_id = Some(match hir_id {
+                Some(lhs) => self.db.alloc_hir(Hir::BinaryOp {
+                    op: HirBinaryOp::Concat,
+                    lhs,
+                    rhs: value.hir(),
+                }),
+                None => value.hir(),
+            });
+        }
+
+        let result_type = self.operand_type(operator.result_type());
+
+        Some(Value::typed(hir_id.unwrap_or(self.nil_hir), result_type))
+    }
+
+    /// Lowers `require(cond)` and `require_eq(a, b)` to a check which raises if it fails.
+    /// Like `assert`, the call is nil when the check passes.
+    fn compile_require(&mut self, call: &FunctionCall) -> Option<Value> {
+        let name = self.intrinsic_name(call)?;
+
+        let expected = match name.text() {
+            "require" => 1,
+            "require_eq" => 2,
+            _ => return None,
+        };
+
+        let args = call.args();
+
+        if args.len() != expected {
+            self.error(
+                DiagnosticInfo::ArgumentMismatch {
+                    expected,
+                    found: args.len(),
+                },
+                call.syntax().text_range(),
+            );
+            return Some(Value::typed(self.unknown_hir, self.nil_type));
+        }
+
+        let condition = if expected == 1 {
+            if args[0].spread().is_some() {
+                self.error(
+                    DiagnosticInfo::NonVarargSpread,
+                    args[0].syntax().text_range(),
+                );
+            }
+
+            let condition = args[0]
+                .expr()
+                .map(|expr| self.compile_expr(expr, Some(self.bool_type)))
+                .unwrap_or_else(|| self.unknown());
+
+            self.type_check(
+                condition.ty(),
+                self.bool_type,
+                args[0].syntax().text_range(),
+            );
+
+            condition.hir()
+        } else {
+            let [lhs, rhs] = self.compile_int_args(&args).try_into().unwrap();
+
+            self.db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Equals,
+                lhs,
+                rhs,
+            })
+        };
+
+        let raise = self.db.alloc_hir(Hir::Raise(None));
+
+        let hir_id = self.db.alloc_hir(Hir::If {
+            condition,
+            then_block: self.nil_hir,
+            else_block: raise,
+        });
+
+        Some(Value::typed(hir_id, self.nil_type))
+    }
+
+    /// The name of a call to a builtin which is lowered directly, unless it has been shadowed.
+    fn intrinsic_name(&self, call: &FunctionCall) -> Option<SyntaxToken> {
+        let Some(Expr::Path(path)) = call.callee() else {
+            return None;
+        };
+
+        let [name] = path.idents().try_into().ok()?;
+
+        builtin(name.text())?;
+
+        if self
+            .scope_stack
+            .iter()
+            .any(|&scope_id| self.db.scope(scope_id).symbol(name.text()).is_some())
+        {
+            return None;
+        }
+
+        Some(name)
+    }
+
+    fn compile_int_args(&mut self, args: &[FunctionCallArg]) -> Vec<HirId> {
+        let mut values = Vec::new();
+
+        for arg in args {
+            if arg.spread().is_some() {
+                self.error(DiagnosticInfo::NonVarargSpread, arg.syntax().text_range());
+            }
+
+            let value = arg
+                .expr()
+                .map(|expr| self.compile_expr(expr, Some(self.int_type)))
+                .unwrap_or_else(|| self.unknown());
+
+            self.type_check(value.ty(), self.int_type, arg.syntax().text_range());
+            values.push(value.hir());
+        }
+
+        values
+    }
+
+    /// Binds each operand which isn't already a plain value to a hidden symbol in its own scope.
+    /// Returns the scopes from outermost to innermost and references to the operands.
+    fn bind_operands<const N: usize>(
+        &mut self,
+        mut operands: [HirId; N],
+    ) -> (Vec<ScopeId>, [HirId; N]) {
+        let mut scopes = Vec::new();
+
+        for operand in operands.iter_mut() {
+            if matches!(self.db.hir(*operand), Hir::Atom(..) | Hir::Reference(..)) {
+                continue;
+            }
+
+            let symbol_id = self.db.alloc_symbol(Symbol::LetBinding {
+                type_id: self.int_type,
+                hir_id: *operand,
+            });
+
+            let mut scope = Scope::default();
+            scope.define_hidden_symbol(symbol_id);
+            scopes.push(self.db.alloc_scope(scope));
+
+            *operand = self.db.alloc_hir(Hir::Reference(symbol_id));
+        }
+
+        (scopes, operands)
+    }
+
+    fn wrap_scopes(&mut self, scopes: Vec<ScopeId>, mut hir_id: HirId) -> HirId {
+        for scope_id in scopes.into_iter().rev() {
+            hir_id = self.db.alloc_hir(Hir::Scope {
+                scope_id,
+                value: hir_id,
+            });
+        }
+        hir_id
+    }
+
+    fn compile_string(&mut self, string: SyntaxToken) -> Value {
+        let bytes = unquote(string.text()).into_bytes();
+        let len = bytes.len();
+
+        Value::typed(
+            self.db.alloc_hir(Hir::Atom(bytes)),
+            if len == 32 {
+                self.bytes32_type
+            } else {
+                self.bytes_type
+            },
+        )
+    }
+
+    /// Finds the symbol in scope whose name is closest to an undefined one, if any is close enough
+    /// to likely be a typo.
+    fn suggest_symbol(&self, name: &str) -> Option<String> {
+        // Names shorter than three characters are close to too many others to suggest anything.
+        let max_distance = name.chars().count() / 3;
+
+        self.scope_stack
+            .iter()
+            .flat_map(|&scope_id| self.db.scope(scope_id).symbol_names())
+            .map(|candidate| (edit_distance(name, candidate), candidate))
+            .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    fn compile_path_expr(&mut self, path: Path) -> Value {
+        let mut idents = path.idents();
+
+        if idents.len() > 1 {
+            return self.compile_variant_path(path);
+        }
+
+        let name = idents.remove(0);
+
+        let Some(symbol_id) = self
+            .scope_stack
+            .iter()
+            .rev()
+            .find_map(|&scope_id| self.db.scope(scope_id).symbol(name.text()))
+        else {
+            let info = match self.suggest_symbol(name.text()) {
+                Some(suggestion) => DiagnosticInfo::UndefinedReferenceSuggestion {
+                    name: name.to_string(),
+                    suggestion,
+                },
+                None => DiagnosticInfo::UndefinedReference(name.to_string()),
+            };
+            self.error(info, name.text_range());
+            return self.unknown();
+        };
+
+        if let Some(&declared_at) = self.private_symbols.get(&symbol_id) {
+            self.check_visibility(declared_at, &name);
+        }
+
+        if self.unavailable_builtins.contains(&symbol_id) {
+            self.error(
+                DiagnosticInfo::UnavailableBuiltin {
+                    name: name.to_string(),
+                    target: self.options.target().name().to_string(),
+                },
+                name.text_range(),
+            );
+        }
+
+        let declared_type = match self.db.symbol(symbol_id) {
+            Symbol::Function { ty, .. } => self.db.alloc_type(Type::Function(ty.clone())),
+            Symbol::Parameter { type_id } => *type_id,
+            Symbol::LetBinding { type_id, .. } => *type_id,
+            Symbol::ConstBinding { type_id, .. } => *type_id, //todo
+        };
+
+        let mut hir_id = self.db.alloc_hir(Hir::Reference(symbol_id));
+
+        let Some(type_id) = self.symbol_type(symbol_id) else {
+            return Value::typed(hir_id, declared_type);
+        };
+
+        // A union which has been narrowed to one of its members is read without its tag.
+        if matches!(self.db.ty(declared_type), Type::Union(..))
+            && !self.types_equal(type_id, declared_type)
+        {
+            hir_id = self.db.alloc_hir(Hir::Rest(hir_id));
+        }
+
+        Value::typed(hir_id, type_id)
+    }
+
+    /// Paths such as `Color::Green` refer to enum variants, which can only be used as values
+    /// directly if they don't have any fields.
+    fn compile_variant_path(&mut self, path: Path) -> Value {
+        if let Some(value) = self.compile_method_path(&path) {
+            return value;
+        }
+
+        let type_id = self.compile_path_type(path.clone());
+
+        match self.db.ty(type_id).clone() {
+            Type::EnumVariant(enum_variant) if enum_variant.fields().is_empty() => {
+                let hir_id = self
+                    .db
+                    .alloc_hir(Hir::Pair(enum_variant.discriminant(), self.nil_hir));
+                Value::typed(hir_id, type_id)
+            }
+            Type::Unknown => self.unknown(),
+            _ => {
+                self.error(DiagnosticInfo::PathNotAllowed, path.syntax().text_range());
+                self.unknown()
+            }
+        }
+    }
+
+    /// Paths such as `Point::len` refer to methods declared in an `impl` block for the struct.
+    fn compile_method_path(&mut self, path: &Path) -> Option<Value> {
+        let idents = path.idents();
+        let [type_name, method_name] = idents.as_slice() else {
+            return None;
+        };
+
+        let type_id = self
+            .scope_stack
+            .iter()
+            .rev()
+            .find_map(|&scope_id| self.db.scope(scope_id).type_alias(type_name.text()))?;
+
+        if !matches!(self.db.ty(type_id), Type::Struct(..)) {
+            return None;
+        }
+
+        let Some(symbol_id) = self.method(type_id, method_name.text()) else {
+            self.error(
+                DiagnosticInfo::UndefinedMethod {
+                    ty: type_name.to_string(),
+                    name: method_name.to_string(),
+                },
+                method_name.text_range(),
+            );
+            return Some(self.unknown());
+        };
+
+        Some(self.compile_method_reference(symbol_id))
+    }
+
+    fn compile_method_reference(&mut self, symbol_id: SymbolId) -> Value {
+        let Symbol::Function { ty, .. } = self.db.symbol(symbol_id).clone() else {
+            unreachable!();
+        };
+        let type_id = self.db.alloc_type(Type::Function(ty));
+        Value::typed(self.db.alloc_hir(Hir::Reference(symbol_id)), type_id)
+    }
+
+    /// Compiles the callee of a call such as `p.len()`, which is a method call if the value has
+    /// a method with that name. The value is returned separately, since it's passed as `self`.
+    fn compile_method_callee(&mut self, field_access: FieldAccess) -> (Value, Option<Value>) {
+        let Some(receiver) = field_access
+            .expr()
+            .map(|expr| self.compile_expr(expr, None))
+        else {
+            return (self.unknown(), None);
+        };
+
+        let Some(field_name) = field_access.field() else {
+            return (self.unknown(), None);
+        };
+
+        if let Some(symbol_id) = self.method(receiver.ty(), field_name.text()) {
+            return (self.compile_method_reference(symbol_id), Some(receiver));
+        }
+
+        (self.compile_field(receiver, field_name), None)
+    }
+
+    fn expected_param_type(
+        &self,
+        function_type: FunctionType,
+        index: usize,
+        spread: bool,
+    ) -> Option<TypeId> {
+        let params = function_type.parameter_types();
+        let len = params.len();
+
+        if index + 1 < len {
+            return Some(params[index]);
+        }
+
+        if !function_type.varargs() {
+            if index + 1 == len {
+                return Some(params[index]);
+            } else {
+                return None;
+            }
+        }
+
+        if spread {
+            return Some(params[len - 1]);
+        }
+
+        match self.db.ty(params[len - 1]) {
+            Type::List(list_type) => Some(*list_type),
+            _ => None,
+        }
+    }
+
+    fn compile_function_call(&mut self, call: FunctionCall) -> Value {
+        if let Some(value) = self.compile_typed_literal(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_min_max(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_clamp(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_abs_sign(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_bit(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_checked_div(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_try_guard(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_le_bytes(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_resize(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_serialized_length(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_list_search(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_list_pairs(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_list_access(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_list_nth(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_softfork(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_require(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_env(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_dbg(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_op(&call) {
+            return value;
+        }
+
+        if let Some(value) = self.compile_concat(&call) {
+            return value;
+        }
+
+        let Some(callee) = call.callee() else {
+            return self.unknown();
+        };
+
+        let (callee, receiver) = match callee {
+            Expr::FieldAccess(field_access) => self.compile_method_callee(field_access),
+            callee => (self.compile_expr(callee, None), None),
+        };
+
+        // Calls to a deprecated function still compile, but each of them is reported.
+        if let Hir::Reference(symbol_id) = self.db.hir(callee.hir()) {
+            if let Some((name, message)) = self.deprecated.get(symbol_id).cloned() {
+                self.warning(
+                    DiagnosticInfo::DeprecatedFunction { name, message },
+                    call.syntax().text_range(),
+                );
+            }
+        }
 
-        let callee = self.compile_expr(callee, None);
-        let expected = match self.db.ty(callee.ty()) {
+        let mut expected = match self.db.ty(callee.ty()) {
             Type::Function(function) => Some(function.clone()),
+            // The callee has already been reported, such as for an undefined reference.
+            Type::Unknown => None,
             _ => {
                 self.error(
                     DiagnosticInfo::UncallableType(self.type_name(callee.ty())),
-                    call.callee().unwrap().syntax().text_range(),
+                    call.syntax().text_range(),
                 );
                 None
             }
         };
 
+        // The receiver of a method call is checked against the first parameter, and the rest of
+        // the arguments against the parameters after it.
+        if let (Some(function), Some(receiver)) = (expected.clone(), receiver.as_ref()) {
+            let params = function.parameter_types();
+
+            if let Some(&self_type) = params.first() {
+                let range = call.callee().unwrap().syntax().text_range();
+                self.type_check(receiver.ty(), self_type, range);
+                expected = Some(FunctionType::new(
+                    params[1..].to_vec(),
+                    function.return_type(),
+                    function.varargs() && params.len() > 1,
+                ));
+            } else {
+                self.error(
+                    DiagnosticInfo::ArgumentMismatch {
+                        expected: 0,
+                        found: call.args().len() + 1,
+                    },
+                    call.syntax().text_range(),
+                );
+                expected = None;
+            }
+        }
+
         let mut args = self.nil_hir;
         let mut arg_types = Vec::new();
+        let mut arg_ranges = Vec::new();
         let mut spread = false;
+        let mut scopes = Vec::new();
 
         let arg_len = call.args().len();
 
         for (i, arg) in call.args().into_iter().enumerate().rev() {
-            let expected_type = expected.as_ref().and_then(|expected| {
-                self.expected_param_type(
-                    expected.clone(),
-                    i,
-                    i + 1 == arg_len && arg.spread().is_some(),
-                )
-            });
+            let range = arg.syntax().text_range();
+
+            // A tuple can be spread into the remaining parameters of a function which doesn't
+            // take a variable number of arguments, with one item for each of them.
+            let tuple_params = expected
+                .as_ref()
+                .filter(|expected| {
+                    i + 1 == arg_len && arg.spread().is_some() && !expected.varargs()
+                })
+                .map(|expected| {
+                    expected
+                        .parameter_types()
+                        .get(i..)
+                        .unwrap_or_default()
+                        .to_vec()
+                });
+
+            let expected_type = match &tuple_params {
+                Some(params) => self.tuple_type(params),
+                None => expected.as_ref().and_then(|expected| {
+                    self.expected_param_type(
+                        expected.clone(),
+                        i,
+                        i + 1 == arg_len && arg.spread().is_some(),
+                    )
+                }),
+            };
 
             let value = arg
                 .expr()
                 .map(|expr| self.compile_expr(expr, expected_type))
                 .unwrap_or_else(|| self.unknown());
 
+            if let Some(params) = tuple_params {
+                if matches!(self.db.ty(value.ty()), Type::Pair(..)) {
+                    let (tuple_scopes, [tuple]) = self.bind_operands([value.hir()]);
+                    scopes.extend(tuple_scopes);
+
+                    let items = self.tuple_items(tuple, value.ty(), params.len().max(1));
+
+                    for (hir_id, type_id) in items.into_iter().rev() {
+                        arg_types.push(type_id);
+                        arg_ranges.push(range);
+                        args = self.db.alloc_hir(Hir::Pair(hir_id, args));
+                    }
+                    continue;
+                }
+            }
+
             arg_types.push(value.ty());
+            arg_ranges.push(range);
 
             if arg.spread().is_some() {
                 if i + 1 == arg_len {
@@ -1459,6 +4751,11 @@ impl<'a> Lowerer<'a> {
         }
 
         arg_types.reverse();
+        arg_ranges.reverse();
+
+        if let Some(receiver) = receiver {
+            args = self.db.alloc_hir(Hir::Pair(receiver.hir(), args));
+        }
 
         if let Some(expected) = expected.as_ref() {
             let param_len = expected.parameter_types().len();
@@ -1478,10 +4775,7 @@ impl<'a> Lowerer<'a> {
 
             for (i, arg) in arg_types.into_iter().enumerate() {
                 if i + 1 == arg_len && spread && !expected.varargs() {
-                    self.error(
-                        DiagnosticInfo::NonVarargSpread,
-                        call.args()[i].syntax().text_range(),
-                    );
+                    self.error(DiagnosticInfo::NonVarargSpread, arg_ranges[i]);
                     continue;
                 }
 
@@ -1491,13 +4785,10 @@ impl<'a> Lowerer<'a> {
                         .ty(expected.parameter_types().last().copied().unwrap())
                     {
                         Type::List(list_type) => {
-                            self.type_check(arg, *list_type, call.args()[i].syntax().text_range());
+                            self.type_check(arg, *list_type, arg_ranges[i]);
                         }
                         _ => {
-                            self.error(
-                                DiagnosticInfo::NonListVararg,
-                                call.args()[i].syntax().text_range(),
-                            );
+                            self.error(DiagnosticInfo::NonListVararg, arg_ranges[i]);
                         }
                     }
                     continue;
@@ -1507,7 +4798,7 @@ impl<'a> Lowerer<'a> {
                     self.type_check(
                         arg,
                         expected.parameter_types()[param_len - 1],
-                        call.args()[i].syntax().text_range(),
+                        arg_ranges[i],
                     );
                     continue;
                 }
@@ -1519,7 +4810,7 @@ impl<'a> Lowerer<'a> {
                         .get(i)
                         .copied()
                         .unwrap_or(self.unknown_type),
-                    call.args()[i].syntax().text_range(),
+                    arg_ranges[i],
                 );
             }
         }
@@ -1528,12 +4819,55 @@ impl<'a> Lowerer<'a> {
             callee: callee.hir(),
             args,
         });
+        self.operations.push(hir_id);
+
+        if let Hir::Reference(symbol_id) = *self.db.hir(callee.hir()) {
+            self.guarded_calls
+                .push((symbol_id, args, call.syntax().text_range()));
+        }
 
         let type_id = expected
             .map(|expected| expected.return_type())
             .unwrap_or(self.unknown_type);
 
-        Value::typed(hir_id, type_id)
+        Value::typed(self.wrap_scopes(scopes, hir_id), type_id)
+    }
+
+    /// The tuple type whose items are the given types, if there are at least two of them.
+    fn tuple_type(&mut self, items: &[TypeId]) -> Option<TypeId> {
+        let (&last, rest) = items.split_last()?;
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        Some(rest.iter().rev().fold(last, |tuple, &item| {
+            self.db.alloc_type(Type::Pair(item, tuple))
+        }))
+    }
+
+    /// Splits a tuple into up to `len` items, where the last one is the rest of the tuple. There
+    /// are fewer items if the tuple is shorter.
+    fn tuple_items(
+        &mut self,
+        mut hir_id: HirId,
+        mut type_id: TypeId,
+        len: usize,
+    ) -> Vec<(HirId, TypeId)> {
+        let mut items = Vec::new();
+
+        while items.len() + 1 < len {
+            let Type::Pair(first, rest) = *self.db.ty(type_id) else {
+                break;
+            };
+
+            items.push((self.db.alloc_hir(Hir::First(hir_id)), first));
+            hir_id = self.db.alloc_hir(Hir::Rest(hir_id));
+            type_id = rest;
+        }
+
+        items.push((hir_id, type_id));
+        items
     }
 
     fn compile_type(&mut self, ty: AstType) -> TypeId {
@@ -1541,7 +4875,19 @@ impl<'a> Lowerer<'a> {
             AstType::Path(path) => self.compile_path_type(path),
             AstType::ListType(list) => self.compile_list_type(list),
             AstType::FunctionType(function) => self.compile_function_type(function),
-            AstType::PairType(tuple) => self.compile_pair_type(tuple),
+            AstType::TupleType(tuple) => self.compile_tuple_type(tuple),
+            AstType::UnionType(union) => {
+                let members = union
+                    .items()
+                    .into_iter()
+                    .map(|ty| self.compile_type(ty))
+                    .collect();
+                self.db.alloc_type(Type::Union(members))
+            }
+            AstType::GroupType(group) => group
+                .ty()
+                .map(|ty| self.compile_type(ty))
+                .unwrap_or(self.unknown_type),
         }
     }
 
@@ -1566,6 +4912,10 @@ impl<'a> Lowerer<'a> {
             return self.unknown_type;
         };
 
+        if let Some(&declared_at) = self.private_types.get(&ty) {
+            self.check_visibility(declared_at, &name);
+        }
+
         for name in idents {
             ty = self.path_into_type(ty, name.text(), name.text_range());
         }
@@ -1573,6 +4923,18 @@ impl<'a> Lowerer<'a> {
         ty
     }
 
+    /// Items which aren't `pub` can only be referenced from the file they're declared in.
+    fn check_visibility(&mut self, declared_at: usize, name: &SyntaxToken) {
+        let used_at = name.text_range().start().into();
+
+        if self.options.file_index(declared_at) != self.options.file_index(used_at) {
+            self.error(
+                DiagnosticInfo::PrivateItem(name.to_string()),
+                name.text_range(),
+            );
+        }
+    }
+
     fn path_into_type(&mut self, ty: TypeId, name: &str, range: TextRange) -> TypeId {
         match self.db.ty(ty) {
             Type::Enum(enum_type) => {
@@ -1595,21 +4957,35 @@ impl<'a> Lowerer<'a> {
         };
 
         let item_type = self.compile_type(inner);
-        self.db.alloc_type(Type::List(item_type))
+
+        let Some(len) = list.len() else {
+            return self.db.alloc_type(Type::List(item_type));
+        };
+
+        let len = decimal_digits(split_int_suffix(len.text()).0)
+            .parse()
+            .unwrap_or(usize::MAX);
+
+        self.db.alloc_type(Type::Array(item_type, len))
     }
 
-    fn compile_pair_type(&mut self, pair_type: PairType) -> TypeId {
-        let first = pair_type
-            .first()
+    fn compile_tuple_type(&mut self, tuple_type: TupleType) -> TypeId {
+        let items: Vec<TypeId> = tuple_type
+            .items()
+            .into_iter()
             .map(|ty| self.compile_type(ty))
-            .unwrap_or(self.unknown_type);
+            .collect();
 
-        let rest = pair_type
-            .rest()
-            .map(|ty| self.compile_type(ty))
-            .unwrap_or(self.unknown_type);
+        // Tuples are nested pairs, where the last item is the rest of the innermost pair.
+        let Some(mut type_id) = items.last().copied() else {
+            return self.unknown_type;
+        };
+
+        for &item in items.iter().rev().skip(1) {
+            type_id = self.db.alloc_type(Type::Pair(item, type_id));
+        }
 
-        self.db.alloc_type(Type::Pair(first, rest))
+        type_id
     }
 
     fn compile_function_type(&mut self, function: AstFunctionType) -> TypeId {
@@ -1654,7 +5030,10 @@ impl<'a> Lowerer<'a> {
         visited_aliases: &mut HashSet<TypeId>,
     ) -> bool {
         match self.db.ty_raw(ty).clone() {
-            Type::List(..) => false,
+            // Any member of a union can end the recursion.
+            Type::List(..) | Type::Array(_, 0) | Type::Union(..) => false,
+            // Unlike a list, a fixed size list can't end early, so it contains its items.
+            Type::Array(inner, _) => self.detect_cycle(inner, text_range, visited_aliases),
             Type::Pair(left, right) => {
                 self.detect_cycle(left, text_range, visited_aliases)
                     || self.detect_cycle(right, text_range, visited_aliases)
@@ -1676,87 +5055,14 @@ impl<'a> Lowerer<'a> {
             | Type::Int
             | Type::Bool
             | Type::Bytes
-            | Type::Bytes32 => false,
+            | Type::Bytes32
+            | Type::PublicKey
+            | Type::Signature => false,
         }
     }
 
     fn type_name(&self, ty: TypeId) -> String {
-        self.type_name_visitor(ty, &mut IndexSet::new())
-    }
-
-    fn type_name_visitor(&self, ty: TypeId, stack: &mut IndexSet<TypeId>) -> String {
-        for &scope_id in self.scope_stack.iter().rev() {
-            if let Some(name) = self.db.scope(scope_id).type_name(ty) {
-                return name.to_string();
-            }
-        }
-
-        if stack.contains(&ty) {
-            return "<recursive>".to_string();
-        }
-
-        stack.insert(ty);
-
-        let name = match self.db.ty(ty) {
-            Type::Unknown => "{unknown}".to_string(),
-            Type::Nil => "Nil".to_string(),
-            Type::Any => "Any".to_string(),
-            Type::Int => "Int".to_string(),
-            Type::Bool => "Bool".to_string(),
-            Type::Bytes => "Bytes".to_string(),
-            Type::Bytes32 => "Bytes32".to_string(),
-            Type::List(items) => {
-                let inner = self.type_name_visitor(*items, stack);
-                format!("{}[]", inner)
-            }
-            Type::Pair(left, right) => {
-                let left = self.type_name_visitor(*left, stack);
-                let right = self.type_name_visitor(*right, stack);
-                format!("({left}, {right})")
-            }
-            Type::Struct(struct_type) => {
-                let fields: Vec<String> = struct_type
-                    .fields()
-                    .iter()
-                    .map(|(name, ty)| format!("{}: {}", name, self.type_name_visitor(*ty, stack)))
-                    .collect();
-
-                format!("{{ {} }}", fields.join(", "))
-            }
-            Type::Enum { .. } => "<unnamed enum>".to_string(),
-            Type::EnumVariant(enum_variant) => {
-                let enum_name = self.type_name_visitor(enum_variant.enum_type(), stack);
-
-                let fields: Vec<String> = enum_variant
-                    .fields()
-                    .iter()
-                    .map(|(name, ty)| format!("{}: {}", name, self.type_name_visitor(*ty, stack)))
-                    .collect();
-
-                format!(
-                    "{}::{} {{ {} }}",
-                    enum_name,
-                    enum_variant.name(),
-                    fields.join(", ")
-                )
-            }
-            Type::Function(function_type) => {
-                let params: Vec<String> = function_type
-                    .parameter_types()
-                    .iter()
-                    .map(|&ty| self.type_name_visitor(ty, stack))
-                    .collect();
-
-                let ret = self.type_name_visitor(function_type.return_type(), stack);
-
-                format!("fun({}) -> {}", params.join(", "), ret)
-            }
-            Type::Alias(..) => unreachable!(),
-        };
-
-        stack.pop().unwrap();
-
-        name
+        type_name(self.db, &self.scope_stack, ty)
     }
 
     fn type_check(&mut self, from: TypeId, to: TypeId, range: TextRange) {
@@ -1771,6 +5077,34 @@ impl<'a> Lowerer<'a> {
         }
     }
 
+    /// Like `type_check`, but an `Int` can be used as `Bytes` and bytes as an `Int`, with a
+    /// warning since the conversion is implicit.
+    fn coercion_check(&mut self, from: TypeId, to: TypeId, range: TextRange) {
+        if self.is_assignable_to(from, to, false, &mut HashSet::new()) {
+            return;
+        }
+
+        let is_int = |lowerer: &Self, ty| {
+            lowerer.is_assignable_to(ty, lowerer.int_type, false, &mut HashSet::new())
+        };
+        let is_bytes = |lowerer: &Self, ty| {
+            lowerer.is_assignable_to(ty, lowerer.bytes_type, false, &mut HashSet::new())
+        };
+
+        if (is_int(self, from) && is_bytes(self, to)) || (is_bytes(self, from) && is_int(self, to))
+        {
+            self.warning(
+                DiagnosticInfo::ImplicitConversion {
+                    from: self.type_name(from),
+                    to: self.type_name(to),
+                },
+                range,
+            );
+        } else {
+            self.type_check(from, to, range);
+        }
+    }
+
     fn cast_check(&mut self, from: TypeId, to: TypeId, range: TextRange) {
         if !self.is_assignable_to(from, to, true, &mut HashSet::new()) {
             self.error(
@@ -1806,18 +5140,41 @@ impl<'a> Lowerer<'a> {
             (Type::Bool, Type::Bool) => true,
             (Type::Bytes, Type::Bytes) => true,
             (Type::Bytes32, Type::Bytes32 | Type::Bytes) => true,
+            (Type::PublicKey, Type::PublicKey | Type::Bytes) => true,
+            (Type::Signature, Type::Signature | Type::Bytes) => true,
             (_, Type::Any) => true,
 
             // Primitive casts.
             (Type::Nil, Type::Bytes | Type::Bool | Type::Int) if cast => true,
             (Type::Int, Type::Bytes) if cast => true,
             (Type::Bytes | Type::Bytes32, Type::Int) if cast => true,
+            (Type::Bytes, Type::Bytes32 | Type::PublicKey | Type::Signature) if cast => true,
             (Type::Bool, Type::Int | Type::Bytes) if cast => true,
             (Type::Any, _) if cast => true,
 
             // List types with compatible items are also assignable.
             (Type::List(a), Type::List(b)) => self.is_assignable_to(a, b, cast, visited),
 
+            // Nil is the empty list, so it's a list of any item type.
+            (Type::Nil, Type::List(..) | Type::Array(_, 0)) => true,
+
+            // Fixed size lists are lists, and can only be assigned to others of the same size.
+            (Type::Array(a, _), Type::List(b)) => self.is_assignable_to(a, b, cast, visited),
+            (Type::Array(a, a_len), Type::Array(b, b_len)) => {
+                a_len == b_len && self.is_assignable_to(a, b, cast, visited)
+            }
+            (Type::List(a), Type::Array(b, _)) if cast => {
+                self.is_assignable_to(a, b, cast, visited)
+            }
+
+            // A value is only a union once it's tagged, so members aren't assignable to it.
+            (Type::Union(a), Type::Union(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(&a, &b)| self.is_assignable_to(a, b, cast, visited))
+            }
+
             (Type::Pair(a_left, a_right), Type::Pair(b_left, b_right)) => {
                 self.is_assignable_to(a_left, b_left, cast, visited)
                     && self.is_assignable_to(a_right, b_right, cast, visited)
@@ -1876,6 +5233,8 @@ impl<'a> Lowerer<'a> {
             Type::Bool => matches!(b, Type::Bool),
             Type::Bytes => matches!(b, Type::Bytes),
             Type::Bytes32 => matches!(b, Type::Bytes32),
+            Type::PublicKey => matches!(b, Type::PublicKey),
+            Type::Signature => matches!(b, Type::Signature),
             Type::Enum(..) | Type::EnumVariant(..) | Type::Struct(..) => a_id == b_id,
             Type::List(inner) => {
                 if let Type::List(other_inner) = b {
@@ -1884,6 +5243,24 @@ impl<'a> Lowerer<'a> {
                     false
                 }
             }
+            Type::Union(members) => {
+                if let Type::Union(other_members) = b {
+                    members.len() == other_members.len()
+                        && members
+                            .iter()
+                            .zip(other_members.iter())
+                            .all(|(&a, &b)| self.types_equal_visitor(a, b, visited))
+                } else {
+                    false
+                }
+            }
+            Type::Array(inner, len) => {
+                if let Type::Array(other_inner, other_len) = b {
+                    len == other_len && self.types_equal_visitor(inner, other_inner, visited)
+                } else {
+                    false
+                }
+            }
             Type::Pair(left, right) => {
                 if let Type::Pair(other_left, other_right) = b {
                     self.types_equal_visitor(left, other_left, visited)
@@ -1943,6 +5320,29 @@ impl<'a> Lowerer<'a> {
             .scope_mut(self.scope_stack.last().copied().expect("no scope found"))
     }
 
+    /// Evaluates a value at compile time, if it's constant. A value which is nested too deeply to
+    /// evaluate is reported, and is otherwise treated as if it weren't constant.
+    fn const_value(&mut self, hir_id: HirId, range: TextRange) -> Option<Vec<u8>> {
+        let result = eval_hir(
+            self.db,
+            self.scope_stack[0],
+            self.options.max_depth(),
+            hir_id,
+        );
+        self.check_depth(result, range)
+    }
+
+    fn check_depth<T>(
+        &mut self,
+        result: Result<Option<T>, TooDeep>,
+        range: TextRange,
+    ) -> Option<T> {
+        result.unwrap_or_else(|TooDeep| {
+            self.error(DiagnosticInfo::EvalTooDeep, range);
+            None
+        })
+    }
+
     fn error(&mut self, info: DiagnosticInfo, range: TextRange) {
         self.diagnostics.push(Diagnostic::new(
             DiagnosticKind::Error,
@@ -1960,7 +5360,573 @@ impl<'a> Lowerer<'a> {
     }
 }
 
-fn bigint_to_bytes(num: BigInt) -> Vec<u8> {
+/// The message of an item's `@deprecated("...")` attribute, if it has one.
+fn deprecation(item: &Item) -> Option<String> {
+    item.attributes()
+        .into_iter()
+        .filter(|attribute| {
+            attribute
+                .name()
+                .is_some_and(|name| name.text() == "deprecated")
+        })
+        .find_map(|attribute| attribute.args().first().cloned())
+        .map(|message| unquote(message.text()))
+}
+
+/// Removes the surrounding quotes from a string literal, which may be unterminated. A backslash
+/// at the end of a line continues the string on the next line, without the line break.
+fn unquote(text: &str) -> String {
+    let quote = text.chars().next().unwrap();
+    let after_prefix = &text[1..];
+    after_prefix
+        .strip_suffix(quote)
+        .unwrap_or(after_prefix)
+        .replace("\\\r\n", "")
+        .replace("\\\n", "")
+}
+
+/// Splits an integer literal into its digits and type suffix, such as `i` for `Int` or `b` for `Bytes`.
+fn split_int_suffix(text: &str) -> (&str, &str) {
+    // The suffix starts after the last digit, rather than at the first letter, since `b` is a hex
+    // digit. Without any binary digits, `0b` is zero with the `b` suffix.
+    let (start, is_digit): (usize, fn(char) -> bool) = match text.get(..2) {
+        Some("0x") => (2, |c| c.is_ascii_hexdigit() || c == '_'),
+        Some("0b") if text[2..].starts_with(['0', '1', '_']) => {
+            (2, |c| matches!(c, '0' | '1' | '_'))
+        }
+        _ => (0, |c| c.is_ascii_digit() || c == '_'),
+    };
+
+    let index = text[start..]
+        .find(|c: char| !is_digit(c))
+        .map_or(text.len(), |index| start + index);
+    text.split_at(index)
+}
+
+/// The digits of an integer literal without separators, converting binary and hex literals to
+/// decimal. Hex digits are unsigned, so `0xffi` is 255 rather than -1.
+fn decimal_digits(digits: &str) -> String {
+    let digits = digits.replace('_', "");
+
+    let (radix, rest) = match (digits.strip_prefix("0b"), digits.strip_prefix("0x")) {
+        (Some(binary), _) => (2, binary),
+        (_, Some(hex)) => (16, hex),
+        _ => return digits,
+    };
+
+    BigInt::parse_bytes(rest.as_bytes(), radix)
+        .expect("binary and hex literals are validated by the lexer")
+        .to_string()
+}
+
+/// Builds an expression which limits the value to the range from `min` to `max`, inclusive.
+fn clamp(db: &mut Database, value: HirId, min: HirId, max: HirId) -> HirId {
+    let below_min = db.alloc_hir(Hir::BinaryOp {
+        op: HirBinaryOp::LessThan,
+        lhs: value,
+        rhs: min,
+    });
+    let above_max = db.alloc_hir(Hir::BinaryOp {
+        op: HirBinaryOp::GreaterThan,
+        lhs: value,
+        rhs: max,
+    });
+    let at_most_max = db.alloc_hir(Hir::If {
+        condition: above_max,
+        then_block: max,
+        else_block: value,
+    });
+    db.alloc_hir(Hir::If {
+        condition: below_min,
+        then_block: min,
+        else_block: at_most_max,
+    })
+}
+
+/// Builds a function which returns a condition list, starting with the opcode and followed by
+/// each of its parameters in order.
+fn condition_constructor(
+    db: &mut Database,
+    opcode: u8,
+    params: &[(&str, TypeId)],
+    condition_type: TypeId,
+    nil_hir: HirId,
+) -> SymbolId {
+    let mut scope = Scope::default();
+    let mut param_types = Vec::new();
+    let mut param_refs = Vec::new();
+
+    for &(name, type_id) in params {
+        let param = db.alloc_symbol(Symbol::Parameter { type_id });
+        scope.define_symbol(name.to_string(), param);
+        param_types.push(type_id);
+        param_refs.push(db.alloc_hir(Hir::Reference(param)));
+    }
+
+    let mut hir_id = nil_hir;
+
+    for param_ref in param_refs.into_iter().rev() {
+        hir_id = db.alloc_hir(Hir::Pair(param_ref, hir_id));
+    }
+
+    let opcode = db.alloc_hir(Hir::Atom(vec![opcode]));
+    let hir_id = db.alloc_hir(Hir::Pair(opcode, hir_id));
+    let scope_id = db.alloc_scope(scope);
+
+    db.alloc_symbol(Symbol::Function {
+        scope_id,
+        hir_id,
+        ty: FunctionType::new(param_types, condition_type, false),
+    })
+}
+
+/// Builds a function with the given parameters, whose body is given the function itself and a
+/// reference to each parameter, so that it can call itself.
+fn recursive_function<const N: usize>(
+    db: &mut Database,
+    params: [(&str, TypeId); N],
+    return_type: TypeId,
+    nil_hir: HirId,
+    body: impl FnOnce(&mut Database, SymbolId, [HirId; N]) -> HirId,
+) -> SymbolId {
+    let mut scope = Scope::default();
+    let mut param_types = Vec::new();
+
+    let param_refs = params.map(|(name, type_id)| {
+        let param = db.alloc_symbol(Symbol::Parameter { type_id });
+        scope.define_symbol(name.to_string(), param);
+        param_types.push(type_id);
+        db.alloc_hir(Hir::Reference(param))
+    });
+
+    let scope_id = db.alloc_scope(scope);
+    let ty = FunctionType::new(param_types, return_type, false);
+
+    // The function calls itself, so it's allocated before its body.
+    let symbol_id = db.alloc_symbol(Symbol::Function {
+        scope_id,
+        hir_id: nil_hir,
+        ty: ty.clone(),
+    });
+
+    let hir_id = body(db, symbol_id, param_refs);
+
+    *db.symbol_mut(symbol_id) = Symbol::Function {
+        scope_id,
+        hir_id,
+        ty,
+    };
+
+    symbol_id
+}
+
+/// Calls a function with the arguments in order.
+fn call_function(db: &mut Database, symbol_id: SymbolId, args: &[HirId], nil_hir: HirId) -> HirId {
+    let callee = db.alloc_hir(Hir::Reference(symbol_id));
+    let args = args
+        .iter()
+        .rev()
+        .fold(nil_hir, |rest, &arg| db.alloc_hir(Hir::Pair(arg, rest)));
+    db.alloc_hir(Hir::FunctionCall { callee, args })
+}
+
+/// Builds a function which checks whether two values have the same structure, with each pair
+/// of atoms in the same position being equal.
+fn structural_equals(
+    db: &mut Database,
+    any_type: TypeId,
+    bool_type: TypeId,
+    nil_hir: HirId,
+) -> SymbolId {
+    let params = [("lhs", any_type), ("rhs", any_type)];
+
+    recursive_function(
+        db,
+        params,
+        bool_type,
+        nil_hir,
+        |db, symbol_id, [lhs, rhs]| {
+            let lhs_is_cons = db.alloc_hir(Hir::IsCons(lhs));
+            let rhs_is_cons = db.alloc_hir(Hir::IsCons(rhs));
+
+            let lhs_first = db.alloc_hir(Hir::First(lhs));
+            let rhs_first = db.alloc_hir(Hir::First(rhs));
+            let firsts_equal = call_function(db, symbol_id, &[lhs_first, rhs_first], nil_hir);
+
+            let lhs_rest = db.alloc_hir(Hir::Rest(lhs));
+            let rhs_rest = db.alloc_hir(Hir::Rest(rhs));
+            let rests_equal = call_function(db, symbol_id, &[lhs_rest, rhs_rest], nil_hir);
+
+            let pairs_equal = db.alloc_hir(Hir::If {
+                condition: firsts_equal,
+                then_block: rests_equal,
+                else_block: nil_hir,
+            });
+            let both_pairs = db.alloc_hir(Hir::If {
+                condition: rhs_is_cons,
+                then_block: pairs_equal,
+                else_block: nil_hir,
+            });
+
+            let atoms_equal = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Equals,
+                lhs,
+                rhs,
+            });
+            let both_atoms = db.alloc_hir(Hir::If {
+                condition: rhs_is_cons,
+                then_block: nil_hir,
+                else_block: atoms_equal,
+            });
+
+            db.alloc_hir(Hir::If {
+                condition: lhs_is_cons,
+                then_block: both_pairs,
+                else_block: both_atoms,
+            })
+        },
+    )
+}
+
+/// Whether an item is equal to the value, which is compared with the structural equality function
+/// if there is one, and with `=` otherwise.
+fn item_equals(
+    db: &mut Database,
+    equals: Option<SymbolId>,
+    item: HirId,
+    value: HirId,
+    nil_hir: HirId,
+) -> HirId {
+    match equals {
+        Some(equals) => call_function(db, equals, &[item, value], nil_hir),
+        None => db.alloc_hir(Hir::BinaryOp {
+            op: HirBinaryOp::Equals,
+            lhs: item,
+            rhs: value,
+        }),
+    }
+}
+
+/// Builds a function which checks whether any item of a list is equal to the value.
+fn list_contains(
+    db: &mut Database,
+    any_type: TypeId,
+    bool_type: TypeId,
+    equals: Option<SymbolId>,
+    nil_hir: HirId,
+) -> SymbolId {
+    let params = [("list", any_type), ("value", any_type)];
+
+    recursive_function(
+        db,
+        params,
+        bool_type,
+        nil_hir,
+        |db, symbol_id, [list, value]| {
+            let is_cons = db.alloc_hir(Hir::IsCons(list));
+            let first = db.alloc_hir(Hir::First(list));
+            let rest = db.alloc_hir(Hir::Rest(list));
+
+            let found = item_equals(db, equals, first, value, nil_hir);
+            let recurse = call_function(db, symbol_id, &[rest, value], nil_hir);
+
+            let true_hir = db.alloc_hir(Hir::Atom(vec![1]));
+            let search = db.alloc_hir(Hir::If {
+                condition: found,
+                then_block: true_hir,
+                else_block: recurse,
+            });
+
+            db.alloc_hir(Hir::If {
+                condition: is_cons,
+                then_block: search,
+                else_block: nil_hir,
+            })
+        },
+    )
+}
+
+/// Builds a function which finds the index of the first item of a list that is equal to the
+/// value, starting from the given index. The result is a list containing only the index if there
+/// is one, and nil otherwise.
+fn list_index_of(
+    db: &mut Database,
+    any_type: TypeId,
+    int_type: TypeId,
+    equals: Option<SymbolId>,
+    nil_hir: HirId,
+) -> SymbolId {
+    let params = [("list", any_type), ("value", any_type), ("index", int_type)];
+    let list_type = db.alloc_type(Type::List(int_type));
+
+    recursive_function(
+        db,
+        params,
+        list_type,
+        nil_hir,
+        |db, symbol_id, [list, value, index]| {
+            let is_cons = db.alloc_hir(Hir::IsCons(list));
+            let first = db.alloc_hir(Hir::First(list));
+            let rest = db.alloc_hir(Hir::Rest(list));
+
+            let found = item_equals(db, equals, first, value, nil_hir);
+            let some_index = db.alloc_hir(Hir::Pair(index, nil_hir));
+
+            let one = db.alloc_hir(Hir::Atom(vec![1]));
+            let next_index = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Add,
+                lhs: index,
+                rhs: one,
+            });
+            let recurse = call_function(db, symbol_id, &[rest, value, next_index], nil_hir);
+
+            let search = db.alloc_hir(Hir::If {
+                condition: found,
+                then_block: some_index,
+                else_block: recurse,
+            });
+
+            db.alloc_hir(Hir::If {
+                condition: is_cons,
+                then_block: search,
+                else_block: nil_hir,
+            })
+        },
+    )
+}
+
+/// Builds a function which is a list of only the first item of a list, or nil if it's empty.
+fn list_head(db: &mut Database, any_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("list", any_type)];
+
+    recursive_function(db, params, any_type, nil_hir, |db, _, [list]| {
+        let is_cons = db.alloc_hir(Hir::IsCons(list));
+        let first = db.alloc_hir(Hir::First(list));
+        let head = db.alloc_hir(Hir::Pair(first, nil_hir));
+
+        db.alloc_hir(Hir::If {
+            condition: is_cons,
+            then_block: head,
+            else_block: nil_hir,
+        })
+    })
+}
+
+/// Builds a function which is every item of a list after the first, or nil if it's empty.
+fn list_tail(db: &mut Database, any_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("list", any_type)];
+
+    recursive_function(db, params, any_type, nil_hir, |db, _, [list]| {
+        let is_cons = db.alloc_hir(Hir::IsCons(list));
+        let rest = db.alloc_hir(Hir::Rest(list));
+
+        db.alloc_hir(Hir::If {
+            condition: is_cons,
+            then_block: rest,
+            else_block: nil_hir,
+        })
+    })
+}
+
+/// Builds a function which is a list of only the last item of a list, or nil if it's empty.
+fn list_last(db: &mut Database, any_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("list", any_type)];
+
+    recursive_function(db, params, any_type, nil_hir, |db, symbol_id, [list]| {
+        let is_cons = db.alloc_hir(Hir::IsCons(list));
+        let first = db.alloc_hir(Hir::First(list));
+        let rest = db.alloc_hir(Hir::Rest(list));
+        let rest_is_cons = db.alloc_hir(Hir::IsCons(rest));
+        let last = db.alloc_hir(Hir::Pair(first, nil_hir));
+        let recurse = call_function(db, symbol_id, &[rest], nil_hir);
+
+        let non_empty = db.alloc_hir(Hir::If {
+            condition: rest_is_cons,
+            then_block: recurse,
+            else_block: last,
+        });
+
+        db.alloc_hir(Hir::If {
+            condition: is_cons,
+            then_block: non_empty,
+            else_block: nil_hir,
+        })
+    })
+}
+
+/// Builds a function which moves each item of a list onto the front of the result, which is the
+/// items in reverse order once the list is empty.
+fn list_reverse(db: &mut Database, any_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("list", any_type), ("result", any_type)];
+
+    recursive_function(
+        db,
+        params,
+        any_type,
+        nil_hir,
+        |db, symbol_id, [list, result]| {
+            let is_cons = db.alloc_hir(Hir::IsCons(list));
+            let first = db.alloc_hir(Hir::First(list));
+            let rest = db.alloc_hir(Hir::Rest(list));
+            let next_result = db.alloc_hir(Hir::Pair(first, result));
+            let recurse = call_function(db, symbol_id, &[rest, next_result], nil_hir);
+
+            db.alloc_hir(Hir::If {
+                condition: is_cons,
+                then_block: recurse,
+                else_block: result,
+            })
+        },
+    )
+}
+
+/// Builds a function which removes items from the front of a list until the count reaches zero,
+/// stopping early at the end of the list, which is nil. A negative count is out of range too, so
+/// it's also nil rather than the whole list.
+fn list_skip(db: &mut Database, any_type: TypeId, int_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("list", any_type), ("count", int_type)];
+
+    recursive_function(
+        db,
+        params,
+        any_type,
+        nil_hir,
+        |db, symbol_id, [list, count]| {
+            let is_cons = db.alloc_hir(Hir::IsCons(list));
+            let rest = db.alloc_hir(Hir::Rest(list));
+
+            let zero = db.alloc_hir(Hir::Atom(Vec::new()));
+            let is_positive = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::GreaterThan,
+                lhs: count,
+                rhs: zero,
+            });
+
+            let one = db.alloc_hir(Hir::Atom(vec![1]));
+            let next_count = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Subtract,
+                lhs: count,
+                rhs: one,
+            });
+            let recurse = call_function(db, symbol_id, &[rest, next_count], nil_hir);
+
+            let skip = db.alloc_hir(Hir::If {
+                condition: is_cons,
+                then_block: recurse,
+                else_block: nil_hir,
+            });
+
+            let is_negative = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::LessThan,
+                lhs: count,
+                rhs: zero,
+            });
+            let not_positive = db.alloc_hir(Hir::If {
+                condition: is_negative,
+                then_block: nil_hir,
+                else_block: list,
+            });
+
+            db.alloc_hir(Hir::If {
+                condition: is_positive,
+                then_block: skip,
+                else_block: not_positive,
+            })
+        },
+    )
+}
+
+/// Builds a function which pairs up the items of two lists in order, stopping at the end of the
+/// shorter one.
+fn list_zip(db: &mut Database, any_type: TypeId, nil_hir: HirId) -> SymbolId {
+    let params = [("a", any_type), ("b", any_type)];
+
+    recursive_function(db, params, any_type, nil_hir, |db, symbol_id, [a, b]| {
+        let a_is_cons = db.alloc_hir(Hir::IsCons(a));
+        let b_is_cons = db.alloc_hir(Hir::IsCons(b));
+        let a_first = db.alloc_hir(Hir::First(a));
+        let b_first = db.alloc_hir(Hir::First(b));
+        let a_rest = db.alloc_hir(Hir::Rest(a));
+        let b_rest = db.alloc_hir(Hir::Rest(b));
+        let recurse = call_function(db, symbol_id, &[a_rest, b_rest], nil_hir);
+
+        let item = db.alloc_hir(Hir::Pair(a_first, b_first));
+        let items = db.alloc_hir(Hir::Pair(item, recurse));
+
+        let both_cons = db.alloc_hir(Hir::If {
+            condition: b_is_cons,
+            then_block: items,
+            else_block: nil_hir,
+        });
+
+        db.alloc_hir(Hir::If {
+            condition: a_is_cons,
+            then_block: both_cons,
+            else_block: nil_hir,
+        })
+    })
+}
+
+/// Builds a function which pairs each item of a list with its index, starting from the given
+/// index.
+fn list_enumerate(
+    db: &mut Database,
+    any_type: TypeId,
+    int_type: TypeId,
+    nil_hir: HirId,
+) -> SymbolId {
+    let params = [("list", any_type), ("index", int_type)];
+
+    recursive_function(
+        db,
+        params,
+        any_type,
+        nil_hir,
+        |db, symbol_id, [list, index]| {
+            let is_cons = db.alloc_hir(Hir::IsCons(list));
+            let first = db.alloc_hir(Hir::First(list));
+            let rest = db.alloc_hir(Hir::Rest(list));
+
+            let one = db.alloc_hir(Hir::Atom(vec![1]));
+            let next_index = db.alloc_hir(Hir::BinaryOp {
+                op: HirBinaryOp::Add,
+                lhs: index,
+                rhs: one,
+            });
+            let recurse = call_function(db, symbol_id, &[rest, next_index], nil_hir);
+
+            let item = db.alloc_hir(Hir::Pair(index, first));
+            let items = db.alloc_hir(Hir::Pair(item, recurse));
+
+            db.alloc_hir(Hir::If {
+                condition: is_cons,
+                then_block: items,
+                else_block: nil_hir,
+            })
+        },
+    )
+}
+
+/// The Levenshtein distance between two names, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+pub(crate) fn bigint_to_bytes(num: BigInt) -> Vec<u8> {
     let bytes: Vec<u8> = num.to_signed_bytes_be();
     let mut slice = bytes.as_slice();
     // make number minimal by removing leading zeros
@@ -1,45 +1,89 @@
-use std::collections::HashMap;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    mem,
+};
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
 
 use crate::{
+    const_eval::eval_const,
     database::{Database, HirId, LirId, ScopeId, SymbolId},
     hir::{Hir, HirBinaryOp},
     lir::Lir,
+    lowerer::bigint_to_bytes,
     symbol::Symbol,
+    CompilerOptions,
 };
 
+/// The fewest arms an `if` chain needs to be lowered to a jump table.
+pub(crate) const MIN_JUMP_TABLE_ARMS: usize = 4;
+
+/// The most branches a jump table can have, including those filled in for gaps between values.
+pub(crate) const MAX_JUMP_TABLE_SIZE: usize = 64;
+
+/// An `if` chain where each condition compares the same symbol to an integer.
+struct DispatchChain {
+    symbol_id: SymbolId,
+    /// The value and branch of each arm, in the order they're written.
+    arms: Vec<(BigInt, HirId)>,
+    /// The branch which the chain falls through to.
+    default: HirId,
+}
+
+/// The collections are all ordered, so that the output doesn't depend on how symbols are hashed.
 pub struct Optimizer<'a> {
     db: &'a mut Database,
-    captures: HashMap<ScopeId, IndexSet<SymbolId>>,
-    environments: HashMap<ScopeId, IndexSet<SymbolId>>,
-    varargs: HashMap<ScopeId, bool>,
-    scope_inheritance: HashMap<ScopeId, ScopeId>,
+    options: &'a CompilerOptions,
+    captures: IndexMap<ScopeId, IndexSet<SymbolId>>,
+    environments: IndexMap<ScopeId, IndexSet<SymbolId>>,
+    varargs: IndexMap<ScopeId, bool>,
+    scope_inheritance: IndexMap<ScopeId, ScopeId>,
+    function_definitions: IndexMap<SymbolId, LirId>,
+    /// The key of each function's definition in the database's cache.
+    definition_keys: IndexMap<SymbolId, u64>,
 }
 
 impl<'a> Optimizer<'a> {
-    pub fn new(db: &'a mut Database) -> Self {
+    pub fn new(db: &'a mut Database, options: &'a CompilerOptions) -> Self {
         Self {
             db,
-            captures: HashMap::new(),
-            environments: HashMap::new(),
-            varargs: HashMap::new(),
-            scope_inheritance: HashMap::new(),
+            options,
+            captures: IndexMap::new(),
+            environments: IndexMap::new(),
+            varargs: IndexMap::new(),
+            scope_inheritance: IndexMap::new(),
+            function_definitions: IndexMap::new(),
+            definition_keys: IndexMap::new(),
         }
     }
 
+    /// The symbols which each function captures from outside of it, by the scope of the function.
+    pub fn into_captures(self) -> IndexMap<ScopeId, IndexSet<SymbolId>> {
+        self.captures
+    }
+
     fn compute_captures_entrypoint(&mut self, scope_id: ScopeId, hir_id: HirId) {
         if self.captures.contains_key(&scope_id) {
             return;
         }
         self.captures.insert(scope_id, IndexSet::new());
         self.compute_captures_hir(scope_id, hir_id);
+
+        // Every function defined in the scope is curried into its environment, even if it's never
+        // referenced, so its captures are needed as well.
+        for symbol_id in self.db.scope(scope_id).local_symbols() {
+            if matches!(self.db.symbol(symbol_id), Symbol::Function { .. }) {
+                self.compute_reference_captures(scope_id, symbol_id);
+            }
+        }
     }
 
     fn compute_captures_hir(&mut self, scope_id: ScopeId, hir_id: HirId) {
         match self.db.hir(hir_id).clone() {
             Hir::Unknown => unreachable!(),
-            Hir::Atom(_) => {}
+            Hir::Atom(_) | Hir::Env => {}
             Hir::Reference(symbol_id) => self.compute_reference_captures(scope_id, symbol_id),
             Hir::Scope {
                 scope_id: new_scope_id,
@@ -62,8 +106,23 @@ impl<'a> Optimizer<'a> {
             | Hir::Rest(value)
             | Hir::Not(value)
             | Hir::Sha256(value)
+            | Hir::Keccak256(value)
             | Hir::IsCons(value)
             | Hir::Strlen(value) => self.compute_captures_hir(scope_id, value),
+            Hir::Substr { value, start, end } => {
+                self.compute_captures_hir(scope_id, value);
+                self.compute_captures_hir(scope_id, start);
+                self.compute_captures_hir(scope_id, end);
+            }
+            Hir::Softfork { cost, program } => {
+                self.compute_captures_hir(scope_id, cost);
+                self.compute_captures_hir(scope_id, program);
+            }
+            Hir::Op { args, .. } => {
+                for arg in args {
+                    self.compute_captures_hir(scope_id, arg);
+                }
+            }
             Hir::If {
                 condition,
                 then_block,
@@ -73,6 +132,12 @@ impl<'a> Optimizer<'a> {
                 self.compute_captures_hir(scope_id, then_block);
                 self.compute_captures_hir(scope_id, else_block);
             }
+            Hir::Dispatch { index, branches } => {
+                self.compute_captures_hir(scope_id, index);
+                for branch in branches {
+                    self.compute_captures_hir(scope_id, branch);
+                }
+            }
             Hir::Pair(first, rest) => {
                 self.compute_captures_hir(scope_id, first);
                 self.compute_captures_hir(scope_id, rest);
@@ -96,7 +161,7 @@ impl<'a> Optimizer<'a> {
                 ..
             } => self.compute_function_captures(scope_id, function_scope_id, hir_id, ty.varargs()),
             Symbol::Parameter { .. } => {}
-            Symbol::LetBinding { hir_id, .. } => self.compute_captures_hir(scope_id, hir_id),
+            Symbol::LetBinding { .. } => {}
             Symbol::ConstBinding { hir_id, .. } => self.compute_captures_hir(scope_id, hir_id),
         }
     }
@@ -121,6 +186,18 @@ impl<'a> Optimizer<'a> {
             .unwrap()
             .extend(new_captures);
 
+        let env = self.function_environment(function_scope_id);
+        self.environments.insert(function_scope_id, env);
+
+        if varargs {
+            self.varargs.insert(function_scope_id, true);
+        }
+    }
+
+    /// The layout of a function's environment, which `opt_path` indexes into. Its definitions
+    /// come first, then its captures, then its parameters. The definitions are curried into its
+    /// body, and closures and calls pass the captures after them in the order of `captures`.
+    fn function_environment(&self, function_scope_id: ScopeId) -> IndexSet<SymbolId> {
         let mut env = IndexSet::new();
 
         for symbol_id in self.db.scope(function_scope_id).local_symbols() {
@@ -129,7 +206,7 @@ impl<'a> Optimizer<'a> {
             }
         }
 
-        for symbol_id in self.captures[&function_scope_id].clone() {
+        for &symbol_id in &self.captures[&function_scope_id] {
             env.insert(symbol_id);
         }
 
@@ -139,14 +216,17 @@ impl<'a> Optimizer<'a> {
             }
         }
 
-        self.environments.insert(function_scope_id, env);
-
-        if varargs {
-            self.varargs.insert(function_scope_id, true);
-        }
+        env
     }
 
     fn compute_scope_captures(&mut self, scope_id: ScopeId, new_scope_id: ScopeId, value: HirId) {
+        // Bindings are evaluated in the enclosing scope, rather than wherever they are referenced.
+        for symbol_id in self.db.scope(new_scope_id).local_symbols() {
+            if let Symbol::LetBinding { hir_id, .. } = self.db.symbol(symbol_id).clone() {
+                self.compute_captures_hir(scope_id, hir_id);
+            }
+        }
+
         self.compute_captures_entrypoint(new_scope_id, value);
 
         let new_captures: Vec<SymbolId> = self.captures[&new_scope_id]
@@ -173,7 +253,9 @@ impl<'a> Optimizer<'a> {
 
     pub fn opt_main(&mut self, main: SymbolId) -> LirId {
         let Symbol::Function {
-            scope_id, hir_id, ..
+            scope_id,
+            hir_id,
+            ty,
         } = self.db.symbol(main).clone()
         else {
             unreachable!();
@@ -181,24 +263,13 @@ impl<'a> Optimizer<'a> {
 
         self.compute_captures_entrypoint(scope_id, hir_id);
 
-        let mut env = IndexSet::new();
-
-        for symbol_id in self.db.scope(scope_id).local_symbols() {
-            if self.db.symbol(symbol_id).is_definition() {
-                env.insert(symbol_id);
-            }
-        }
-
-        for symbol_id in self.captures[&scope_id].clone() {
-            env.insert(symbol_id);
-        }
-
-        for symbol_id in self.db.scope(scope_id).local_symbols() {
-            if self.db.symbol(symbol_id).is_parameter() {
-                env.insert(symbol_id);
-            }
+        // The solution is laid out like the arguments of a Chialisp `mod`, so a spread parameter
+        // is the rest of the solution rather than a single value.
+        if ty.varargs() {
+            self.varargs.insert(scope_id, true);
         }
 
+        let env = self.function_environment(scope_id);
         self.environments.insert(scope_id, env);
 
         let body = self.opt_hir(scope_id, hir_id);
@@ -215,7 +286,22 @@ impl<'a> Optimizer<'a> {
             args.push(self.opt_definition(scope_id, symbol_id));
         }
 
-        self.db.alloc_lir(Lir::Curry(body, args))
+        let program = self.db.alloc_lir(Lir::Curry(body, args));
+
+        if self.options.curried_values().is_empty() {
+            return program;
+        }
+
+        // Values curried into the entrypoint are applied in the standard curried form, so the
+        // result is the same program that currying it with other Chia tools would produce.
+        let values = self
+            .options
+            .curried_values()
+            .iter()
+            .map(|value| self.db.alloc_lir(Lir::Atom(value.to_atom())))
+            .collect();
+
+        self.db.alloc_lir(Lir::Curry(program, values))
     }
 
     fn opt_scope(&mut self, parent_scope_id: ScopeId, scope_id: ScopeId, hir_id: HirId) -> LirId {
@@ -229,6 +315,12 @@ impl<'a> Optimizer<'a> {
     }
 
     fn opt_path(&mut self, scope_id: ScopeId, symbol_id: SymbolId) -> LirId {
+        let path = self.path(scope_id, symbol_id);
+        self.db.alloc_lir(Lir::Path(path))
+    }
+
+    /// The path to a symbol in the environment of a scope.
+    fn path(&self, scope_id: ScopeId, symbol_id: SymbolId) -> u32 {
         let mut environment = self.environments[&scope_id].clone();
 
         let mut current_scope_id = scope_id;
@@ -249,16 +341,52 @@ impl<'a> Optimizer<'a> {
             path += 1;
         }
 
-        if index + 1 == environment.len() && self.varargs.get(&scope_id).copied().unwrap_or(false) {
-            // Undo last index
-            path -= 1;
-            path /= 2;
+        // The last symbol belongs to the outermost scope, which is the function that may have a
+        // spread parameter.
+        if index + 1 == environment.len()
+            && self
+                .varargs
+                .get(&current_scope_id)
+                .copied()
+                .unwrap_or(false)
+        {
+            // The spread parameter is the rest of the environment after every symbol before it,
+            // rather than the first value of that rest.
+            path = (1 << (index + 1)) - 1;
+        }
+
+        path
+    }
+
+    fn opt_env(&mut self, scope_id: ScopeId) -> LirId {
+        let path = self.env_path(scope_id);
+        self.db.alloc_lir(Lir::Path(path))
+    }
+
+    /// The path to the arguments of the enclosing function, which come after everything that is
+    /// curried into its environment and the environments of any scopes within it.
+    fn env_path(&self, scope_id: ScopeId) -> u32 {
+        let mut environment = self.environments[&scope_id].clone();
+
+        let mut current_scope_id = scope_id;
+
+        while self.scope_inheritance.contains_key(&current_scope_id) {
+            current_scope_id = self.scope_inheritance[&current_scope_id];
+            environment.extend(&self.environments[&current_scope_id]);
+        }
+
+        let curried = environment
+            .iter()
+            .take_while(|&&symbol_id| !self.db.symbol(symbol_id).is_parameter())
+            .count();
 
-            // Make it the rest
+        let mut path = 1;
+        for _ in 0..curried {
+            path *= 2;
             path += 1;
         }
 
-        self.db.alloc_lir(Lir::Path(path))
+        path
     }
 
     fn opt_definition(&mut self, scope_id: ScopeId, symbol_id: SymbolId) -> LirId {
@@ -268,6 +396,20 @@ impl<'a> Optimizer<'a> {
                 hir_id,
                 ..
             } => {
+                // A function body doesn't depend on where it's referenced from, so it's only built once.
+                if let Some(&lir_id) = self.function_definitions.get(&symbol_id) {
+                    return lir_id;
+                }
+
+                // If an earlier compilation built a definition from exactly the same inputs, it's
+                // reused rather than optimized again.
+                let key = self.definition_key(symbol_id);
+
+                if let Some(lir_id) = self.db.cached_definition(key) {
+                    self.function_definitions.insert(symbol_id, lir_id);
+                    return lir_id;
+                }
+
                 let mut body = self.opt_hir(function_scope_id, hir_id);
                 let mut definitions = Vec::new();
 
@@ -281,7 +423,10 @@ impl<'a> Optimizer<'a> {
                     body = self.db.alloc_lir(Lir::Curry(body, definitions));
                 }
 
-                self.db.alloc_lir(Lir::FunctionBody(body))
+                let lir_id = self.db.alloc_lir(Lir::FunctionBody(body));
+                self.function_definitions.insert(symbol_id, lir_id);
+                self.db.cache_definition(key, lir_id);
+                lir_id
             }
             Symbol::Parameter { .. } => {
                 unreachable!();
@@ -291,7 +436,204 @@ impl<'a> Optimizer<'a> {
         }
     }
 
+    /// A hash of everything that a function's definition is built from, which is its lowered
+    /// body and the definitions nested in it. References are hashed as the paths they're
+    /// optimized to, so a function only depends on the functions it refers to through where
+    /// their captures are in its environment. Definitions with the same key are the same LIR.
+    fn definition_key(&mut self, symbol_id: SymbolId) -> u64 {
+        if let Some(&key) = self.definition_keys.get(&symbol_id) {
+            return key;
+        }
+
+        let Symbol::Function {
+            scope_id: function_scope_id,
+            hir_id,
+            ..
+        } = self.db.symbol(symbol_id).clone()
+        else {
+            unreachable!();
+        };
+
+        let mut state = DefaultHasher::new();
+        self.options.jump_tables().hash(&mut state);
+        self.hash_hir(function_scope_id, hir_id, &mut state);
+
+        let definitions: Vec<SymbolId> = self
+            .db
+            .scope(function_scope_id)
+            .local_symbols()
+            .into_iter()
+            .filter(|&symbol_id| self.db.symbol(symbol_id).is_definition())
+            .collect();
+
+        definitions.len().hash(&mut state);
+
+        for symbol_id in definitions {
+            self.hash_definition(function_scope_id, symbol_id, &mut state);
+        }
+
+        let key = state.finish();
+        self.definition_keys.insert(symbol_id, key);
+        key
+    }
+
+    fn hash_definition(
+        &mut self,
+        scope_id: ScopeId,
+        symbol_id: SymbolId,
+        state: &mut DefaultHasher,
+    ) {
+        match self.db.symbol(symbol_id).clone() {
+            Symbol::Function { .. } => self.definition_key(symbol_id).hash(state),
+            Symbol::LetBinding { hir_id, .. } => self.hash_hir(scope_id, hir_id, state),
+            Symbol::Parameter { .. } | Symbol::ConstBinding { .. } => unreachable!(),
+        }
+    }
+
+    /// Hashes the inputs which `opt_hir` reads to optimize a value, in the same order.
+    fn hash_hir(&mut self, scope_id: ScopeId, hir_id: HirId, state: &mut DefaultHasher) {
+        let hir = self.db.hir(hir_id).clone();
+        mem::discriminant(&hir).hash(state);
+
+        match hir {
+            Hir::Unknown => unreachable!(),
+            Hir::Atom(atom) => atom.hash(state),
+            Hir::Reference(symbol_id) => self.hash_reference(scope_id, symbol_id, state),
+            Hir::Scope {
+                scope_id: new_scope_id,
+                value,
+            } => {
+                self.hash_hir(new_scope_id, value, state);
+
+                let environment = self.environments[&new_scope_id].clone();
+                environment.len().hash(state);
+
+                for symbol_id in environment {
+                    self.hash_definition(scope_id, symbol_id, state);
+                }
+            }
+            Hir::FunctionCall { callee, args } => {
+                self.hash_hir(scope_id, args, state);
+
+                // A direct call passes the captures with the arguments, rather than as a closure.
+                match self.db.hir(callee).clone() {
+                    Hir::Reference(symbol_id)
+                        if matches!(self.db.symbol(symbol_id), Symbol::Function { .. }) =>
+                    {
+                        true.hash(state);
+                        self.hash_reference(scope_id, symbol_id, state);
+                    }
+                    _ => {
+                        false.hash(state);
+                        self.hash_hir(scope_id, callee, state);
+                    }
+                }
+            }
+            Hir::BinaryOp { op, lhs, rhs } => {
+                mem::discriminant(&op).hash(state);
+                self.hash_hir(scope_id, lhs, state);
+                self.hash_hir(scope_id, rhs, state);
+            }
+            Hir::First(value)
+            | Hir::Rest(value)
+            | Hir::Not(value)
+            | Hir::Sha256(value)
+            | Hir::Keccak256(value)
+            | Hir::IsCons(value)
+            | Hir::Strlen(value) => self.hash_hir(scope_id, value, state),
+            Hir::Raise(value) => {
+                value.is_some().hash(state);
+
+                if let Some(value) = value {
+                    self.hash_hir(scope_id, value, state);
+                }
+            }
+            Hir::Substr { value, start, end } => {
+                for hir_id in [value, start, end] {
+                    self.hash_hir(scope_id, hir_id, state);
+                }
+            }
+            Hir::Softfork { cost, program } => {
+                self.hash_hir(scope_id, cost, state);
+                self.hash_hir(scope_id, program, state);
+            }
+            Hir::Op { opcode, args } => {
+                opcode.hash(state);
+                self.hash_hirs(scope_id, &args, state);
+            }
+            Hir::Env => self.env_path(scope_id).hash(state),
+            Hir::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                for hir_id in [condition, then_block, else_block] {
+                    self.hash_hir(scope_id, hir_id, state);
+                }
+            }
+            Hir::Dispatch { index, branches } => {
+                self.hash_hir(scope_id, index, state);
+                self.hash_hirs(scope_id, &branches, state);
+            }
+            Hir::Pair(first, rest) => {
+                self.hash_hir(scope_id, first, state);
+                self.hash_hir(scope_id, rest, state);
+            }
+        }
+    }
+
+    fn hash_hirs(&mut self, scope_id: ScopeId, hir_ids: &[HirId], state: &mut DefaultHasher) {
+        hir_ids.len().hash(state);
+
+        for &hir_id in hir_ids {
+            self.hash_hir(scope_id, hir_id, state);
+        }
+    }
+
+    /// Hashes a reference the same way as `opt_reference` optimizes it.
+    fn hash_reference(
+        &mut self,
+        scope_id: ScopeId,
+        symbol_id: SymbolId,
+        state: &mut DefaultHasher,
+    ) {
+        let symbol = self.db.symbol(symbol_id).clone();
+        mem::discriminant(&symbol).hash(state);
+
+        match symbol {
+            Symbol::Function {
+                scope_id: function_scope_id,
+                ..
+            } => {
+                self.path(scope_id, symbol_id).hash(state);
+
+                let captures = &self.captures[&function_scope_id];
+                captures.len().hash(state);
+
+                for &capture in captures {
+                    self.path(scope_id, capture).hash(state);
+                }
+            }
+            Symbol::ConstBinding { hir_id, .. } => self.hash_hir(scope_id, hir_id, state),
+            _ => self.path(scope_id, symbol_id).hash(state),
+        }
+    }
+
     fn opt_hir(&mut self, scope_id: ScopeId, hir_id: HirId) -> LirId {
+        let lir_id = self.opt_hir_unfolded(scope_id, hir_id);
+
+        // Subtrees which are fully constant are replaced with their value.
+        if matches!(self.db.lir(lir_id), Lir::Atom(..)) {
+            return lir_id;
+        }
+
+        match eval_const(self.db, lir_id) {
+            Some(atom) => self.db.alloc_lir(Lir::Atom(atom)),
+            None => lir_id,
+        }
+    }
+
+    fn opt_hir_unfolded(&mut self, scope_id: ScopeId, hir_id: HirId) -> LirId {
         match self.db.hir(hir_id) {
             Hir::Unknown => unreachable!(),
             Hir::Atom(atom) => self.db.alloc_lir(Lir::Atom(atom.clone())),
@@ -316,6 +658,9 @@ impl<'a> Optimizer<'a> {
                     HirBinaryOp::Equals => Self::opt_eq,
                     HirBinaryOp::NotEquals => Self::opt_neq,
                     HirBinaryOp::Concat => Self::opt_concat,
+                    HirBinaryOp::Divmod => Self::opt_divmod,
+                    HirBinaryOp::ShiftRight => Self::opt_shift_right,
+                    HirBinaryOp::BitwiseAnd => Self::opt_bitwise_and,
                 };
                 handler(self, scope_id, *lhs, *rhs)
             }
@@ -324,13 +669,21 @@ impl<'a> Optimizer<'a> {
             Hir::Not(value) => self.opt_not(scope_id, *value),
             Hir::Raise(value) => self.opt_raise(scope_id, *value),
             Hir::Sha256(value) => self.opt_sha256(scope_id, *value),
+            Hir::Keccak256(value) => self.opt_keccak256(scope_id, *value),
             Hir::IsCons(value) => self.opt_is_cons(scope_id, *value),
             Hir::Strlen(value) => self.opt_strlen(scope_id, *value),
+            Hir::Substr { value, start, end } => self.opt_substr(scope_id, *value, *start, *end),
+            Hir::Softfork { cost, program } => self.opt_softfork(scope_id, *cost, *program),
+            Hir::Op { opcode, args } => self.opt_op(scope_id, opcode.clone(), args.clone()),
+            Hir::Env => self.opt_env(scope_id),
             Hir::If {
                 condition,
                 then_block,
                 else_block,
             } => self.opt_if(scope_id, *condition, *then_block, *else_block),
+            Hir::Dispatch { index, branches } => {
+                self.opt_dispatch(scope_id, *index, branches.clone())
+            }
         }
     }
 
@@ -355,6 +708,11 @@ impl<'a> Optimizer<'a> {
         self.db.alloc_lir(Lir::Sha256(lir_id))
     }
 
+    fn opt_keccak256(&mut self, scope_id: ScopeId, hir_id: HirId) -> LirId {
+        let lir_id = self.opt_hir(scope_id, hir_id);
+        self.db.alloc_lir(Lir::Keccak256(lir_id))
+    }
+
     fn opt_is_cons(&mut self, scope_id: ScopeId, hir_id: HirId) -> LirId {
         let lir_id = self.opt_hir(scope_id, hir_id);
         self.db.alloc_lir(Lir::IsCons(lir_id))
@@ -365,6 +723,27 @@ impl<'a> Optimizer<'a> {
         self.db.alloc_lir(Lir::Strlen(lir_id))
     }
 
+    fn opt_substr(&mut self, scope_id: ScopeId, value: HirId, start: HirId, end: HirId) -> LirId {
+        let value = self.opt_hir(scope_id, value);
+        let start = self.opt_hir(scope_id, start);
+        let end = self.opt_hir(scope_id, end);
+        self.db.alloc_lir(Lir::Substr(value, start, end))
+    }
+
+    fn opt_softfork(&mut self, scope_id: ScopeId, cost: HirId, program: HirId) -> LirId {
+        let cost = self.opt_hir(scope_id, cost);
+        let program = self.opt_hir(scope_id, program);
+        self.db.alloc_lir(Lir::Softfork(cost, program))
+    }
+
+    fn opt_op(&mut self, scope_id: ScopeId, opcode: Vec<u8>, args: Vec<HirId>) -> LirId {
+        let args = args
+            .into_iter()
+            .map(|arg| self.opt_hir(scope_id, arg))
+            .collect();
+        self.db.alloc_lir(Lir::Op(opcode, args))
+    }
+
     fn opt_reference(&mut self, scope_id: ScopeId, symbol_id: SymbolId) -> LirId {
         match self.db.symbol(symbol_id).clone() {
             Symbol::Function {
@@ -373,14 +752,10 @@ impl<'a> Optimizer<'a> {
             } => {
                 let body = self.opt_path(scope_id, symbol_id);
 
+                // The function's own definitions are already curried into its body, so only the
+                // captures are added, the same as for a direct call.
                 let mut captures = Vec::new();
 
-                for symbol_id in self.db.scope(function_scope_id).local_symbols() {
-                    if self.db.symbol(symbol_id).is_definition() {
-                        captures.push(self.opt_path(scope_id, symbol_id));
-                    }
-                }
-
                 for symbol_id in self.captures[&function_scope_id].clone() {
                     captures.push(self.opt_path(scope_id, symbol_id));
                 }
@@ -401,7 +776,7 @@ impl<'a> Optimizer<'a> {
                 ..
             } = self.db.symbol(symbol_id)
             {
-                for symbol_id in self.captures[&callee_scope_id].clone().into_iter().rev() {
+                for symbol_id in self.captures[callee_scope_id].clone().into_iter().rev() {
                     let capture = self.opt_path(scope_id, symbol_id);
                     args = self.db.alloc_lir(Lir::Pair(capture, args));
                 }
@@ -447,6 +822,27 @@ impl<'a> Optimizer<'a> {
         self.db.alloc_lir(Lir::Rest(divmod))
     }
 
+    fn opt_divmod(&mut self, scope_id: ScopeId, lhs: HirId, rhs: HirId) -> LirId {
+        let lhs = self.opt_hir(scope_id, lhs);
+        let rhs = self.opt_hir(scope_id, rhs);
+        self.db.alloc_lir(Lir::Divmod(lhs, rhs))
+    }
+
+    /// CLVM only has a left shift, which shifts right by a negative amount.
+    fn opt_shift_right(&mut self, scope_id: ScopeId, lhs: HirId, rhs: HirId) -> LirId {
+        let value = self.opt_hir(scope_id, lhs);
+        let shift = self.opt_hir(scope_id, rhs);
+        let zero = self.db.alloc_lir(Lir::Atom(Vec::new()));
+        let negated = self.db.alloc_lir(Lir::Sub(vec![zero, shift]));
+        self.db.alloc_lir(Lir::Ash(value, negated))
+    }
+
+    fn opt_bitwise_and(&mut self, scope_id: ScopeId, lhs: HirId, rhs: HirId) -> LirId {
+        let lhs = self.opt_hir(scope_id, lhs);
+        let rhs = self.opt_hir(scope_id, rhs);
+        self.db.alloc_lir(Lir::Logand(lhs, rhs))
+    }
+
     fn opt_lt(&mut self, scope_id: ScopeId, lhs: HirId, rhs: HirId) -> LirId {
         self.opt_gt(scope_id, rhs, lhs)
     }
@@ -484,7 +880,43 @@ impl<'a> Optimizer<'a> {
     fn opt_concat(&mut self, scope_id: ScopeId, lhs: HirId, rhs: HirId) -> LirId {
         let lhs = self.opt_hir(scope_id, lhs);
         let rhs = self.opt_hir(scope_id, rhs);
-        self.db.alloc_lir(Lir::Concat(vec![lhs, rhs]))
+
+        // Nested concatenations are flattened, so that constants which end up next to each other
+        // are merged into a single atom, even if other operands aren't constant.
+        let mut values: Vec<LirId> = Vec::new();
+
+        for lir_id in [lhs, rhs] {
+            let operands = match self.db.lir(lir_id) {
+                Lir::Concat(operands) => operands.clone(),
+                _ => vec![lir_id],
+            };
+
+            for operand in operands {
+                let merged = match (
+                    values.last().map(|&last| self.db.lir(last)),
+                    self.db.lir(operand),
+                ) {
+                    (Some(Lir::Atom(prefix)), Lir::Atom(suffix)) => {
+                        Some([prefix.as_slice(), suffix].concat())
+                    }
+                    _ => None,
+                };
+
+                match merged {
+                    Some(atom) => {
+                        values.pop();
+                        values.push(self.db.alloc_lir(Lir::Atom(atom)));
+                    }
+                    None => values.push(operand),
+                }
+            }
+        }
+
+        if let [value] = values.as_slice() {
+            return *value;
+        }
+
+        self.db.alloc_lir(Lir::Concat(values))
     }
 
     fn opt_not(&mut self, scope_id: ScopeId, value: HirId) -> LirId {
@@ -504,10 +936,177 @@ impl<'a> Optimizer<'a> {
         then_block: HirId,
         else_block: HirId,
     ) -> LirId {
+        if self.options.jump_tables() {
+            if let Some(lir_id) = self.opt_jump_table(scope_id, condition, then_block, else_block) {
+                return lir_id;
+            }
+        }
+
         let condition = self.opt_hir(scope_id, condition);
         let then_branch = self.opt_hir(scope_id, then_block);
         let else_branch = self.opt_hir(scope_id, else_block);
         self.db
             .alloc_lir(Lir::If(condition, then_branch, else_branch))
     }
+
+    /// Lowers a chain such as `if x == 0 { .. } else if x == 1 { .. } else { .. }` to a lookup
+    /// of the branch in a constant list, indexed by the value. Values between the arms, outside
+    /// of them, or which aren't minimally encoded fall through to the final `else` branch.
+    fn opt_jump_table(
+        &mut self,
+        scope_id: ScopeId,
+        condition: HirId,
+        then_block: HirId,
+        else_block: HirId,
+    ) -> Option<LirId> {
+        let DispatchChain {
+            symbol_id,
+            mut arms,
+            default,
+        } = self.dispatch_chain(condition, then_block, else_block)?;
+
+        if arms.len() < MIN_JUMP_TABLE_ARMS {
+            return None;
+        }
+
+        arms.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Later arms with the same value can never be reached, so the chain is left as it is.
+        if arms.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return None;
+        }
+
+        let low = arms.first().unwrap().0.clone();
+        let high = arms.last().unwrap().0.clone();
+        let size = usize::try_from(&high - &low + 1).ok()?;
+
+        if size > MAX_JUMP_TABLE_SIZE {
+            return None;
+        }
+
+        let default = self.opt_hir(scope_id, default);
+        let mut branches = vec![default; size];
+
+        for (value, branch) in arms {
+            let index = usize::try_from(value - &low).unwrap();
+            branches[index] = self.opt_hir(scope_id, branch);
+        }
+
+        let value = self.opt_reference(scope_id, symbol_id);
+        let low_atom = self.db.alloc_lir(Lir::Atom(bigint_to_bytes(low.clone())));
+        let high_atom = self.db.alloc_lir(Lir::Atom(bigint_to_bytes(high)));
+
+        let index = if low == BigInt::ZERO {
+            value
+        } else {
+            self.db.alloc_lir(Lir::Sub(vec![value, low_atom]))
+        };
+
+        let path = self.branch_path(index);
+        let dispatch = self.db.alloc_lir(Lir::Dispatch(path, branches));
+
+        let below = self.db.alloc_lir(Lir::Gt(low_atom, value));
+        let above = self.db.alloc_lir(Lir::Gt(value, high_atom));
+
+        // The chain compares bytes, so a value which isn't minimally encoded, such as `0x0001`,
+        // matches none of the arms even though it indexes the table like the integer it encodes.
+        // Adding nothing to it gives the minimal encoding, which it must already be.
+        let canonical = self.db.alloc_lir(Lir::Add(vec![value]));
+        let is_canonical = self.db.alloc_lir(Lir::Eq(value, canonical));
+        let non_canonical = self.db.alloc_lir(Lir::Not(is_canonical));
+
+        let out_of_range = self
+            .db
+            .alloc_lir(Lir::Any(vec![non_canonical, below, above]));
+
+        Some(self.db.alloc_lir(Lir::If(out_of_range, default, dispatch)))
+    }
+
+    fn opt_dispatch(&mut self, scope_id: ScopeId, index: HirId, branches: Vec<HirId>) -> LirId {
+        let index = self.opt_hir(scope_id, index);
+        let branches = branches
+            .into_iter()
+            .map(|branch| self.opt_hir(scope_id, branch))
+            .collect();
+
+        let path = self.branch_path(index);
+        self.db.alloc_lir(Lir::Dispatch(path, branches))
+    }
+
+    /// The branch at index `i` of the list is at the path `3 * 2^i - 1`.
+    fn branch_path(&mut self, index: LirId) -> LirId {
+        let three = self.db.alloc_lir(Lir::Atom(vec![3]));
+        let one = self.db.alloc_lir(Lir::Atom(vec![1]));
+        let shifted = self.db.alloc_lir(Lir::Ash(three, index));
+        self.db.alloc_lir(Lir::Sub(vec![shifted, one]))
+    }
+
+    /// Follows the `else` branches of an `if` chain for as long as each condition compares the
+    /// same symbol to an integer. Returns `None` if the first condition isn't one.
+    fn dispatch_chain(
+        &self,
+        condition: HirId,
+        then_block: HirId,
+        else_block: HirId,
+    ) -> Option<DispatchChain> {
+        let mut arms = Vec::new();
+        let mut symbol_id = None;
+        let mut next = Some((condition, then_block, else_block));
+        let mut default = else_block;
+
+        while let Some((condition, then_block, else_block)) = next.take() {
+            let Some((id, value)) = self.dispatch_condition(condition) else {
+                break;
+            };
+
+            if *symbol_id.get_or_insert(id) != id {
+                break;
+            }
+
+            arms.push((value, then_block));
+            default = else_block;
+
+            if let Hir::If {
+                condition,
+                then_block,
+                else_block,
+            } = *self.db.hir(else_block)
+            {
+                next = Some((condition, then_block, else_block));
+            }
+        }
+
+        Some(DispatchChain {
+            symbol_id: symbol_id?,
+            arms,
+            default,
+        })
+    }
+
+    /// The symbol and integer compared by a condition such as `x == 1`, if it is one.
+    fn dispatch_condition(&self, condition: HirId) -> Option<(SymbolId, BigInt)> {
+        let Hir::BinaryOp {
+            op: HirBinaryOp::Equals,
+            lhs,
+            rhs,
+        } = *self.db.hir(condition)
+        else {
+            return None;
+        };
+
+        let (symbol_id, atom) = match (self.db.hir(lhs), self.db.hir(rhs)) {
+            (Hir::Reference(symbol_id), Hir::Atom(atom))
+            | (Hir::Atom(atom), Hir::Reference(symbol_id)) => (*symbol_id, atom),
+            _ => return None,
+        };
+
+        // Atoms are compared by their bytes, so only the minimal encoding of an integer matches
+        // the same values as indexing the table does.
+        let value = BigInt::from_signed_bytes_be(atom);
+        if bigint_to_bytes(value.clone()) != *atom {
+            return None;
+        }
+
+        Some((symbol_id, value))
+    }
 }
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use crate::{
+    database::{Database, HirId, SymbolId},
+    hir::Hir,
+    symbol::Symbol,
+};
+
+/// Whether evaluating a function body always calls the function again with its own parameters,
+/// unchanged, before it can return. Such a call can only do the same, so the function never
+/// returns. This is best-effort: branches which don't return count as not recursing, and calls
+/// with arguments which are equal but written differently aren't recognized.
+pub(crate) fn always_recurses(
+    db: &Database,
+    function: SymbolId,
+    params: &[SymbolId],
+    varargs: bool,
+    hir_id: HirId,
+) -> bool {
+    check(db, function, params, varargs, hir_id, &mut HashMap::new())
+}
+
+/// Checks an expression, remembering the result for each let binding, since a binding can be
+/// referenced any number of times and each of its references would otherwise be checked again.
+fn check(
+    db: &Database,
+    function: SymbolId,
+    params: &[SymbolId],
+    varargs: bool,
+    hir_id: HirId,
+    bindings: &mut HashMap<SymbolId, bool>,
+) -> bool {
+    if let Hir::Reference(symbol_id) = *db.hir(hir_id) {
+        let Symbol::LetBinding { hir_id, .. } = *db.symbol(symbol_id) else {
+            return false;
+        };
+
+        if let Some(&result) = bindings.get(&symbol_id) {
+            return result;
+        }
+
+        let result = check(db, function, params, varargs, hir_id, bindings);
+        bindings.insert(symbol_id, result);
+        return result;
+    }
+
+    let mut recurses = |hir_id| check(db, function, params, varargs, hir_id, bindings);
+
+    match db.hir(hir_id).clone() {
+        Hir::Unknown | Hir::Atom(..) | Hir::Env | Hir::Raise(None) | Hir::Reference(..) => false,
+        Hir::FunctionCall { callee, args } => {
+            is_self_call(db, function, params, varargs, callee, args)
+                || recurses(callee)
+                || recurses(args)
+        }
+        Hir::Scope { value, .. } => recurses(value),
+        Hir::BinaryOp { lhs, rhs, .. } | Hir::Pair(lhs, rhs) => recurses(lhs) || recurses(rhs),
+        Hir::First(value)
+        | Hir::Rest(value)
+        | Hir::Not(value)
+        | Hir::Raise(Some(value))
+        | Hir::Sha256(value)
+        | Hir::Keccak256(value)
+        | Hir::IsCons(value)
+        | Hir::Strlen(value) => recurses(value),
+        Hir::Softfork { cost, program } => recurses(cost) || recurses(program),
+        Hir::Op { args, .. } => args.into_iter().any(recurses),
+        Hir::Substr { value, start, end } => recurses(value) || recurses(start) || recurses(end),
+        // Only one of the branches is evaluated, so both of them must recurse.
+        Hir::If {
+            condition,
+            then_block,
+            else_block,
+        } => recurses(condition) || (recurses(then_block) && recurses(else_block)),
+        Hir::Dispatch { index, branches } => recurses(index) || branches.into_iter().all(recurses),
+    }
+}
+
+/// Whether a call is to the function itself, passing each of its parameters in order.
+fn is_self_call(
+    db: &Database,
+    function: SymbolId,
+    params: &[SymbolId],
+    varargs: bool,
+    callee: HirId,
+    mut args: HirId,
+) -> bool {
+    if !matches!(db.hir(callee), Hir::Reference(symbol_id) if *symbol_id == function) {
+        return false;
+    }
+
+    let is_param =
+        |hir_id, param| matches!(db.hir(hir_id), Hir::Reference(symbol_id) if *symbol_id == param);
+
+    for (i, &param) in params.iter().enumerate() {
+        // The spread parameter is the rest of the arguments itself.
+        if varargs && i + 1 == params.len() {
+            return is_param(args, param);
+        }
+
+        let Hir::Pair(first, rest) = *db.hir(args) else {
+            return false;
+        };
+
+        if !is_param(first, param) {
+            return false;
+        }
+
+        args = rest;
+    }
+
+    matches!(db.hir(args), Hir::Atom(atom) if atom.is_empty())
+}
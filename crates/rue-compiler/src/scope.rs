@@ -1,12 +1,12 @@
 use std::collections::HashMap;
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use crate::{database::TypeId, SymbolId};
 
 #[derive(Debug, Default)]
 pub struct Scope {
-    symbol_table: HashMap<String, SymbolId>,
+    symbol_table: IndexMap<String, SymbolId>,
     type_aliases: HashMap<String, TypeId>,
     type_names: HashMap<TypeId, String>,
     local_symbols: IndexSet<SymbolId>,
@@ -18,10 +18,27 @@ impl Scope {
         self.local_symbols.insert(symbol_id);
     }
 
+    /// Defines a symbol which can't be referenced by name, such as an intermediate value.
+    pub fn define_hidden_symbol(&mut self, symbol_id: SymbolId) {
+        self.local_symbols.insert(symbol_id);
+    }
+
     pub fn symbol(&self, name: &str) -> Option<SymbolId> {
         self.symbol_table.get(name).copied()
     }
 
+    /// The name which a symbol was defined with in this scope, if it isn't hidden.
+    pub fn symbol_name(&self, symbol_id: SymbolId) -> Option<&str> {
+        self.symbol_table
+            .iter()
+            .find(|(_, &id)| id == symbol_id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn symbol_names(&self) -> impl Iterator<Item = &str> {
+        self.symbol_table.keys().map(|name| name.as_str())
+    }
+
     pub fn define_type(&mut self, name: String, type_id: TypeId) {
         self.type_aliases.insert(name.clone(), type_id);
         self.type_names.insert(type_id, name);
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+    database::{Database, HirId, ScopeId, SymbolId},
+    hir::Hir,
+    symbol::Symbol,
+    ty::{type_name, FunctionType},
+};
+
+/// Writes every symbol reachable from the root scope as a tree of scopes, with the kind and type
+/// of each symbol, and the symbols each function captures if the program was optimized. Scopes
+/// are numbered in the order they're written, so the output doesn't depend on allocation order.
+pub(crate) fn dump_symbols(
+    db: &Database,
+    root_scope_id: ScopeId,
+    captures: &IndexMap<ScopeId, IndexSet<SymbolId>>,
+) -> String {
+    let mut collector = SymbolCollector {
+        db,
+        names: HashMap::new(),
+        visited_scopes: HashSet::new(),
+        visited_hir: HashSet::new(),
+    };
+    collector.visit_scope(root_scope_id, None);
+
+    let mut dumper = SymbolDumper {
+        db,
+        captures,
+        lines: Vec::new(),
+        names: collector.names,
+        scope_stack: Vec::new(),
+        visited_scopes: HashSet::new(),
+        visited_hir: HashSet::new(),
+        next_scope: 0,
+    };
+
+    dumper.visit_scope(root_scope_id, None, 0);
+    dumper.render()
+}
+
+/// Finds the names of every symbol which is defined in a scope, before any are written. Lambdas
+/// aren't defined in a scope, so they're the only functions without a name afterwards.
+struct SymbolCollector<'a> {
+    db: &'a Database,
+    names: HashMap<SymbolId, String>,
+    visited_scopes: HashSet<ScopeId>,
+    visited_hir: HashSet<HirId>,
+}
+
+impl<'a> SymbolCollector<'a> {
+    fn visit_scope(&mut self, scope_id: ScopeId, body: Option<HirId>) {
+        if !self.visited_scopes.insert(scope_id) {
+            return;
+        }
+
+        let scope = self.db.scope(scope_id);
+
+        for symbol_id in scope.local_symbols() {
+            let name = scope.symbol_name(symbol_id).unwrap_or("<hidden>");
+            self.names.insert(symbol_id, name.to_string());
+            self.visit_symbol(symbol_id);
+        }
+
+        if let Some(body) = body {
+            self.visit_hir(body);
+        }
+    }
+
+    fn visit_symbol(&mut self, symbol_id: SymbolId) {
+        match self.db.symbol(symbol_id).clone() {
+            Symbol::Function {
+                scope_id, hir_id, ..
+            } => self.visit_scope(scope_id, Some(hir_id)),
+            Symbol::Parameter { .. } => {}
+            Symbol::LetBinding { hir_id, .. } | Symbol::ConstBinding { hir_id, .. } => {
+                self.visit_hir(hir_id);
+            }
+        }
+    }
+
+    fn visit_hir(&mut self, hir_id: HirId) {
+        if !self.visited_hir.insert(hir_id) {
+            return;
+        }
+
+        match self.db.hir(hir_id).clone() {
+            Hir::Scope { scope_id, value } => self.visit_scope(scope_id, Some(value)),
+            Hir::Reference(symbol_id) => self.visit_symbol(symbol_id),
+            hir => {
+                for child in hir_children(&hir) {
+                    self.visit_hir(child);
+                }
+            }
+        }
+    }
+}
+
+enum Line {
+    Scope {
+        depth: usize,
+        index: usize,
+    },
+    Symbol {
+        depth: usize,
+        symbol_id: SymbolId,
+        kind: &'static str,
+        ty: String,
+        captures: Option<Vec<SymbolId>>,
+    },
+}
+
+struct SymbolDumper<'a> {
+    db: &'a Database,
+    captures: &'a IndexMap<ScopeId, IndexSet<SymbolId>>,
+    lines: Vec<Line>,
+    /// Captures can refer to lambdas which haven't been visited yet, so names are looked up last.
+    names: HashMap<SymbolId, String>,
+    scope_stack: Vec<ScopeId>,
+    visited_scopes: HashSet<ScopeId>,
+    visited_hir: HashSet<HirId>,
+    next_scope: usize,
+}
+
+impl<'a> SymbolDumper<'a> {
+    fn visit_scope(&mut self, scope_id: ScopeId, body: Option<HirId>, depth: usize) {
+        if !self.visited_scopes.insert(scope_id) {
+            return;
+        }
+
+        self.lines.push(Line::Scope {
+            depth,
+            index: self.next_scope,
+        });
+        self.next_scope += 1;
+        self.scope_stack.push(scope_id);
+
+        for symbol_id in self.db.scope(scope_id).local_symbols() {
+            self.visit_symbol(symbol_id, depth + 1);
+        }
+
+        if let Some(body) = body {
+            self.visit_hir(body, depth + 1);
+        }
+
+        self.scope_stack.pop().unwrap();
+    }
+
+    fn visit_symbol(&mut self, symbol_id: SymbolId, depth: usize) {
+        match self.db.symbol(symbol_id).clone() {
+            Symbol::Function {
+                scope_id,
+                hir_id,
+                ty,
+            } => {
+                self.lines.push(Line::Symbol {
+                    depth,
+                    symbol_id,
+                    kind: "function",
+                    ty: self.function_type_name(&ty),
+                    captures: self
+                        .captures
+                        .get(&scope_id)
+                        .map(|captures| captures.iter().copied().collect()),
+                });
+                self.visit_scope(scope_id, Some(hir_id), depth + 1);
+            }
+            Symbol::Parameter { type_id } => {
+                self.lines.push(Line::Symbol {
+                    depth,
+                    symbol_id,
+                    kind: "parameter",
+                    ty: type_name(self.db, &self.scope_stack, type_id),
+                    captures: None,
+                });
+            }
+            Symbol::LetBinding { type_id, hir_id } => {
+                self.lines.push(Line::Symbol {
+                    depth,
+                    symbol_id,
+                    kind: "let",
+                    ty: type_name(self.db, &self.scope_stack, type_id),
+                    captures: None,
+                });
+                self.visit_hir(hir_id, depth + 1);
+            }
+            Symbol::ConstBinding { type_id, hir_id } => {
+                self.lines.push(Line::Symbol {
+                    depth,
+                    symbol_id,
+                    kind: "const",
+                    ty: type_name(self.db, &self.scope_stack, type_id),
+                    captures: None,
+                });
+                self.visit_hir(hir_id, depth + 1);
+            }
+        }
+    }
+
+    /// Finds the scopes nested inside of an expression, such as those of blocks.
+    fn visit_hir(&mut self, hir_id: HirId, depth: usize) {
+        if !self.visited_hir.insert(hir_id) {
+            return;
+        }
+
+        match self.db.hir(hir_id).clone() {
+            Hir::Scope { scope_id, value } => self.visit_scope(scope_id, Some(value), depth),
+            Hir::Reference(symbol_id) => {
+                let is_lambda = matches!(self.db.symbol(symbol_id), Symbol::Function { .. })
+                    && !self.names.contains_key(&symbol_id);
+
+                if is_lambda {
+                    self.names.insert(symbol_id, "<lambda>".to_string());
+                    self.visit_symbol(symbol_id, depth);
+                }
+            }
+            hir => {
+                for child in hir_children(&hir) {
+                    self.visit_hir(child, depth);
+                }
+            }
+        }
+    }
+
+    fn function_type_name(&self, ty: &FunctionType) -> String {
+        let params: Vec<String> = ty
+            .parameter_types()
+            .iter()
+            .map(|&type_id| type_name(self.db, &self.scope_stack, type_id))
+            .collect();
+        let return_type = type_name(self.db, &self.scope_stack, ty.return_type());
+        format!("fun({}) -> {return_type}", params.join(", "))
+    }
+
+    fn name(&self, symbol_id: SymbolId) -> &str {
+        self.names
+            .get(&symbol_id)
+            .map_or("<lambda>", String::as_str)
+    }
+
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        for line in &self.lines {
+            match line {
+                Line::Scope { depth, index } => {
+                    output.push_str(&format!("{}scope {index}\n", "  ".repeat(*depth)));
+                }
+                Line::Symbol {
+                    depth,
+                    symbol_id,
+                    kind,
+                    ty,
+                    captures,
+                } => {
+                    output.push_str(&format!(
+                        "{}{kind} {}: {ty}",
+                        "  ".repeat(*depth),
+                        self.name(*symbol_id)
+                    ));
+
+                    if let Some(captures) = captures {
+                        let names: Vec<&str> = captures.iter().map(|&id| self.name(id)).collect();
+                        output.push_str(&format!(" captures [{}]", names.join(", ")));
+                    }
+
+                    output.push('\n');
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// The expressions directly inside of an expression, other than the value of a scope.
+fn hir_children(hir: &Hir) -> Vec<HirId> {
+    match *hir {
+        Hir::Unknown
+        | Hir::Atom(..)
+        | Hir::Reference(..)
+        | Hir::Env
+        | Hir::Raise(None)
+        | Hir::Scope { .. } => Vec::new(),
+        Hir::Pair(first, rest) => vec![first, rest],
+        Hir::FunctionCall { callee, args } => vec![callee, args],
+        Hir::BinaryOp { lhs, rhs, .. } => vec![lhs, rhs],
+        Hir::First(value)
+        | Hir::Rest(value)
+        | Hir::Not(value)
+        | Hir::Raise(Some(value))
+        | Hir::Sha256(value)
+        | Hir::Keccak256(value)
+        | Hir::IsCons(value)
+        | Hir::Strlen(value) => vec![value],
+        Hir::Substr { value, start, end } => vec![value, start, end],
+        Hir::Softfork { cost, program } => vec![cost, program],
+        Hir::Op { ref args, .. } => args.clone(),
+        Hir::If {
+            condition,
+            then_block,
+            else_block,
+        } => vec![condition, then_block, else_block],
+        Hir::Dispatch {
+            index,
+            ref branches,
+        } => {
+            let mut children = vec![index];
+            children.extend(branches);
+            children
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
 use crate::{
-    database::{HirId, TypeId},
+    database::{Database, HirId, ScopeId, TypeId},
     SymbolId,
 };
 
@@ -16,8 +16,12 @@ pub enum Type {
     Bool,
     Bytes,
     Bytes32,
+    PublicKey,
+    Signature,
     Pair(TypeId, TypeId),
     List(TypeId),
+    Array(TypeId, usize),
+    Union(Vec<TypeId>),
     Struct(StructType),
     Enum(EnumType),
     EnumVariant(EnumVariant),
@@ -124,6 +128,8 @@ impl FunctionType {
     }
 }
 
+/// An expression which has been lowered to HIR, along with its type and the type guards it
+/// implies. This only exists while lowering, since the optimizer works on the HIR alone.
 #[derive(Debug, Clone)]
 pub struct Value {
     hir: HirId,
@@ -189,3 +195,120 @@ impl Guard {
         self.else_type
     }
 }
+
+/// The name of a type as it's written in the source, using the names of types defined in the
+/// given scopes where there are any, with the innermost scope last.
+pub(crate) fn type_name(db: &Database, scopes: &[ScopeId], ty: TypeId) -> String {
+    type_name_visitor(db, scopes, ty, &mut IndexSet::new())
+}
+
+fn type_name_visitor(
+    db: &Database,
+    scopes: &[ScopeId],
+    ty: TypeId,
+    stack: &mut IndexSet<TypeId>,
+) -> String {
+    for &scope_id in scopes.iter().rev() {
+        if let Some(name) = db.scope(scope_id).type_name(ty) {
+            return name.to_string();
+        }
+    }
+
+    if stack.contains(&ty) {
+        return "<recursive>".to_string();
+    }
+
+    stack.insert(ty);
+
+    let name = match db.ty(ty) {
+        Type::Unknown => "{unknown}".to_string(),
+        Type::Nil => "Nil".to_string(),
+        Type::Any => "Any".to_string(),
+        Type::Int => "Int".to_string(),
+        Type::Bool => "Bool".to_string(),
+        Type::Bytes => "Bytes".to_string(),
+        Type::Bytes32 => "Bytes32".to_string(),
+        Type::PublicKey => "PublicKey".to_string(),
+        Type::Signature => "Signature".to_string(),
+        Type::List(items) => {
+            let inner = item_type_name(db, scopes, *items, stack);
+            format!("{}[]", inner)
+        }
+        Type::Array(items, len) => {
+            let inner = item_type_name(db, scopes, *items, stack);
+            format!("{inner}[{len}]")
+        }
+        Type::Union(members) => {
+            let members: Vec<String> = members
+                .iter()
+                .map(|&ty| type_name_visitor(db, scopes, ty, stack))
+                .collect();
+            members.join(" | ")
+        }
+        Type::Pair(left, right) => {
+            let left = type_name_visitor(db, scopes, *left, stack);
+            let right = type_name_visitor(db, scopes, *right, stack);
+            format!("({left}, {right})")
+        }
+        Type::Struct(struct_type) => {
+            let fields: Vec<String> = struct_type
+                .fields()
+                .iter()
+                .map(|(name, ty)| {
+                    format!("{}: {}", name, type_name_visitor(db, scopes, *ty, stack))
+                })
+                .collect();
+
+            format!("{{ {} }}", fields.join(", "))
+        }
+        Type::Enum { .. } => "<unnamed enum>".to_string(),
+        Type::EnumVariant(enum_variant) => {
+            let enum_name = type_name_visitor(db, scopes, enum_variant.enum_type(), stack);
+
+            let fields: Vec<String> = enum_variant
+                .fields()
+                .iter()
+                .map(|(name, ty)| {
+                    format!("{}: {}", name, type_name_visitor(db, scopes, *ty, stack))
+                })
+                .collect();
+
+            format!(
+                "{}::{} {{ {} }}",
+                enum_name,
+                enum_variant.name(),
+                fields.join(", ")
+            )
+        }
+        Type::Function(function_type) => {
+            let params: Vec<String> = function_type
+                .parameter_types()
+                .iter()
+                .map(|&ty| type_name_visitor(db, scopes, ty, stack))
+                .collect();
+
+            let ret = type_name_visitor(db, scopes, function_type.return_type(), stack);
+
+            format!("fun({}) -> {}", params.join(", "), ret)
+        }
+        Type::Alias(..) => unreachable!(),
+    };
+
+    stack.pop().unwrap();
+
+    name
+}
+
+/// The name of the item type of a list, which is grouped if it's a union.
+fn item_type_name(
+    db: &Database,
+    scopes: &[ScopeId],
+    ty: TypeId,
+    stack: &mut IndexSet<TypeId>,
+) -> String {
+    let name = type_name_visitor(db, scopes, ty, stack);
+    match db.ty(ty) {
+        Type::Union(..) if name.contains(" | ") => format!("({name})"),
+        _ => name,
+    }
+}
@@ -10,6 +10,7 @@ pub struct Lexer<'a> {
     source: &'a str,
     chars: Chars<'a>,
     pos: usize,
+    last_kind: Option<TokenKind>,
 }
 
 impl<'a> Lexer<'a> {
@@ -18,6 +19,7 @@ impl<'a> Lexer<'a> {
             source,
             chars: source.chars(),
             pos: 0,
+            last_kind: None,
         }
     }
 
@@ -43,6 +45,7 @@ impl<'a> Lexer<'a> {
             },
             '*' => TokenKind::Star,
             '%' => TokenKind::Percent,
+            '|' => TokenKind::Pipe,
             '<' => match self.peek() {
                 '=' => {
                     self.bump();
@@ -96,7 +99,11 @@ impl<'a> Lexer<'a> {
                 _ => TokenKind::Colon,
             },
             ';' => TokenKind::Semicolon,
+            '@' => TokenKind::At,
+            '\'' if self.is_label() => self.label(),
             c @ ('"' | '\'') => self.string(c),
+            '0' if self.peek() == 'x' => self.hex(),
+            '0' if self.peek() == 'b' => self.binary(),
             c if c.is_ascii_digit() => self.integer(),
             'a'..='z' | 'A'..='Z' | '_' => {
                 while matches!(self.peek(), 'a'..='z' | 'A'..='Z' | '0'..='9' | '_') {
@@ -119,6 +126,12 @@ impl<'a> Lexer<'a> {
                     "false" => TokenKind::False,
                     "as" => TokenKind::As,
                     "is" => TokenKind::Is,
+                    "static_assert" => TokenKind::StaticAssert,
+                    "match" => TokenKind::Match,
+                    "break" => TokenKind::Break,
+                    "impl" => TokenKind::Impl,
+                    "where" => TokenKind::Where,
+                    "pub" => TokenKind::Pub,
                     _ => TokenKind::Ident,
                 }
             }
@@ -138,9 +151,83 @@ impl<'a> Lexer<'a> {
         while self.peek().is_ascii_digit() || self.peek() == '_' {
             self.bump();
         }
+        if self.peek().is_ascii_alphabetic() {
+            while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+                self.bump();
+            }
+        }
         TokenKind::Int
     }
 
+    /// A binary integer such as `0b1010`, which must have at least one digit. It can have the
+    /// same `i` and `b` suffixes as a decimal integer. Without any digits, `0b` is the decimal
+    /// integer zero with the `b` suffix.
+    fn binary(&mut self) -> TokenKind {
+        if !matches!(self.peek_nth(1), '0' | '1' | '_') {
+            return self.integer();
+        }
+        self.bump();
+        let mut has_digits = false;
+        while matches!(self.peek(), '0' | '1' | '_') {
+            has_digits |= self.bump() != '_';
+        }
+        let suffix_start = self.pos;
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.bump();
+        }
+        match &self.source[suffix_start..self.pos] {
+            _ if !has_digits => TokenKind::Unknown,
+            "" | "i" | "b" => TokenKind::Int,
+            _ => TokenKind::Unknown,
+        }
+    }
+
+    /// Hex bytes such as `0xff`, which must have at least one digit. With the `i` suffix, as in
+    /// `0xffi`, the digits are an unsigned integer instead.
+    fn hex(&mut self) -> TokenKind {
+        self.bump();
+        let mut has_digits = false;
+        while self.peek().is_ascii_hexdigit() || self.peek() == '_' {
+            has_digits |= self.bump() != '_';
+        }
+        let suffix_start = self.pos;
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.bump();
+        }
+        match &self.source[suffix_start..self.pos] {
+            _ if !has_digits => TokenKind::Unknown,
+            "" => TokenKind::Hex,
+            "i" => TokenKind::Int,
+            _ => TokenKind::Unknown,
+        }
+    }
+
+    /// Labels and single quoted strings both start with a quote, so a label is only lexed after
+    /// `break`, or when it's immediately followed by a colon and the block it names.
+    fn is_label(&self) -> bool {
+        let mut chars = self.chars.clone();
+
+        if !matches!(chars.next(), Some('a'..='z' | 'A'..='Z' | '_')) {
+            return false;
+        }
+
+        let mut chars = chars.skip_while(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_'));
+
+        match chars.next() {
+            Some('\'') => false,
+            _ if self.last_kind == Some(TokenKind::Break) => true,
+            Some(':') => chars.find(|c| !c.is_whitespace()) == Some('{'),
+            _ => false,
+        }
+    }
+
+    fn label(&mut self) -> TokenKind {
+        while matches!(self.peek(), 'a'..='z' | 'A'..='Z' | '0'..='9' | '_') {
+            self.bump();
+        }
+        TokenKind::Label
+    }
+
     fn string(&mut self, quote: char) -> TokenKind {
         let is_terminated = loop {
             match self.bump() {
@@ -167,20 +254,16 @@ impl<'a> Lexer<'a> {
         let mut depth = 1;
         let is_terminated = loop {
             match self.bump() {
-                '*' => {
-                    if self.peek() == '/' {
-                        self.bump();
-                        depth -= 1;
-                        if depth == 0 {
-                            break true;
-                        }
+                '*' if self.peek() == '/' => {
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        break true;
                     }
                 }
-                '/' => {
-                    if self.peek() == '*' {
-                        self.bump();
-                        depth += 1;
-                    }
+                '/' if self.peek() == '*' => {
+                    self.bump();
+                    depth += 1;
                 }
                 '\0' => break false,
                 _ => {}
@@ -212,7 +295,14 @@ impl<'a> Iterator for Lexer<'a> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.token()
+        let token = self.token()?;
+        if !matches!(
+            token.kind(),
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment { .. }
+        ) {
+            self.last_kind = Some(token.kind());
+        }
+        Some(token)
     }
 }
 
@@ -238,6 +328,30 @@ mod tests {
     fn test_int() {
         check("0", &[TokenKind::Int]);
         check("42", &[TokenKind::Int]);
+        check("42i", &[TokenKind::Int]);
+        check("1_000b", &[TokenKind::Int]);
+        check("0b1010", &[TokenKind::Int]);
+        check("0b1111_0000", &[TokenKind::Int]);
+        check("0b", &[TokenKind::Int]);
+        check("0b_", &[TokenKind::Unknown]);
+        check("0b102", &[TokenKind::Unknown]);
+        check("0b1i", &[TokenKind::Int]);
+        check("0b1_0i", &[TokenKind::Int]);
+        check("0b1b", &[TokenKind::Int]);
+        check("0b1z", &[TokenKind::Unknown]);
+        check("0b12i", &[TokenKind::Unknown]);
+        check("0xffi", &[TokenKind::Int]);
+        check("0x00i", &[TokenKind::Int]);
+    }
+
+    #[test]
+    fn test_hex() {
+        check("0xff", &[TokenKind::Hex]);
+        check("0xDEAD_beef", &[TokenKind::Hex]);
+        check("0x", &[TokenKind::Unknown]);
+        check("0x_", &[TokenKind::Unknown]);
+        check("0xi", &[TokenKind::Unknown]);
+        check("0xffz", &[TokenKind::Unknown]);
     }
 
     #[test]
@@ -266,6 +380,12 @@ mod tests {
                 is_terminated: false,
             }],
         );
+        check(
+            "\"abc\\\ndef\"",
+            &[TokenKind::String {
+                is_terminated: true,
+            }],
+        );
     }
 
     #[test]
@@ -286,6 +406,46 @@ mod tests {
         check("nil", &[TokenKind::Nil]);
         check("as", &[TokenKind::As]);
         check("is", &[TokenKind::Is]);
+        check("static_assert", &[TokenKind::StaticAssert]);
+        check("match", &[TokenKind::Match]);
+        check("break", &[TokenKind::Break]);
+        check("impl", &[TokenKind::Impl]);
+        check("where", &[TokenKind::Where]);
+        check("pub", &[TokenKind::Pub]);
+    }
+
+    #[test]
+    fn test_label() {
+        check(
+            "'outer: {",
+            &[
+                TokenKind::Label,
+                TokenKind::Colon,
+                TokenKind::Whitespace,
+                TokenKind::OpenBrace,
+            ],
+        );
+        check(
+            "break 'outer;",
+            &[
+                TokenKind::Break,
+                TokenKind::Whitespace,
+                TokenKind::Label,
+                TokenKind::Semicolon,
+            ],
+        );
+        check(
+            "'a'",
+            &[TokenKind::String {
+                is_terminated: true,
+            }],
+        );
+        check(
+            "'key: value'",
+            &[TokenKind::String {
+                is_terminated: true,
+            }],
+        );
     }
 
     #[test]
@@ -308,6 +468,8 @@ mod tests {
         check("->", &[TokenKind::Arrow]);
         check("=>", &[TokenKind::FatArrow]);
         check("...", &[TokenKind::Spread]);
+        check("@", &[TokenKind::At]);
+        check("|", &[TokenKind::Pipe]);
     }
 
     #[test]
@@ -334,6 +496,52 @@ mod tests {
         check("!", &[TokenKind::Not]);
     }
 
+    #[test]
+    fn test_maximal_munch() {
+        use TokenKind::*;
+
+        check("a<=b", &[Ident, LessThanEquals, Ident]);
+        check("a>=b", &[Ident, GreaterThanEquals, Ident]);
+        check("a==b", &[Ident, Equals, Ident]);
+        check("a!=b", &[Ident, NotEquals, Ident]);
+        check("A::B", &[Ident, PathSeparator, Ident]);
+        check("a->b", &[Ident, Arrow, Ident]);
+        check("a=>b", &[Ident, FatArrow, Ident]);
+        check("a...b", &[Ident, Spread, Ident]);
+
+        // The longest operator is always taken first, with the rest lexed after it.
+        check("a<==b", &[Ident, LessThanEquals, Assign, Ident]);
+        check("a>==b", &[Ident, GreaterThanEquals, Assign, Ident]);
+        check("a===b", &[Ident, Equals, Assign, Ident]);
+        check("a!==b", &[Ident, NotEquals, Assign, Ident]);
+        check("a=>=b", &[Ident, FatArrow, Assign, Ident]);
+        check("A:::B", &[Ident, PathSeparator, Colon, Ident]);
+        check("a-->b", &[Ident, Minus, Arrow, Ident]);
+        check("a->>b", &[Ident, Arrow, GreaterThan, Ident]);
+        check("a....b", &[Ident, Spread, Dot, Ident]);
+        check("a..b", &[Ident, Dot, Dot, Ident]);
+        check("!!=", &[Not, NotEquals]);
+
+        // Operators are lexed the same way next to literals and brackets.
+        check("1>=2", &[Int, GreaterThanEquals, Int]);
+        check("0xff!=0x00", &[Hex, NotEquals, Hex]);
+        check("...[1]", &[Spread, OpenBracket, Int, CloseBracket]);
+        check(")->Int", &[CloseParen, Arrow, Ident]);
+        check("_=>1", &[Ident, FatArrow, Int]);
+        check(
+            "'a'<='b'",
+            &[
+                String {
+                    is_terminated: true,
+                },
+                LessThanEquals,
+                String {
+                    is_terminated: true,
+                },
+            ],
+        );
+    }
+
     #[test]
     fn test_whitespace() {
         check(" ", &[TokenKind::Whitespace]);
@@ -2,7 +2,9 @@
 pub enum TokenKind {
     Ident,
     Int,
+    Hex,
     String { is_terminated: bool },
+    Label,
 
     OpenParen,
     CloseParen,
@@ -27,6 +29,12 @@ pub enum TokenKind {
     False,
     As,
     Is,
+    StaticAssert,
+    Match,
+    Break,
+    Impl,
+    Where,
+    Pub,
 
     Dot,
     Comma,
@@ -36,6 +44,8 @@ pub enum TokenKind {
     Arrow,
     FatArrow,
     Spread,
+    At,
+    Pipe,
 
     Plus,
     Minus,
@@ -1,4 +1,4 @@
-use rue_compiler::{analyze, DiagnosticKind};
+use rue_compiler::{analyze, CompilerOptions, DiagnosticKind};
 use rue_parser::{line_col, parse, LineCol};
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
@@ -55,7 +55,7 @@ impl LanguageServer for Backend {
 impl Backend {
     async fn on_change(&self, uri: Url, text: String, _version: i32) {
         let (root, errors) = parse(&text);
-        let compiler_errors = analyze(root);
+        let compiler_errors = analyze(root, &CompilerOptions::default());
 
         let mut diagnostics: Vec<Diagnostic> = errors
             .into_iter()
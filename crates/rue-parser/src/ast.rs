@@ -60,7 +60,10 @@ ast_enum!(
     TypeAliasItem,
     ConstItem,
     StructItem,
-    EnumItem
+    EnumItem,
+    StaticAssertItem,
+    ImplItem,
+    TestItem
 );
 ast_node!(FunctionItem);
 ast_node!(FunctionParam);
@@ -69,7 +72,12 @@ ast_node!(StructItem);
 ast_node!(EnumItem);
 ast_node!(EnumVariant);
 ast_node!(ConstItem);
+ast_node!(StaticAssertItem);
+ast_node!(ImplItem);
+ast_node!(TestItem);
 ast_node!(StructField);
+ast_node!(Attribute);
+ast_node!(WhereClause);
 
 ast_node!(Block);
 ast_node!(Path);
@@ -82,6 +90,7 @@ ast_enum!(
     ListExpr,
     PairExpr,
     Block,
+    LabeledBlock,
     LambdaExpr,
     PrefixExpr,
     BinaryExpr,
@@ -89,6 +98,7 @@ ast_enum!(
     CastExpr,
     GuardExpr,
     IfExpr,
+    MatchExpr,
     FunctionCall,
     FieldAccess,
     IndexAccess
@@ -105,6 +115,10 @@ ast_node!(GroupExpr);
 ast_node!(CastExpr);
 ast_node!(GuardExpr);
 ast_node!(IfExpr);
+ast_node!(MatchExpr);
+ast_node!(MatchArm);
+ast_node!(LabeledBlock);
+ast_node!(VariantPattern);
 ast_node!(FunctionCall);
 ast_node!(FunctionCallArg);
 ast_node!(FieldAccess);
@@ -113,19 +127,32 @@ ast_node!(IndexAccess);
 ast_node!(LambdaExpr);
 ast_node!(LambdaParam);
 
-ast_enum!(Type, Path, ListType, PairType, FunctionType);
+ast_enum!(
+    Type,
+    Path,
+    ListType,
+    TupleType,
+    UnionType,
+    GroupType,
+    FunctionType
+);
 ast_node!(ListType);
 ast_node!(ListTypeItem);
-ast_node!(PairType);
+ast_node!(TupleType);
+ast_node!(UnionType);
+ast_node!(GroupType);
 ast_node!(FunctionType);
 ast_node!(FunctionTypeParam);
 
-ast_enum!(Stmt, LetStmt, IfStmt, ReturnStmt, RaiseStmt, AssertStmt);
+ast_enum!(Stmt, LetStmt, IfStmt, ReturnStmt, RaiseStmt, AssertStmt, BreakStmt);
 ast_node!(LetStmt);
+ast_node!(TuplePattern);
+ast_node!(StructPattern);
 ast_node!(IfStmt);
 ast_node!(ReturnStmt);
 ast_node!(RaiseStmt);
 ast_node!(AssertStmt);
+ast_node!(BreakStmt);
 
 impl Root {
     pub fn items(&self) -> Vec<Item> {
@@ -133,6 +160,46 @@ impl Root {
     }
 }
 
+impl Item {
+    pub fn attributes(&self) -> Vec<Attribute> {
+        self.syntax()
+            .children()
+            .filter_map(Attribute::cast)
+            .collect()
+    }
+
+    /// Whether the item is exported from its file with `pub`.
+    pub fn is_pub(&self) -> bool {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .any(|token| token.kind() == SyntaxKind::Pub)
+    }
+}
+
+impl Attribute {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn args(&self) -> Vec<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .filter(|token| {
+                matches!(
+                    token.kind(),
+                    SyntaxKind::Ident | SyntaxKind::String | SyntaxKind::Int
+                )
+            })
+            .skip(1)
+            .collect()
+    }
+}
+
 impl FunctionItem {
     pub fn name(&self) -> Option<SyntaxToken> {
         self.syntax()
@@ -152,11 +219,21 @@ impl FunctionItem {
         self.syntax().children().find_map(Type::cast)
     }
 
+    pub fn where_clause(&self) -> Option<WhereClause> {
+        self.syntax().children().find_map(WhereClause::cast)
+    }
+
     pub fn body(&self) -> Option<Block> {
         self.syntax().children().find_map(Block::cast)
     }
 }
 
+impl WhereClause {
+    pub fn conditions(&self) -> Vec<Expr> {
+        self.syntax().children().filter_map(Expr::cast).collect()
+    }
+}
+
 impl FunctionParam {
     pub fn spread(&self) -> Option<SyntaxToken> {
         self.syntax()
@@ -275,6 +352,48 @@ impl ConstItem {
     }
 }
 
+impl StaticAssertItem {
+    pub fn condition(&self) -> Option<Expr> {
+        self.syntax().children().find_map(Expr::cast)
+    }
+
+    pub fn message(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::String)
+    }
+}
+
+impl TestItem {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::String)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        self.syntax().children().find_map(Block::cast)
+    }
+}
+
+impl ImplItem {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn functions(&self) -> Vec<FunctionItem> {
+        self.syntax()
+            .children()
+            .filter_map(FunctionItem::cast)
+            .collect()
+    }
+}
+
 impl Block {
     pub fn items(&self) -> Vec<Item> {
         self.syntax().children().filter_map(Item::cast).collect()
@@ -307,13 +426,21 @@ impl LetStmt {
             .find(|token| token.kind() == SyntaxKind::Ident)
     }
 
+    pub fn tuple_pattern(&self) -> Option<TuplePattern> {
+        self.syntax().children().find_map(TuplePattern::cast)
+    }
+
+    pub fn struct_pattern(&self) -> Option<StructPattern> {
+        self.syntax().children().find_map(StructPattern::cast)
+    }
+
     pub fn ty(&self) -> Option<Type> {
-        let exprs: Vec<Expr> = self.syntax().children().filter_map(Expr::cast).collect();
-        let types: Vec<Type> = self.syntax().children().filter_map(Type::cast).collect();
-        if exprs.len() == 1 && types.len() == 1 {
-            return None;
-        }
-        self.syntax().children().find_map(Type::cast)
+        // A path can be either a type or an expression, so only a colon marks the type.
+        self.syntax()
+            .children_with_tokens()
+            .any(|element| element.kind() == SyntaxKind::Colon)
+            .then(|| self.syntax().children().find_map(Type::cast))
+            .flatten()
     }
 
     pub fn expr(&self) -> Option<Expr> {
@@ -321,6 +448,31 @@ impl LetStmt {
     }
 }
 
+impl TuplePattern {
+    pub fn names(&self) -> Vec<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+            .collect()
+    }
+}
+
+impl StructPattern {
+    pub fn path(&self) -> Option<Path> {
+        self.syntax().children().find_map(Path::cast)
+    }
+
+    /// The fields which are bound, each to a variable with the same name.
+    pub fn fields(&self) -> Vec<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .filter(|token| token.kind() == SyntaxKind::Ident)
+            .collect()
+    }
+}
+
 impl IfStmt {
     pub fn condition(&self) -> Option<Expr> {
         self.syntax().children().find_map(Expr::cast)
@@ -347,6 +499,24 @@ impl AssertStmt {
     pub fn expr(&self) -> Option<Expr> {
         self.syntax().children().find_map(Expr::cast)
     }
+
+    /// The value which is raised if the condition is false.
+    pub fn payload(&self) -> Option<Expr> {
+        self.syntax().children().filter_map(Expr::cast).nth(1)
+    }
+}
+
+impl BreakStmt {
+    pub fn label(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Label)
+    }
+
+    pub fn expr(&self) -> Option<Expr> {
+        self.syntax().children().find_map(Expr::cast)
+    }
 }
 
 impl InitializerExpr {
@@ -521,12 +691,9 @@ impl ListItem {
 }
 
 impl PairExpr {
-    pub fn first(&self) -> Option<Expr> {
-        self.syntax().children().find_map(Expr::cast)
-    }
-
-    pub fn rest(&self) -> Option<Expr> {
-        self.syntax().children().filter_map(Expr::cast).last()
+    /// The items of the tuple, where the last one is the rest of the innermost pair.
+    pub fn items(&self) -> Vec<Expr> {
+        self.syntax().children().filter_map(Expr::cast).collect()
     }
 }
 
@@ -539,11 +706,16 @@ impl LambdaExpr {
     }
 
     pub fn ty(&self) -> Option<Type> {
-        self.syntax().children().find_map(Type::cast)
+        // A body which is a path could also be a type, so only a colon marks the return type.
+        self.syntax()
+            .children_with_tokens()
+            .any(|element| element.kind() == SyntaxKind::Colon)
+            .then(|| self.syntax().children().find_map(Type::cast))
+            .flatten()
     }
 
     pub fn body(&self) -> Option<Expr> {
-        self.syntax().children().find_map(Expr::cast)
+        self.syntax().children().filter_map(Expr::cast).last()
     }
 }
 
@@ -581,6 +753,67 @@ impl IfExpr {
     }
 }
 
+impl LabeledBlock {
+    pub fn label(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Label)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        self.syntax().children().find_map(Block::cast)
+    }
+}
+
+impl MatchExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.syntax().children().find_map(Expr::cast)
+    }
+
+    pub fn arms(&self) -> Vec<MatchArm> {
+        self.syntax()
+            .children()
+            .filter_map(MatchArm::cast)
+            .collect()
+    }
+}
+
+impl MatchArm {
+    pub fn pattern(&self) -> Option<VariantPattern> {
+        self.syntax().children().find_map(VariantPattern::cast)
+    }
+
+    pub fn expr(&self) -> Option<Expr> {
+        self.syntax().children().find_map(Expr::cast)
+    }
+}
+
+impl VariantPattern {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn bindings(&self) -> Option<Vec<SyntaxToken>> {
+        let mut tokens = self
+            .syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .skip_while(|token| token.kind() != SyntaxKind::OpenParen);
+
+        tokens.next()?;
+
+        Some(
+            tokens
+                .filter(|token| token.kind() == SyntaxKind::Ident)
+                .collect(),
+        )
+    }
+}
+
 impl FunctionCall {
     pub fn callee(&self) -> Option<Expr> {
         self.syntax().children().find_map(Expr::cast)
@@ -616,7 +849,7 @@ impl FieldAccess {
         self.syntax()
             .children_with_tokens()
             .filter_map(SyntaxElement::into_token)
-            .find(|token| matches!(token.kind(), SyntaxKind::Ident))
+            .find(|token| matches!(token.kind(), SyntaxKind::Ident | SyntaxKind::Int))
     }
 }
 
@@ -631,12 +864,25 @@ impl IndexAccess {
             .filter_map(SyntaxElement::into_token)
             .find(|token| token.kind() == SyntaxKind::Int)
     }
+
+    /// The index when it's an expression rather than an integer, which is read at runtime.
+    pub fn index_expr(&self) -> Option<Expr> {
+        self.syntax().children().filter_map(Expr::cast).nth(1)
+    }
 }
 
 impl ListType {
     pub fn ty(&self) -> Option<Type> {
         self.syntax().children().find_map(Type::cast)
     }
+
+    /// The number of items in a fixed size list type, such as the `3` in `Int[3]`.
+    pub fn len(&self) -> Option<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Int)
+    }
 }
 
 impl ListTypeItem {
@@ -652,13 +898,21 @@ impl ListTypeItem {
     }
 }
 
-impl PairType {
-    pub fn first(&self) -> Option<Type> {
-        self.syntax().children().find_map(Type::cast)
+impl UnionType {
+    pub fn items(&self) -> Vec<Type> {
+        self.syntax().children().filter_map(Type::cast).collect()
     }
+}
 
-    pub fn rest(&self) -> Option<Type> {
-        self.syntax().children().filter_map(Type::cast).last()
+impl TupleType {
+    pub fn items(&self) -> Vec<Type> {
+        self.syntax().children().filter_map(Type::cast).collect()
+    }
+}
+
+impl GroupType {
+    pub fn ty(&self) -> Option<Type> {
+        self.syntax().children().find_map(Type::cast)
     }
 }
 
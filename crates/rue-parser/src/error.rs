@@ -40,6 +40,12 @@ pub enum ParserErrorKind {
 
     #[error("unterminated block comment")]
     UnterminatedBlockComment,
+
+    #[error("nesting is too deep")]
+    NestingTooDeep,
+
+    #[error("invalid UTF-8")]
+    InvalidUtf8,
 }
 
 /// Join a list of syntax kinds into a string, wrapped in backticks.
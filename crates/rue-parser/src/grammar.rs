@@ -1,3 +1,5 @@
+use rowan::Checkpoint;
+
 use crate::{parser::Parser, BinaryOp, SyntaxKind};
 
 pub fn root(p: &mut Parser) {
@@ -8,33 +10,108 @@ pub fn root(p: &mut Parser) {
     p.finish();
 }
 
+const ITEM_RECOVERY_SET: &[SyntaxKind] = &[
+    SyntaxKind::Fun,
+    SyntaxKind::Type,
+    SyntaxKind::Struct,
+    SyntaxKind::Enum,
+    SyntaxKind::Const,
+    SyntaxKind::StaticAssert,
+    SyntaxKind::Impl,
+    SyntaxKind::At,
+    SyntaxKind::Pub,
+];
+
+/// The items which can be exported from their file with `pub`.
+const PUB_ITEM_SET: &[SyntaxKind] = &[
+    SyntaxKind::Fun,
+    SyntaxKind::Type,
+    SyntaxKind::Struct,
+    SyntaxKind::Enum,
+    SyntaxKind::Const,
+];
+
 fn item(p: &mut Parser) {
+    let cp = p.checkpoint();
+
+    while p.at(SyntaxKind::At) {
+        attribute(p);
+    }
+
+    if p.try_eat(SyntaxKind::Pub) && !PUB_ITEM_SET.iter().any(|&kind| p.at(kind)) {
+        p.error(ITEM_RECOVERY_SET);
+        p.skip_until(ITEM_RECOVERY_SET);
+        return;
+    }
+
     if p.at(SyntaxKind::Fun) {
-        function_item(p);
+        function_item(p, cp);
     } else if p.at(SyntaxKind::Type) {
-        type_alias_item(p);
+        type_alias_item(p, cp);
     } else if p.at(SyntaxKind::Struct) {
-        struct_item(p);
+        struct_item(p, cp);
     } else if p.at(SyntaxKind::Enum) {
-        enum_item(p);
+        enum_item(p, cp);
     } else if p.at(SyntaxKind::Const) {
-        const_item(p);
+        const_item(p, cp);
+    } else if p.at(SyntaxKind::StaticAssert) {
+        static_assert_item(p, cp);
+    } else if p.at(SyntaxKind::Impl) {
+        impl_item(p, cp);
+    } else if p.at_contextual("test") {
+        test_item(p, cp);
     } else {
-        p.error(&[]);
+        p.error(ITEM_RECOVERY_SET);
+        p.skip_until(ITEM_RECOVERY_SET);
     }
 }
 
-fn function_item(p: &mut Parser) {
-    p.start(SyntaxKind::FunctionItem);
+fn attribute(p: &mut Parser) {
+    p.start(SyntaxKind::Attribute);
+    p.expect(SyntaxKind::At);
+    p.expect(SyntaxKind::Ident);
+    if p.try_eat(SyntaxKind::OpenParen) {
+        while !p.at(SyntaxKind::CloseParen) {
+            if !p.try_eat(SyntaxKind::Ident)
+                && !p.try_eat(SyntaxKind::String)
+                && !p.try_eat(SyntaxKind::Int)
+            {
+                p.error(&[SyntaxKind::CloseParen]);
+                break;
+            }
+            if !p.try_eat(SyntaxKind::Comma) {
+                break;
+            }
+        }
+        p.expect(SyntaxKind::CloseParen);
+    }
+    p.finish();
+}
+
+fn function_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::FunctionItem);
     p.expect(SyntaxKind::Fun);
     p.expect(SyntaxKind::Ident);
     function_params(p);
     p.expect(SyntaxKind::Arrow);
     ty(p);
+    if p.at(SyntaxKind::Where) {
+        where_clause(p);
+    }
     block(p);
     p.finish();
 }
 
+fn where_clause(p: &mut Parser) {
+    p.start(SyntaxKind::WhereClause);
+    p.expect(SyntaxKind::Where);
+    expr_no_initializer(p);
+    while p.try_eat(SyntaxKind::Comma) {
+        expr_no_initializer(p);
+    }
+    p.finish();
+}
+
 fn function_params(p: &mut Parser) {
     p.expect(SyntaxKind::OpenParen);
     while !p.at(SyntaxKind::CloseParen) {
@@ -49,14 +126,18 @@ fn function_params(p: &mut Parser) {
 fn function_param(p: &mut Parser) {
     p.start(SyntaxKind::FunctionParam);
     p.try_eat(SyntaxKind::Spread);
+    // The `self` parameter of a method takes its type from the `impl` block.
+    let is_self = p.at_contextual("self");
     p.expect(SyntaxKind::Ident);
-    p.expect(SyntaxKind::Colon);
-    ty(p);
+    if !is_self || p.at(SyntaxKind::Colon) {
+        p.expect(SyntaxKind::Colon);
+        ty(p);
+    }
     p.finish();
 }
 
-fn type_alias_item(p: &mut Parser) {
-    p.start(SyntaxKind::TypeAliasItem);
+fn type_alias_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::TypeAliasItem);
     p.expect(SyntaxKind::Type);
     p.expect(SyntaxKind::Ident);
     p.expect(SyntaxKind::Assign);
@@ -65,8 +146,8 @@ fn type_alias_item(p: &mut Parser) {
     p.finish();
 }
 
-fn struct_item(p: &mut Parser) {
-    p.start(SyntaxKind::StructItem);
+fn struct_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::StructItem);
     p.expect(SyntaxKind::Struct);
     p.expect(SyntaxKind::Ident);
     p.expect(SyntaxKind::OpenBrace);
@@ -88,8 +169,8 @@ fn struct_field(p: &mut Parser) {
     p.finish();
 }
 
-fn enum_item(p: &mut Parser) {
-    p.start(SyntaxKind::EnumItem);
+fn enum_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::EnumItem);
     p.expect(SyntaxKind::Enum);
     p.expect(SyntaxKind::Ident);
     p.expect(SyntaxKind::OpenBrace);
@@ -120,8 +201,8 @@ fn enum_variant(p: &mut Parser) {
     p.finish();
 }
 
-fn const_item(p: &mut Parser) {
-    p.start(SyntaxKind::ConstItem);
+fn const_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::ConstItem);
     p.expect(SyntaxKind::Const);
     p.expect(SyntaxKind::Ident);
     p.expect(SyntaxKind::Colon);
@@ -132,11 +213,60 @@ fn const_item(p: &mut Parser) {
     p.finish();
 }
 
+fn static_assert_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::StaticAssertItem);
+    p.expect(SyntaxKind::StaticAssert);
+    p.expect(SyntaxKind::OpenParen);
+    expr(p);
+    p.expect(SyntaxKind::Comma);
+    p.expect(SyntaxKind::String);
+    p.expect(SyntaxKind::CloseParen);
+    p.expect(SyntaxKind::Semicolon);
+    p.finish();
+}
+
+fn impl_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::ImplItem);
+    p.expect(SyntaxKind::Impl);
+    p.expect(SyntaxKind::Ident);
+    p.expect(SyntaxKind::OpenBrace);
+    while !p.at(SyntaxKind::CloseBrace) && !p.at(SyntaxKind::Eof) {
+        let cp = p.checkpoint();
+        while p.at(SyntaxKind::At) {
+            attribute(p);
+        }
+        if p.at(SyntaxKind::Fun) {
+            function_item(p, cp);
+        } else {
+            p.error(&[SyntaxKind::CloseBrace]);
+            p.skip_until(&[SyntaxKind::Fun, SyntaxKind::At, SyntaxKind::CloseBrace]);
+        }
+    }
+    p.expect(SyntaxKind::CloseBrace);
+    p.finish();
+}
+
+/// A test block, such as `test "adds" { ... }`. The `test` keyword is contextual, since it's only
+/// special where an item is expected.
+fn test_item(p: &mut Parser, cp: Checkpoint) {
+    p.start_at(cp, SyntaxKind::TestItem);
+    p.expect(SyntaxKind::Ident);
+    p.expect(SyntaxKind::String);
+    block(p);
+    p.finish();
+}
+
 fn block(p: &mut Parser) {
+    p.nested(block_contents);
+}
+
+fn block_contents(p: &mut Parser) {
     p.start(SyntaxKind::Block);
     p.expect(SyntaxKind::OpenBrace);
     while !p.at(SyntaxKind::CloseBrace) && !p.at(SyntaxKind::Eof) {
-        if p.at(SyntaxKind::Let) {
+        if p.try_eat(SyntaxKind::Semicolon) {
+            // Stray semicolons are empty statements, which are skipped.
+        } else if p.at(SyntaxKind::Let) {
             let_stmt(p);
         } else if p.at(SyntaxKind::Return) {
             return_stmt(p);
@@ -148,7 +278,13 @@ fn block(p: &mut Parser) {
             }
         } else if p.at(SyntaxKind::Assert) {
             assert_stmt(p);
-        } else if p.at(SyntaxKind::Fun) || p.at(SyntaxKind::Type) || p.at(SyntaxKind::Const) {
+        } else if p.at(SyntaxKind::Break) {
+            break_stmt(p);
+        } else if p.at(SyntaxKind::Fun)
+            || p.at(SyntaxKind::Type)
+            || p.at(SyntaxKind::Const)
+            || p.at(SyntaxKind::At)
+        {
             item(p);
         } else {
             expr(p);
@@ -162,7 +298,13 @@ fn block(p: &mut Parser) {
 fn let_stmt(p: &mut Parser) {
     p.start(SyntaxKind::LetStmt);
     p.expect(SyntaxKind::Let);
-    p.expect(SyntaxKind::Ident);
+    if p.at(SyntaxKind::OpenParen) {
+        tuple_pattern(p);
+    } else if p.peek_at(SyntaxKind::OpenBrace) || p.peek_at(SyntaxKind::PathSeparator) {
+        struct_pattern(p);
+    } else {
+        p.expect(SyntaxKind::Ident);
+    }
     if p.try_eat(SyntaxKind::Colon) {
         ty(p);
     }
@@ -172,6 +314,33 @@ fn let_stmt(p: &mut Parser) {
     p.finish();
 }
 
+fn tuple_pattern(p: &mut Parser) {
+    p.start(SyntaxKind::TuplePattern);
+    p.expect(SyntaxKind::OpenParen);
+    while !p.at(SyntaxKind::CloseParen) {
+        p.expect(SyntaxKind::Ident);
+        if !p.try_eat(SyntaxKind::Comma) {
+            break;
+        }
+    }
+    p.expect(SyntaxKind::CloseParen);
+    p.finish();
+}
+
+fn struct_pattern(p: &mut Parser) {
+    p.start(SyntaxKind::StructPattern);
+    path(p);
+    p.expect(SyntaxKind::OpenBrace);
+    while !p.at(SyntaxKind::CloseBrace) {
+        p.expect(SyntaxKind::Ident);
+        if !p.try_eat(SyntaxKind::Comma) {
+            break;
+        }
+    }
+    p.expect(SyntaxKind::CloseBrace);
+    p.finish();
+}
+
 fn if_stmt_maybe_else(p: &mut Parser, expr_only: bool) -> bool {
     let cp = p.checkpoint();
     p.expect(SyntaxKind::If);
@@ -181,7 +350,14 @@ fn if_stmt_maybe_else(p: &mut Parser, expr_only: bool) -> bool {
     if expr_only || p.at(SyntaxKind::Else) {
         p.start_at(cp, SyntaxKind::IfExpr);
         p.expect(SyntaxKind::Else);
-        block(p);
+        if p.at(SyntaxKind::If) {
+            // An `else if` is a block without braces, whose value is the nested `if` expression.
+            p.start(SyntaxKind::Block);
+            if_stmt_maybe_else(p, true);
+            p.finish();
+        } else {
+            block(p);
+        }
         has_else = true;
     } else {
         p.start_at(cp, SyntaxKind::IfStmt);
@@ -190,6 +366,44 @@ fn if_stmt_maybe_else(p: &mut Parser, expr_only: bool) -> bool {
     has_else
 }
 
+fn match_expr(p: &mut Parser) {
+    p.start(SyntaxKind::MatchExpr);
+    p.expect(SyntaxKind::Match);
+    expr_no_initializer(p);
+    p.expect(SyntaxKind::OpenBrace);
+    while !p.at(SyntaxKind::CloseBrace) {
+        match_arm(p);
+        if !p.try_eat(SyntaxKind::Comma) {
+            break;
+        }
+    }
+    p.expect(SyntaxKind::CloseBrace);
+    p.finish();
+}
+
+fn match_arm(p: &mut Parser) {
+    p.start(SyntaxKind::MatchArm);
+    variant_pattern(p);
+    p.expect(SyntaxKind::FatArrow);
+    expr(p);
+    p.finish();
+}
+
+fn variant_pattern(p: &mut Parser) {
+    p.start(SyntaxKind::VariantPattern);
+    p.expect(SyntaxKind::Ident);
+    if p.try_eat(SyntaxKind::OpenParen) {
+        while !p.at(SyntaxKind::CloseParen) {
+            p.expect(SyntaxKind::Ident);
+            if !p.try_eat(SyntaxKind::Comma) {
+                break;
+            }
+        }
+        p.expect(SyntaxKind::CloseParen);
+    }
+    p.finish();
+}
+
 fn return_stmt(p: &mut Parser) {
     p.start(SyntaxKind::ReturnStmt);
     p.expect(SyntaxKind::Return);
@@ -212,10 +426,32 @@ fn assert_stmt(p: &mut Parser) {
     p.start(SyntaxKind::AssertStmt);
     p.expect(SyntaxKind::Assert);
     expr(p);
+    if p.try_eat(SyntaxKind::Comma) {
+        expr(p);
+    }
     p.expect(SyntaxKind::Semicolon);
     p.finish();
 }
 
+fn break_stmt(p: &mut Parser) {
+    p.start(SyntaxKind::BreakStmt);
+    p.expect(SyntaxKind::Break);
+    p.expect(SyntaxKind::Label);
+    if !p.try_eat(SyntaxKind::Semicolon) {
+        expr(p);
+        p.expect(SyntaxKind::Semicolon);
+    }
+    p.finish();
+}
+
+fn labeled_block(p: &mut Parser) {
+    p.start(SyntaxKind::LabeledBlock);
+    p.expect(SyntaxKind::Label);
+    p.expect(SyntaxKind::Colon);
+    block(p);
+    p.finish();
+}
+
 fn path(p: &mut Parser) {
     p.start(SyntaxKind::Path);
     p.expect(SyntaxKind::Ident);
@@ -241,10 +477,23 @@ fn binding_power(op: BinaryOp) -> (u8, u8) {
 const EXPR_RECOVERY_SET: &[SyntaxKind] = &[SyntaxKind::OpenBrace, SyntaxKind::CloseBrace];
 
 fn expr(p: &mut Parser) {
+    let previous = p.set_initializers_allowed(true);
     expr_binding_power(p, 0);
+    p.set_initializers_allowed(previous);
+}
+
+/// Parses an expression that is followed by a block, where `name {` can't be an initializer.
+fn expr_no_initializer(p: &mut Parser) {
+    let previous = p.set_initializers_allowed(false);
+    expr_binding_power(p, 0);
+    p.set_initializers_allowed(previous);
 }
 
 fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
+    p.nested(|p| expr_binding_power_contents(p, minimum_binding_power));
+}
+
+fn expr_binding_power_contents(p: &mut Parser, minimum_binding_power: u8) {
     if p.at(SyntaxKind::Not) {
         p.start(SyntaxKind::PrefixExpr);
         p.bump();
@@ -256,6 +505,7 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
     let checkpoint = p.checkpoint();
 
     if p.at(SyntaxKind::Int)
+        || p.at(SyntaxKind::Hex)
         || p.at(SyntaxKind::String)
         || p.at(SyntaxKind::True)
         || p.at(SyntaxKind::False)
@@ -267,7 +517,7 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
     } else if p.at(SyntaxKind::Ident) {
         path(p);
 
-        if p.at(SyntaxKind::OpenBrace) {
+        if p.at(SyntaxKind::OpenBrace) && p.initializers_allowed() {
             p.start_at(checkpoint, SyntaxKind::InitializerExpr);
             p.bump();
             while !p.at(SyntaxKind::CloseBrace) {
@@ -288,12 +538,18 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
         list_expr(p);
     } else if p.at(SyntaxKind::OpenBrace) {
         block(p);
+    } else if p.at(SyntaxKind::Label) {
+        labeled_block(p);
     } else if p.at(SyntaxKind::OpenParen) {
         p.bump();
         expr(p);
         if p.try_eat(SyntaxKind::Comma) {
+            // Tuples with more than two items are nested pairs, like tuple types.
             p.start_at(checkpoint, SyntaxKind::PairExpr);
             expr(p);
+            while p.try_eat(SyntaxKind::Comma) {
+                expr(p);
+            }
         } else {
             p.start_at(checkpoint, SyntaxKind::GroupExpr);
         }
@@ -301,6 +557,8 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
         p.finish();
     } else if p.at(SyntaxKind::If) {
         if_stmt_maybe_else(p, true);
+    } else if p.at(SyntaxKind::Match) {
+        match_expr(p);
     } else if p.at(SyntaxKind::Fun) {
         lambda_expr(p);
     } else {
@@ -322,12 +580,20 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) {
         } else if p.at(SyntaxKind::Dot) {
             p.start_at(checkpoint, SyntaxKind::FieldAccess);
             p.bump();
-            p.expect(SyntaxKind::Ident);
+            // Tuple items are accessed by their index, such as `.0`.
+            if !p.try_eat(SyntaxKind::Int) {
+                p.expect(SyntaxKind::Ident);
+            }
             p.finish();
         } else if p.at(SyntaxKind::OpenBracket) {
             p.start_at(checkpoint, SyntaxKind::IndexAccess);
             p.bump();
-            p.expect(SyntaxKind::Int);
+            // An integer on its own is a constant index, and anything else is an expression.
+            if p.at(SyntaxKind::Int) && p.peek_at(SyntaxKind::CloseBracket) {
+                p.bump();
+            } else {
+                expr(p);
+            }
             p.expect(SyntaxKind::CloseBracket);
             p.finish();
         } else if p.at(SyntaxKind::As) {
@@ -441,6 +707,25 @@ fn lambda_param(p: &mut Parser) {
 const TYPE_RECOVERY_SET: &[SyntaxKind] = &[SyntaxKind::OpenBrace, SyntaxKind::CloseBrace];
 
 fn ty(p: &mut Parser) {
+    p.nested(ty_contents);
+}
+
+fn ty_contents(p: &mut Parser) {
+    let checkpoint = p.checkpoint();
+
+    union_member(p);
+
+    if p.at(SyntaxKind::Pipe) {
+        p.start_at(checkpoint, SyntaxKind::UnionType);
+        while p.try_eat(SyntaxKind::Pipe) {
+            p.nested(union_member);
+        }
+        p.finish();
+    }
+}
+
+/// A type which isn't a union, which can be one of the types in a union.
+fn union_member(p: &mut Parser) {
     let checkpoint = p.checkpoint();
 
     if p.at(SyntaxKind::Ident) {
@@ -460,12 +745,19 @@ fn ty(p: &mut Parser) {
         ty(p);
         p.finish();
     } else if p.at(SyntaxKind::OpenParen) {
-        p.start(SyntaxKind::PairType);
         p.bump();
         ty(p);
-        p.expect(SyntaxKind::Comma);
-        ty(p);
-        p.try_eat(SyntaxKind::Comma);
+        if p.try_eat(SyntaxKind::Comma) {
+            p.start_at(checkpoint, SyntaxKind::TupleType);
+            while !p.at(SyntaxKind::CloseParen) {
+                ty(p);
+                if !p.try_eat(SyntaxKind::Comma) {
+                    break;
+                }
+            }
+        } else {
+            p.start_at(checkpoint, SyntaxKind::GroupType);
+        }
         p.expect(SyntaxKind::CloseParen);
         p.finish();
     } else {
@@ -475,6 +767,7 @@ fn ty(p: &mut Parser) {
     while p.at(SyntaxKind::OpenBracket) {
         p.start_at(checkpoint, SyntaxKind::ListType);
         p.bump();
+        p.try_eat(SyntaxKind::Int);
         p.expect(SyntaxKind::CloseBracket);
         p.finish();
     }
@@ -19,6 +19,39 @@ pub fn parse(source: &str) -> (Root, Vec<ParserError>) {
     (Root::cast(ast).unwrap(), errors)
 }
 
+/// Parses source which may not be valid UTF-8, such as a file read as raw bytes. Each invalid
+/// sequence is replaced with `U+FFFD` and reported as an error, so spans refer to the source with
+/// those replacements, which is the text of the returned tree.
+pub fn parse_bytes(source: &[u8]) -> (Root, Vec<ParserError>) {
+    let mut text = String::new();
+    let mut invalid = Vec::new();
+
+    for chunk in source.utf8_chunks() {
+        text.push_str(chunk.valid());
+
+        if !chunk.invalid().is_empty() {
+            invalid.push(text.len()..text.len() + char::REPLACEMENT_CHARACTER.len_utf8());
+            text.push(char::REPLACEMENT_CHARACTER);
+        }
+    }
+
+    let (root, parser_errors) = parse(&text);
+
+    let mut errors: Vec<ParserError> = invalid
+        .iter()
+        .map(|span| ParserError::new(ParserErrorKind::InvalidUtf8, span.clone()))
+        .collect();
+
+    // Replacement characters outside of strings and comments are also unknown tokens, but they're
+    // already reported as invalid UTF-8.
+    errors.extend(parser_errors.into_iter().filter(|error| {
+        !matches!(error.kind(), ParserErrorKind::UnknownToken(..))
+            || !invalid.contains(error.span())
+    }));
+
+    (root, errors)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LineCol {
     pub line: usize,
@@ -46,3 +79,195 @@ pub fn line_col(source: &str, index: usize) -> LineCol {
 
     LineCol { line, col }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_kinds(errors: &[ParserError]) -> Vec<ParserErrorKind> {
+        errors.iter().map(|error| error.kind().clone()).collect()
+    }
+
+    fn nesting_errors(errors: &[ParserError]) -> usize {
+        errors
+            .iter()
+            .filter(|error| error.kind() == &ParserErrorKind::NestingTooDeep)
+            .count()
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        // Each of these overflowed the stack before nesting was limited.
+        for prefix in [
+            "(",
+            "[",
+            "{",
+            "!",
+            "fun main() -> Int { if x { ",
+            "fun(",
+            "'a: {",
+        ] {
+            let source = format!("fun main() -> Int {{ {} }}", prefix.repeat(100_000));
+            let (_, errors) = parse(&source);
+            assert_eq!(nesting_errors(&errors), 1);
+        }
+
+        for prefix in ["(", "fun("] {
+            let source = format!("type T = {};", prefix.repeat(100_000));
+            let (_, errors) = parse(&source);
+            assert_eq!(error_kinds(&errors), [ParserErrorKind::NestingTooDeep]);
+        }
+
+        let source = format!(
+            "fun main() -> Int {{ {}1{} }}",
+            "(".repeat(100),
+            ")".repeat(100)
+        );
+        let (_, errors) = parse(&source);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_comments_in_lists() {
+        let source = "
+            fun add(
+                a: Int, // The first value.
+                /* The second value. */ b: Int // No trailing comma.
+            ) -> Int {
+                a + b
+            }
+
+            fun main() -> Int {
+                let f: fun(Int /* value */, Int) -> Int = add;
+                add(
+                    1, // One.
+                    f(2, /* three */ 3) /* Five. */
+                )
+            }
+        ";
+
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let Some(Item::FunctionItem(add)) = root.items().into_iter().next() else {
+            panic!("expected a function");
+        };
+        assert_eq!(add.params().len(), 2);
+
+        let call = root
+            .syntax()
+            .descendants()
+            .find_map(FunctionCall::cast)
+            .unwrap();
+        assert_eq!(call.args().len(), 2);
+    }
+
+    #[test]
+    fn test_left_associativity() {
+        // Operators of the same precedence group to the left, which matters for `-`, `/` and `%`.
+        for (source, lhs, rhs) in [
+            ("10 - 3 - 2", "10 - 3", "2"),
+            ("16 / 4 / 2", "16 / 4", "2"),
+            ("100 % 7 % 3", "100 % 7", "3"),
+            ("10 - 3 + 2", "10 - 3", "2"),
+            ("16 / 4 * 2", "16 / 4", "2"),
+        ] {
+            let (root, errors) = parse(&format!("fun main() -> Int {{ {source} }}"));
+            assert!(errors.is_empty(), "{errors:?}");
+
+            let binary = root
+                .syntax()
+                .descendants()
+                .find_map(BinaryExpr::cast)
+                .unwrap();
+
+            let text =
+                |expr: Option<Expr>| expr.unwrap().syntax().text().to_string().trim().to_string();
+            assert_eq!(text(binary.lhs()), lhs, "{source}");
+            assert_eq!(text(binary.rhs()), rhs, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_pub_items() {
+        let source = "
+            pub fun square(value: Int) -> Int { value * value }
+            @cfg(debug) pub const LIMIT: Int = 12;
+            fun main() -> Int { square(LIMIT) }
+        ";
+
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let exported: Vec<bool> = root.items().iter().map(Item::is_pub).collect();
+        assert_eq!(exported, [true, true, false]);
+
+        // Only items which can be referenced by name can be exported.
+        let (root, errors) = parse("pub impl Point {} fun main() -> Int { 0 }");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(root.items().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let (root, errors) = parse_bytes(b"fun main() -> Int { \xff }");
+        assert_eq!(
+            error_kinds(&errors)[0],
+            ParserErrorKind::InvalidUtf8,
+            "{errors:?}"
+        );
+        assert_eq!(errors[0].span(), &(20..23));
+        assert_eq!(
+            root.syntax().text().to_string(),
+            "fun main() -> Int { \u{fffd} }"
+        );
+
+        // Invalid bytes inside of a string are still reported.
+        let (_, errors) = parse_bytes(b"fun main() -> Bytes { \"\xc3\" }");
+        assert_eq!(error_kinds(&errors), [ParserErrorKind::InvalidUtf8]);
+
+        let (_, errors) = parse_bytes(b"fun main() -> Int { 42 }");
+        assert!(errors.is_empty());
+    }
+
+    /// A small version of the fuzz target, which runs with the rest of the tests.
+    #[test]
+    fn test_arbitrary_bytes() {
+        let fragments: [&[u8]; 16] = [
+            b"fun",
+            b"(",
+            b")",
+            b"{",
+            b"}",
+            b"[",
+            b"->",
+            b"'a",
+            b":",
+            b"\"",
+            b"/*",
+            b"0x",
+            b"1",
+            b" ",
+            b"\xe2\x82",
+            b"\xff",
+        ];
+        let mut seed: u64 = 42;
+
+        for _ in 0..10_000 {
+            let mut source = Vec::new();
+
+            for _ in 0..seed % 32 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                source.extend_from_slice(fragments[(seed >> 60) as usize]);
+            }
+
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let (root, errors) = parse_bytes(&source);
+            let text = root.syntax().text().to_string();
+
+            for error in errors {
+                assert!(text.get(error.span().clone()).is_some());
+            }
+        }
+    }
+}
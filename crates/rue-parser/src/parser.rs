@@ -4,6 +4,10 @@ use rue_lexer::{Token, TokenKind};
 
 use crate::{ParserError, ParserErrorKind, RueLang, SyntaxKind, SyntaxNode};
 
+/// How deeply expressions, types, and blocks can be nested. Each level of the recursive descent
+/// uses some of the stack, so this keeps arbitrarily nested input from overflowing it.
+const MAX_DEPTH: usize = 256;
+
 pub struct Parser<'a> {
     items: Vec<(SyntaxKind, &'a str)>,
     cursor: usize,
@@ -11,6 +15,9 @@ pub struct Parser<'a> {
     builder: GreenNodeBuilder<'static>,
     errors: Vec<ParserError>,
     expected_kinds: IndexSet<SyntaxKind>,
+    initializers_allowed: bool,
+    depth: usize,
+    too_deep: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -25,6 +32,9 @@ impl<'a> Parser<'a> {
             builder: GreenNodeBuilder::new(),
             errors,
             expected_kinds: IndexSet::new(),
+            initializers_allowed: true,
+            depth: 0,
+            too_deep: false,
         }
     }
 
@@ -41,6 +51,41 @@ impl<'a> Parser<'a> {
             .start_node_at(checkpoint, RueLang::kind_to_raw(kind));
     }
 
+    pub fn initializers_allowed(&self) -> bool {
+        self.initializers_allowed
+    }
+
+    /// Sets whether a path followed by `{` starts an initializer, returning the previous setting.
+    pub fn set_initializers_allowed(&mut self, allowed: bool) -> bool {
+        std::mem::replace(&mut self.initializers_allowed, allowed)
+    }
+
+    /// Parses something which can contain itself, such as an expression. Once nesting is too deep,
+    /// the rest of the input is skipped instead, and reported as a single error.
+    pub fn nested(&mut self, parse: impl FnOnce(&mut Self)) {
+        if self.depth < MAX_DEPTH {
+            self.depth += 1;
+            parse(self);
+            self.depth -= 1;
+            return;
+        }
+
+        if !self.too_deep {
+            self.push_error(ParserErrorKind::NestingTooDeep);
+            self.too_deep = true;
+        }
+
+        if self.at_end() {
+            return;
+        }
+
+        self.start(SyntaxKind::Error);
+        while !self.at_end() {
+            self.token();
+        }
+        self.finish();
+    }
+
     pub fn checkpoint(&mut self) -> Checkpoint {
         self.builder.checkpoint()
     }
@@ -86,6 +131,23 @@ impl<'a> Parser<'a> {
         self.nth(0) == kind
     }
 
+    /// Whether the next token is an identifier with the given text, such as `self`.
+    pub fn at_contextual(&mut self, text: &str) -> bool {
+        self.nth(0) == SyntaxKind::Ident && self.items[self.cursor].1 == text
+    }
+
+    /// Whether the token after the next one is of the given kind, such as to tell a pattern apart
+    /// from a name before either is parsed.
+    pub fn peek_at(&mut self, kind: SyntaxKind) -> bool {
+        self.eat_whitespace();
+        self.items[self.cursor..]
+            .iter()
+            .map(|&(kind, _)| kind)
+            .filter(|&kind| !is_trivia(kind))
+            .nth(1)
+            == Some(kind)
+    }
+
     pub fn error(&mut self, set: &[SyntaxKind]) {
         let expected: Vec<SyntaxKind> = self.expected_kinds.drain(..).collect();
         let found = self.nth(0);
@@ -101,7 +163,28 @@ impl<'a> Parser<'a> {
         self.finish();
     }
 
+    /// Skips tokens until one in the set is found, wrapping them in a single error node.
+    /// This prevents one unexpected token from cascading into an error for every token after it.
+    pub fn skip_until(&mut self, set: &[SyntaxKind]) {
+        if self.at_end() || set.contains(&self.nth(0)) {
+            return;
+        }
+
+        self.start(SyntaxKind::Error);
+        while !self.at_end() && !set.contains(&self.nth(0)) {
+            self.token();
+        }
+        self.finish();
+
+        self.expected_kinds.clear();
+    }
+
     fn push_error(&mut self, error: ParserErrorKind) {
+        // Everything after nesting got too deep was skipped, so anything expected after it is too.
+        if self.too_deep {
+            return;
+        }
+
         if self.cursor == self.items.len() {
             self.errors
                 .push(ParserError::new(error, self.char_pos..self.char_pos));
@@ -121,13 +204,7 @@ impl<'a> Parser<'a> {
 
     fn eat_whitespace(&mut self) {
         while !self.at_end() {
-            if matches!(
-                self.items[self.cursor].0,
-                SyntaxKind::Whitespace
-                    | SyntaxKind::LineComment
-                    | SyntaxKind::BlockComment
-                    | SyntaxKind::Error
-            ) {
+            if is_trivia(self.items[self.cursor].0) {
                 self.token();
             } else {
                 break;
@@ -152,6 +229,16 @@ impl<'a> Parser<'a> {
     }
 }
 
+fn is_trivia(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Whitespace
+            | SyntaxKind::LineComment
+            | SyntaxKind::BlockComment
+            | SyntaxKind::Error
+    )
+}
+
 fn convert_tokens<'a>(
     errors: &mut Vec<ParserError>,
     source: &'a str,
@@ -165,6 +252,7 @@ fn convert_tokens<'a>(
         let kind = match token.kind() {
             TokenKind::Ident => SyntaxKind::Ident,
             TokenKind::Int => SyntaxKind::Int,
+            TokenKind::Hex => SyntaxKind::Hex,
             TokenKind::String { is_terminated } => {
                 if !is_terminated {
                     errors.push(ParserError::new(
@@ -174,6 +262,7 @@ fn convert_tokens<'a>(
                 }
                 SyntaxKind::String
             }
+            TokenKind::Label => SyntaxKind::Label,
 
             TokenKind::OpenParen => SyntaxKind::OpenParen,
             TokenKind::CloseParen => SyntaxKind::CloseParen,
@@ -198,6 +287,12 @@ fn convert_tokens<'a>(
             TokenKind::False => SyntaxKind::False,
             TokenKind::As => SyntaxKind::As,
             TokenKind::Is => SyntaxKind::Is,
+            TokenKind::StaticAssert => SyntaxKind::StaticAssert,
+            TokenKind::Match => SyntaxKind::Match,
+            TokenKind::Break => SyntaxKind::Break,
+            TokenKind::Impl => SyntaxKind::Impl,
+            TokenKind::Where => SyntaxKind::Where,
+            TokenKind::Pub => SyntaxKind::Pub,
 
             TokenKind::Dot => SyntaxKind::Dot,
             TokenKind::Comma => SyntaxKind::Comma,
@@ -207,6 +302,8 @@ fn convert_tokens<'a>(
             TokenKind::Arrow => SyntaxKind::Arrow,
             TokenKind::FatArrow => SyntaxKind::FatArrow,
             TokenKind::Spread => SyntaxKind::Spread,
+            TokenKind::At => SyntaxKind::At,
+            TokenKind::Pipe => SyntaxKind::Pipe,
 
             TokenKind::Plus => SyntaxKind::Plus,
             TokenKind::Minus => SyntaxKind::Minus,
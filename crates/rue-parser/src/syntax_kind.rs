@@ -12,7 +12,9 @@ pub enum SyntaxKind {
 
     Ident,
     Int,
+    Hex,
     String,
+    Label,
 
     OpenParen,
     CloseParen,
@@ -37,6 +39,12 @@ pub enum SyntaxKind {
     False,
     As,
     Is,
+    StaticAssert,
+    Match,
+    Break,
+    Impl,
+    Where,
+    Pub,
 
     Dot,
     Comma,
@@ -46,6 +54,8 @@ pub enum SyntaxKind {
     Arrow,
     FatArrow,
     Spread,
+    At,
+    Pipe,
 
     Plus,
     Minus,
@@ -74,12 +84,21 @@ pub enum SyntaxKind {
     EnumItem,
     EnumVariant,
     ConstItem,
+    StaticAssertItem,
+    ImplItem,
+    TestItem,
+    Attribute,
+    WhereClause,
 
     LetStmt,
+    TuplePattern,
+    StructPattern,
+    VariantPattern,
     IfStmt,
     ReturnStmt,
     RaiseStmt,
     AssertStmt,
+    BreakStmt,
 
     Block,
     Path,
@@ -98,6 +117,9 @@ pub enum SyntaxKind {
     CastExpr,
     GuardExpr,
     IfExpr,
+    MatchExpr,
+    LabeledBlock,
+    MatchArm,
     FunctionCall,
     FunctionCallArg,
     FieldAccess,
@@ -105,7 +127,9 @@ pub enum SyntaxKind {
 
     ListType,
     ListTypeItem,
-    PairType,
+    TupleType,
+    UnionType,
+    GroupType,
     FunctionType,
     FunctionTypeParam,
 }
@@ -121,7 +145,9 @@ impl fmt::Display for SyntaxKind {
 
                 SyntaxKind::Ident => "identifier",
                 SyntaxKind::Int => "integer",
+                SyntaxKind::Hex => "hex literal",
                 SyntaxKind::String => "string",
+                SyntaxKind::Label => "label",
 
                 SyntaxKind::OpenParen => "'('",
                 SyntaxKind::CloseParen => "')'",
@@ -146,6 +172,12 @@ impl fmt::Display for SyntaxKind {
                 SyntaxKind::False => "'false'",
                 SyntaxKind::As => "'as'",
                 SyntaxKind::Is => "'is'",
+                SyntaxKind::StaticAssert => "'static_assert'",
+                SyntaxKind::Match => "'match'",
+                SyntaxKind::Break => "'break'",
+                SyntaxKind::Impl => "'impl'",
+                SyntaxKind::Where => "'where'",
+                SyntaxKind::Pub => "'pub'",
 
                 SyntaxKind::Dot => "'.'",
                 SyntaxKind::Comma => "','",
@@ -155,6 +187,8 @@ impl fmt::Display for SyntaxKind {
                 SyntaxKind::Arrow => "'->'",
                 SyntaxKind::FatArrow => "'=>'",
                 SyntaxKind::Spread => "'...'",
+                SyntaxKind::At => "'@'",
+                SyntaxKind::Pipe => "'|'",
 
                 SyntaxKind::Plus => "'+'",
                 SyntaxKind::Minus => "'-'",
@@ -183,12 +217,21 @@ impl fmt::Display for SyntaxKind {
                 SyntaxKind::EnumItem => "enum item",
                 SyntaxKind::EnumVariant => "enum variant",
                 SyntaxKind::ConstItem => "const item",
+                SyntaxKind::StaticAssertItem => "static assert item",
+                SyntaxKind::ImplItem => "impl item",
+                SyntaxKind::TestItem => "test item",
+                SyntaxKind::Attribute => "attribute",
+                SyntaxKind::WhereClause => "where clause",
 
                 SyntaxKind::LetStmt => "let statement",
+                SyntaxKind::TuplePattern => "tuple pattern",
+                SyntaxKind::StructPattern => "struct pattern",
+                SyntaxKind::VariantPattern => "variant pattern",
                 SyntaxKind::IfStmt => "if statement",
                 SyntaxKind::ReturnStmt => "return statement",
                 SyntaxKind::RaiseStmt => "raise statement",
                 SyntaxKind::AssertStmt => "assert statement",
+                SyntaxKind::BreakStmt => "break statement",
 
                 SyntaxKind::Block => "block",
                 SyntaxKind::Path => "identifier path",
@@ -207,6 +250,9 @@ impl fmt::Display for SyntaxKind {
                 SyntaxKind::CastExpr => "cast expression",
                 SyntaxKind::GuardExpr => "guard expression",
                 SyntaxKind::IfExpr => "if expression",
+                SyntaxKind::MatchExpr => "match expression",
+                SyntaxKind::LabeledBlock => "labeled block",
+                SyntaxKind::MatchArm => "match arm",
                 SyntaxKind::FunctionCall => "function call",
                 SyntaxKind::FunctionCallArg => "function call argument",
                 SyntaxKind::FieldAccess => "field access",
@@ -214,7 +260,9 @@ impl fmt::Display for SyntaxKind {
 
                 SyntaxKind::ListType => "list type",
                 SyntaxKind::ListTypeItem => "list type item",
-                SyntaxKind::PairType => "pair type",
+                SyntaxKind::TupleType => "tuple type",
+                SyntaxKind::UnionType => "union type",
+                SyntaxKind::GroupType => "group type",
                 SyntaxKind::FunctionType => "function type",
                 SyntaxKind::FunctionTypeParam => "function type parameter",
             }
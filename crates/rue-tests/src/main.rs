@@ -10,7 +10,7 @@ use clvmr::{
     Allocator, ChiaDialect,
 };
 use indexmap::{IndexMap, IndexSet};
-use rue_compiler::compile;
+use rue_compiler::{compile, CompilerOptions, Target};
 use rue_parser::{line_col, LineCol};
 use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
@@ -31,6 +31,12 @@ struct TestCase {
     input: String,
     output: String,
     hash: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    jump_tables: bool,
 }
 
 #[derive(Clone)]
@@ -45,6 +51,35 @@ struct TestOutput {
 struct TestErrors {
     parser_errors: Vec<String>,
     compiler_errors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    jump_tables: bool,
+}
+
+impl ExpectedTestData {
+    fn defines(&self) -> &[String] {
+        match self {
+            Case(test_case) => &test_case.defines,
+            Errs(test_errors) => &test_errors.defines,
+        }
+    }
+
+    fn target(&self) -> Option<&str> {
+        match self {
+            Case(test_case) => test_case.target.as_deref(),
+            Errs(test_errors) => test_errors.target.as_deref(),
+        }
+    }
+
+    fn jump_tables(&self) -> bool {
+        match self {
+            Case(test_case) => test_case.jump_tables,
+            Errs(test_errors) => test_errors.jump_tables,
+        }
+    }
 }
 
 /// Automatically updates test cases.
@@ -67,10 +102,26 @@ fn iter_tests() -> impl Iterator<Item = PathBuf> {
         .map(|file| file.into_path())
 }
 
-fn run_test(source: &str, input: &str) -> Result<TestOutput, TestErrors> {
+fn run_test(
+    source: &str,
+    input: &str,
+    defines: &[String],
+    target: Option<&str>,
+    jump_tables: bool,
+) -> Result<TestOutput, TestErrors> {
     let (root, parser_errors) = rue_parser::parse(source);
+
+    let mut options = CompilerOptions::default();
+    for name in defines {
+        options.define(name.clone());
+    }
+    if let Some(name) = target {
+        options.set_target(Target::from_name(name).expect("unknown target"));
+    }
+    options.set_jump_tables(jump_tables);
+
     let mut allocator = Allocator::new();
-    let output = compile(&mut allocator, root, parser_errors.is_empty());
+    let output = compile(&mut allocator, root, parser_errors.is_empty(), &options);
 
     let parser_errors: Vec<String> = parser_errors
         .into_iter()
@@ -97,6 +148,9 @@ fn run_test(source: &str, input: &str) -> Result<TestOutput, TestErrors> {
         return Err(TestErrors {
             parser_errors,
             compiler_errors,
+            defines: defines.to_vec(),
+            target: target.map(str::to_string),
+            jump_tables,
         });
     }
 
@@ -125,7 +179,15 @@ fn run_test(source: &str, input: &str) -> Result<TestOutput, TestErrors> {
             let output = binutils::disassemble(&old_allocator, output_ptr, None);
             (cost, Ok(output))
         }
-        Err(error) => (0, Err(error.to_string())),
+        Err(error) => {
+            // The raised value is kept, so that tests can check which failure was observed.
+            let value_bytes = node_to_bytes(&allocator, error.0).unwrap();
+            let value_ptr =
+                clvmr_old::serde::node_from_bytes(&mut old_allocator, &value_bytes).unwrap();
+            let value = binutils::disassemble(&old_allocator, value_ptr, None);
+            // Only the message is kept, since the node in the error depends on the allocator.
+            (0, Err(format!("{}: {value}", error.1)))
+        }
     };
 
     Ok(TestOutput {
@@ -186,6 +248,19 @@ fn run_tests(update: bool) -> usize {
 
         let expected = test_cases.get(name).cloned();
 
+        let defines = expected
+            .as_ref()
+            .map(|expected| expected.defines().to_vec())
+            .unwrap_or_default();
+
+        let target = expected
+            .as_ref()
+            .and_then(|expected| expected.target().map(str::to_string));
+
+        let jump_tables = expected
+            .as_ref()
+            .is_some_and(|expected| expected.jump_tables());
+
         if expected.is_none() {
             lines.push("missing toml entry".to_string());
             failed = true;
@@ -200,6 +275,9 @@ fn run_tests(update: bool) -> usize {
                     _ => None,
                 })
                 .unwrap_or("()".to_string()),
+            &defines,
+            target.as_deref(),
+            jump_tables,
         );
 
         if let Some(expected) = expected.clone() {
@@ -287,9 +365,11 @@ fn run_tests(update: bool) -> usize {
                             }
                         }
                         Err(error) => {
-                            lines.push(format!("unexpected clvm error: {}", error));
-                            failed = true;
-                            output_failed = true;
+                            if &expected.output != error {
+                                lines.push(format!("unexpected clvm error: {}", error));
+                                failed = true;
+                                output_failed = true;
+                            }
                         }
                     }
 
@@ -313,8 +393,11 @@ fn run_tests(update: bool) -> usize {
                             _ => None,
                         })
                         .unwrap_or("()".to_string()),
-                    output: output.output.unwrap_or("()".to_string()),
+                    output: output.output.unwrap_or_else(|error| error),
                     hash: output.hash,
+                    defines,
+                    target,
+                    jump_tables,
                 }),
                 Err(errors) => Errs(errors),
             };
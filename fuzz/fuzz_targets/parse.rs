@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rue_parser::{parse_bytes, AstNode};
+
+fuzz_target!(|data: &[u8]| {
+    let (root, errors) = parse_bytes(data);
+
+    // Every span must be within the source that was parsed, since they're used to index it.
+    let source = root.syntax().text().to_string();
+
+    for error in errors {
+        assert!(error.span().start <= error.span().end);
+        assert!(source.get(error.span().clone()).is_some());
+    }
+});